@@ -56,6 +56,15 @@ for size in 16 24 32 48 64 128; do
     fi
 done
 
+# Install privileged helper and polkit policy (used by the kill switch)
+echo "Installing privileged helper (requires sudo)..."
+if sudo install -m 755 helper/openvpn3-gui-helper /usr/libexec/openvpn3-gui-helper \
+    && sudo install -m 644 helper/xyz.fonzi.openvpn3gui.policy /usr/share/polkit-1/actions/xyz.fonzi.openvpn3gui.policy; then
+    echo "Installed helper to /usr/libexec/openvpn3-gui-helper"
+else
+    echo "Skipped helper installation - the kill switch will not be available"
+fi
+
 # Update desktop database
 if command -v update-desktop-database &> /dev/null; then
     echo "Updating desktop database..."
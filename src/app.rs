@@ -4,40 +4,86 @@ use cosmic::iced::Subscription;
 use cosmic::app::{Task, Core};
 use cosmic::{Application, Element};
 use std::time::{Duration, Instant};
-use circular_queue::CircularQueue;
 use notify_rust::Notification;
 
 use crate::models::{ConnectionState, Message, NetworkStats};
+use crate::bundle::{collect_system_files, write_bundle, BundleFile};
+use crate::export::{default_export_dir, logs_as, pick_save_path, report_as, ExportFormat};
+use crate::history::{HistoryFilter, HistoryStore, SessionTracker};
+use crate::usage::{today, Quota, Usage, UsageStore};
+use crate::journal::Journal;
+use crate::log_files::{log_dir, LogFileWriter};
+use crate::logging::{LogBuffer, LogFilter, LogLevel, LogRecord, LogSource};
+use crate::redact::{write_redacted, Redactor};
+use crate::report::{Addresses, HealthSummary, LatencySummary, ProfileInfo, ReportLogLine, SessionReport, Timing, Traffic};
 use crate::vpn::{
     pick_file, start_vpn, stop_vpn_by_path, stop_vpn_by_config, 
     check_session_status, fetch_session_stats,
-    find_tunnel_ip, fetch_public_ip, submit_challenge
+    find_tunnel_ip, find_vpn_gateway, manage_session, submit_challenge
 };
-use crate::ui::{view_main, GRAPH_WINDOW};
+use crate::ui::view_main;
 use crate::tray::SystemTray;
-use crate::vpn::health::ping_latency;
+use crate::vpn::probes::{run_probe, ProbeEngine};
+use crate::vpn::diagnosis::{diagnose, diagnose_log, Diagnosis};
+use crate::vpn::error::VpnError;
+use crate::vpn::captive_portal::{check_portal, PortalCheck};
+use crate::vpn::health::{assess, check_dns, HealthInputs, HealthLevel, HealthReport};
+use crate::vpn::killswitch::{apply_kill_switch, build_rules, remove_kill_switch, KillSwitchRules};
+use crate::vpn::parser::{extract_tunnel_device, parse_remotes};
+use crate::vpn::public_ip::{lookup_public_ip, PublicIpLookup, PublicIpTracker};
+use crate::settings::{GraphWindow, Probe, Settings};
+use crate::profiles::ProfileStore;
+use crate::vpn::netns::{run_in_namespace, setup_namespace, teardown_namespace, NAMESPACE};
+use crate::vpn::netwatch::{netlink_events, sleep_events, NetworkEvent, NetworkTriggers, SleepAction, WakeAction};
+use crate::vpn::trust::{evaluate, gather_facts, NetworkFacts, RuleAction, RuleDecision};
+use crate::vpn::session_log::stream_session_log;
+use crate::vpn::snapshot::{capture, NetworkSnapshot};
+use crate::vpn::rates::{CounterSample, RateHistory, RateMeter, SampleOutcome};
+use crate::vpn::split_tunnel::{apply_split_tunnel, launch_app, remove_split_tunnel, SplitSide, SplitTunnelSettings};
 
 /// The main application state
 pub struct OpenVpnGui {
     pub core: Core,
+    pub settings: Settings,
     pub state: ConnectionState,
     pub config_path: Option<String>,
     pub session_path: Option<String>,
-    pub logs: Vec<String>,
-    
-    // Recent configs
-    pub recent_configs: Vec<String>,
+    /// Profile the running session was started with (the selection can change meanwhile)
+    pub session_profile: Option<String>,
+    pub logs: LogBuffer,
+    /// Masks secrets before records are printed, stored or exported
+    pub redactor: Redactor,
+    /// Rotating log files (settings.logging)
+    pub log_file: LogFileWriter,
+    pub journal: Option<Journal>,
+    pub log_filter: LogFilter,
+    pub log_auto_scroll: bool,
+    pub log_scroll_offset: f32,
+    /// `logs.pushed()` when the pane last scrolled to the end
+    pub log_snapped_at: u64,
+
+
+    // Per-profile settings, recent configs and network rules
+    pub profiles: ProfileStore,
     
     // Stats & Graphing
     pub stats: NetworkStats,
-    pub graph_data_in: CircularQueue<f32>,
-    pub graph_data_out: CircularQueue<f32>,
+    pub rate_meter: RateMeter,
+    /// Rates at the resolutions the graph windows need
+    pub rate_history: RateHistory,
     pub show_graph: bool,
+    /// Cursor over the graph, for the readout
+    pub graph_hover: Option<cosmic::iced::Point>,
     
     // Connection Info
     pub connection_start: Option<Instant>,
     pub tunnel_ip: String,
+    pub tunnel_device: Option<String>,
+    pub vpn_gateway: Option<String>,
     pub public_ip: String,
+    pub public_ip_disagreement: Option<String>,
+    pub public_ip_problem: Option<String>,
+    pub public_ip_tracker: PublicIpTracker,
     
     // Auto-Reconnect
     pub auto_reconnect: bool,
@@ -48,6 +94,16 @@ pub struct OpenVpnGui {
     
     // About dialog
     pub show_about: bool,
+    /// Finished sessions, and the History page's state
+    pub history: HistoryStore,
+    pub session_tracker: Option<SessionTracker>,
+    /// Why the current session is ending, recorded in its history entry
+    pub disconnect_reason: Option<String>,
+    pub show_history: bool,
+    pub history_filter: HistoryFilter,
+    pub history_selected: Option<u64>,
+    /// Data used per profile, for rollups and quotas
+    pub usage: UsageStore,
     
     // System Tray
     pub tray: Option<SystemTray>,
@@ -55,41 +111,151 @@ pub struct OpenVpnGui {
     // Session List
     pub session_list: Option<String>,
 
-    // Latency (used for health and stats)
-    pub latency_ms: Option<u32>,
+    // Latency probes (used for health and stats)
+    pub probe_engine: ProbeEngine,
+
+    // Connection health
+    pub health: Option<HealthReport>,
+    pub broken_since: Option<Instant>,
+    pub last_rx_change: Option<Instant>,
+    pub dns_ok: Option<bool>,
+    pub last_dns_check: Option<Instant>,
+    pub restart_pending: bool,
+
+    // Kill switch (rules stay installed across reconnects)
+    pub kill_switch_active: bool,
+    pub user_disconnect: bool,
+
+    // Split tunnelling
+    pub split_tunnel_active: bool,
+    pub launch_command: String,
+
+    // VPN network namespace (torn down with the session)
+    pub namespace_active: bool,
+    pub netns_command: String,
+
+    // Network changes, suspend and resume
+    pub session_paused: bool,
+    pub disconnected_for_sleep: bool,
+    pub pending_uplink_change: Option<Instant>,
+
+    // Trusted-network rules (evaluated when connectivity changes)
+    pub network_facts: Option<NetworkFacts>,
+    pub rule_decision: Option<RuleDecision>,
+    pub pending_rule_check: Option<Instant>,
+
+    // Captive portal (a detected portal holds the connection attempt)
+    pub portal_url: Option<String>,
+    pub portal_check_running: bool,
+    pub last_portal_check: Option<Instant>,
+    pub connect_started: Option<Instant>,
+    pub portal_checked_during_connect: bool,
+
+    // In-flight session-start, aborted when the user disconnects while connecting
+    pub connect_handle: Option<cosmic::iced::task::Handle>,
+
+    // Last failure, shown as a banner with a suggested fix
+    pub last_error: Option<VpnError>,
+    /// The session reported a failure while connecting and is being stopped
+    pub connect_failed: bool,
+
+    // Connect deadline (auth_started set while waiting for 2FA/SSO)
+    pub auth_started: Option<Instant>,
+    pub connect_timed_out: bool,
+    pub reconnect_attempts: u32,
+
+    // Failure diagnosis card (rules dismissed during this attempt are not shown again)
+    pub diagnosis: Option<Diagnosis>,
+    pub dismissed_diagnoses: Vec<&'static str>,
 }
 
 impl Default for OpenVpnGui {
     fn default() -> Self {
-        let mut q_in = CircularQueue::with_capacity(GRAPH_WINDOW);
-        let mut q_out = CircularQueue::with_capacity(GRAPH_WINDOW);
-        // Fill with zeros
-        for _ in 0..GRAPH_WINDOW { 
-            q_in.push(0.0); 
-            q_out.push(0.0); 
+        let settings = Settings::load();
+        let probe_engine = ProbeEngine::new(&settings.probes);
+        let redactor = Redactor::new(&settings.redaction);
+        let mut history = HistoryStore::load();
+        if let Err(e) = history.apply_retention(settings.history.retention_days) {
+            eprintln!("Failed to prune connection history: {}", e);
         }
-        
+        let log_file = LogFileWriter::new(&settings.logging);
+        let journal = settings.logging.journald.then(Journal::new);
+
         Self {
             core: Core::default(),
+            settings,
             state: ConnectionState::Disconnected,
             config_path: None,
             session_path: None,
-            logs: vec!["Application started.".to_string()],
-            recent_configs: Self::load_recent_configs(),
+            session_profile: None,
+            logs: LogBuffer::default(),
+            redactor,
+            log_file,
+            journal,
+            log_filter: LogFilter::default(),
+            log_auto_scroll: true,
+            log_scroll_offset: 0.0,
+            log_snapped_at: 0,
+            profiles: ProfileStore::load(),
             stats: NetworkStats::default(),
-            graph_data_in: q_in,
-            graph_data_out: q_out,
+            rate_meter: RateMeter::default(),
+            rate_history: RateHistory::default(),
             show_graph: true,
+            graph_hover: None,
             connection_start: None,
             tunnel_ip: "-".to_string(),
+            tunnel_device: None,
+            vpn_gateway: None,
             public_ip: "Checking...".to_string(),
+            public_ip_disagreement: None,
+            public_ip_problem: None,
+            public_ip_tracker: PublicIpTracker::new(),
             auto_reconnect: false,
             input_code: String::new(),
             is_asking_2fa: false,
             show_about: false,
+            history,
+            session_tracker: None,
+            disconnect_reason: None,
+            show_history: false,
+            history_filter: HistoryFilter::default(),
+            history_selected: None,
+            usage: UsageStore::load(),
             tray: SystemTray::new().ok(),
             session_list: None,
-            latency_ms: None,
+            probe_engine,
+            health: None,
+            broken_since: None,
+            last_rx_change: None,
+            dns_ok: None,
+            last_dns_check: None,
+            restart_pending: false,
+            kill_switch_active: false,
+            user_disconnect: false,
+            split_tunnel_active: false,
+            launch_command: String::new(),
+            namespace_active: false,
+            netns_command: String::new(),
+            session_paused: false,
+            disconnected_for_sleep: false,
+            pending_uplink_change: None,
+            network_facts: None,
+            rule_decision: None,
+            // Evaluate once the app has settled after startup
+            pending_rule_check: Some(Instant::now()),
+            portal_url: None,
+            portal_check_running: false,
+            last_portal_check: None,
+            connect_started: None,
+            portal_checked_during_connect: false,
+            connect_handle: None,
+            last_error: None,
+            connect_failed: false,
+            auth_started: None,
+            connect_timed_out: false,
+            reconnect_attempts: 0,
+            diagnosis: None,
+            dismissed_diagnoses: Vec::new(),
         }
     }
 }
@@ -111,11 +277,151 @@ impl Application for OpenVpnGui {
     fn init(core: Core, _flags: Self::Flags) -> (Self, Task<Self::Message>) {
         let mut app = Self::default();
         app.core = core;
-        
+        app.log("Application started.".to_string());
+        if app.tray.is_none() {
+            app.log_with(LogLevel::Warn, LogSource::Tray, "System tray unavailable".to_string());
+        }
+
         (app, Task::none())
     }
 
+    fn on_app_exit(&mut self) -> Option<Self::Message> {
+        self.save_before_exit();
+        None
+    }
+
     fn update(&mut self, message: Self::Message) -> Task<Self::Message> {
+        let task = self.handle_message(message);
+        // Keep the log pane pinned to the newest record
+        if self.log_auto_scroll && self.logs.pushed() != self.log_snapped_at {
+            self.log_snapped_at = self.logs.pushed();
+            return Task::batch([task, scroll_logs_to_end()]);
+        }
+        task
+    }
+
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let mut subscriptions = vec![
+            // Run the tick every second
+            cosmic::iced::time::every(Duration::from_secs(1)).map(Message::Tick),
+            Subscription::run(netlink_events).map(Message::NetworkEvent),
+            Subscription::run(sleep_events).map(Message::NetworkEvent),
+        ];
+        // Follow the session log while there is a session (restarted when the level changes)
+        if let Some(path) = self.session_path.clone() {
+            let level = self.settings.session_log_level;
+            subscriptions.push(
+                Subscription::run_with_id(("openvpn3-log", path.clone(), level), stream_session_log(path, level))
+                    .map(Message::SessionLog),
+            );
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    fn view(&self) -> Element<'_, Self::Message> {
+        view_main(self)
+    }
+}
+
+impl OpenVpnGui {
+
+    pub fn log(&mut self, msg: String) {
+        self.log_with(LogLevel::Info, LogSource::App, msg);
+    }
+
+    pub fn log_with(&mut self, level: LogLevel, source: LogSource, msg: String) {
+        self.push_log(LogRecord::new(level, source, msg));
+    }
+
+    /// Store a record, tagged with the current session. Secrets are masked first.
+    pub fn push_log(&mut self, mut record: LogRecord) {
+        if record.session.is_none() {
+            record.session = self.session_path.clone();
+        }
+        let record = self.redactor.redact_record(record);
+        println!("{}", record.format_line()); // Also print to CLI
+        self.log_file.write(&record);
+        if let Some(journal) = &self.journal {
+            let profile = self.config_path.as_deref().unwrap_or_default();
+            let session = record.session.as_deref().unwrap_or_default();
+            let state = format!("{:?}", self.state);
+            journal.send(&record, &[("PROFILE", profile), ("SESSION_PATH", session), ("STATE", &state)]);
+        }
+        self.logs.push(record);
+    }
+
+    pub fn cleanup_connection(&mut self) {
+        self.record_history();
+        self.connect_failed = false;
+        if let Some(session) = &self.session_path {
+            self.usage.end_session(session);
+            if let Err(e) = self.usage.save() {
+                self.log_with(LogLevel::Error, LogSource::App, format!("Failed to save data usage: {}", e));
+            }
+        }
+        self.state = ConnectionState::Disconnected;
+        self.session_path = None;
+        self.session_profile = None;
+        self.connection_start = None;
+        self.tunnel_ip = "-".to_string();
+        self.tunnel_device = None;
+        self.vpn_gateway = None;
+        self.probe_engine.reset_tunnel_targets();
+        self.stats = NetworkStats::default();
+        self.rate_meter.reset();
+        self.is_asking_2fa = false;
+        self.health = None;
+        self.broken_since = None;
+        self.last_rx_change = None;
+        self.dns_ok = None;
+        self.last_dns_check = None;
+        self.session_paused = false;
+        self.pending_uplink_change = None;
+        self.connect_started = None;
+        self.auth_started = None;
+        self.portal_checked_during_connect = false;
+        // Leaving the tunnel changes the public address
+        self.public_ip_tracker.request_refresh();
+    }
+
+    /// Keep what quitting would lose: a session still up goes into the history (it
+    /// keeps running in openvpn3, but this app stops following it) and usage is written
+    fn save_before_exit(&mut self) {
+        if self.session_tracker.is_some() {
+            self.disconnect_reason.get_or_insert_with(|| "App closed while connected".to_string());
+            self.record_history();
+        }
+        if let Err(e) = self.usage.save() {
+            eprintln!("Failed to save data usage: {}", e);
+        }
+    }
+
+    /// Add the session that is ending to the connection history
+    fn record_history(&mut self) {
+        let Some(tracker) = self.session_tracker.take() else {
+            return;
+        };
+        let reason = self.disconnect_reason.take().unwrap_or_else(|| "Session ended".to_string());
+        if !self.settings.history.enabled {
+            return;
+        }
+        let entry = tracker.finish(
+            self.session_profile.clone().unwrap_or_default(),
+            self.stats.bytes_in,
+            self.stats.bytes_out,
+            reason,
+        );
+        if let Err(e) = self.history.append(entry) {
+            self.log_with(LogLevel::Error, LogSource::App, format!("Failed to save connection history: {}", e));
+        }
+    }
+}
+
+// --- Message Handlers ---
+
+impl OpenVpnGui {
+    /// Dispatch a message to its handler
+    fn handle_message(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::Tick(_) => self.handle_tick(),
             Message::BrowseConfig => self.handle_browse_config(),
@@ -127,15 +433,114 @@ impl Application for OpenVpnGui {
             Message::VpnStopped(result) => self.handle_vpn_stopped(result),
             Message::StatsUpdated(stats_opt) => self.handle_stats_updated(stats_opt),
             Message::ToggleGraph(val) => self.handle_toggle_graph(val),
+            Message::ToggleRateSmoothing(val) => self.handle_toggle_rate_smoothing(val),
+            Message::SetGraphWindow(window) => {
+                self.settings.graph.window = window;
+                self.settings.save();
+                Task::none()
+            }
+            Message::ToggleGraphStacked(val) => {
+                self.settings.graph.stacked = val;
+                self.settings.save();
+                Task::none()
+            }
+            Message::GraphHover(position) => {
+                self.graph_hover = position;
+                Task::none()
+            }
             Message::ToggleAutoReconnect(val) => self.handle_toggle_auto_reconnect(val),
+            Message::ToggleKillSwitch(val) => self.handle_toggle_kill_switch(val),
+            Message::ToggleSplitTunnel(val) => self.handle_toggle_split_tunnel(val),
+            Message::ToggleNamespace(val) => self.handle_toggle_namespace(val),
+            Message::TogglePrivacyMode(val) => self.handle_toggle_privacy_mode(val),
+            Message::ToggleCaptivePortal(val) => {
+                self.settings.captive_portal.enabled = val;
+                self.settings.save();
+                Task::none()
+            }
             Message::SessionStatusChecked(status_opt) => self.handle_session_status(status_opt),
             Message::TunnelIpFound(ip) => self.handle_tunnel_ip(ip),
             Message::PublicIpFound(ip) => self.handle_public_ip(ip),
-            Message::SaveLogs => self.handle_save_logs(),
+            Message::SaveLogs => self.save_dialog("Save Logs", "openvpn_logs", &ExportFormat::LOG_FORMATS, Message::LogsSavePathChosen),
+            Message::LogsSavePathChosen(path) => match path {
+                Some(path) => self.handle_save_logs(path),
+                None => Task::none(),
+            },
+            Message::OpenLogFolder => {
+                let dir = log_dir();
+                let opened = std::fs::create_dir_all(&dir)
+                    .and_then(|_| std::process::Command::new("xdg-open").arg(&dir).spawn());
+                match opened {
+                    // Reap xdg-open once it hands the folder to the file manager
+                    Ok(mut child) => {
+                        std::thread::spawn(move || child.wait());
+                    }
+                    Err(e) => {
+                        self.log_with(LogLevel::Error, LogSource::App, format!("Failed to open {}: {}", dir.display(), e));
+                    }
+                }
+                Task::none()
+            }
             Message::InputCodeChanged(s) => self.handle_input_changed(s),
             Message::SubmitCode => self.handle_submit_code(),
             Message::AuthCodeResult(res) => self.handle_auth_result(res),
             Message::ShowAbout => self.handle_show_about(),
+            Message::ShowHistory => {
+                self.show_history = true;
+                Task::none()
+            }
+            Message::CloseHistory => {
+                self.show_history = false;
+                self.history_selected = None;
+                Task::none()
+            }
+            Message::HistoryProfileFilter(profile) => {
+                // The first choice is "All profiles"
+                self.history_filter.profile = self.history.profiles().contains(&profile).then_some(profile);
+                Task::none()
+            }
+            Message::HistoryFromChanged(from) => {
+                self.history_filter.from = from;
+                Task::none()
+            }
+            Message::HistoryToChanged(to) => {
+                self.history_filter.to = to;
+                Task::none()
+            }
+            Message::SelectHistoryEntry(id) => {
+                self.history_selected = (self.history_selected != Some(id)).then_some(id);
+                Task::none()
+            }
+            Message::DeleteHistoryEntry(id) => {
+                if self.history_selected == Some(id) {
+                    self.history_selected = None;
+                }
+                if let Err(e) = self.history.delete(id) {
+                    self.log_with(LogLevel::Error, LogSource::App, format!("Failed to delete history entry: {}", e));
+                }
+                Task::none()
+            }
+            Message::ClearHistory => {
+                self.history_selected = None;
+                match self.history.clear() {
+                    Ok(()) => self.log("Connection history cleared".to_string()),
+                    Err(e) => self.log_with(LogLevel::Error, LogSource::App, format!("Failed to clear history: {}", e)),
+                }
+                Task::none()
+            }
+            Message::SetHistoryRetention(retention) => {
+                self.settings.history.retention_days = retention.0;
+                self.settings.save();
+                if let Err(e) = self.history.apply_retention(retention.0) {
+                    self.log_with(LogLevel::Error, LogSource::App, format!("Failed to prune history: {}", e));
+                }
+                Task::none()
+            }
+            Message::ToggleHistory(val) => {
+                self.settings.history.enabled = val;
+                self.settings.save();
+                Task::none()
+            }
             Message::CloseAbout => self.handle_close_about(),
             Message::ShowSessions => {
                 Task::perform(crate::vpn::manager::list_sessions(), |x| cosmic::Action::App(Message::SessionsListed(x)))
@@ -148,48 +553,151 @@ impl Application for OpenVpnGui {
                 self.session_list = None;
                 Task::none()
             }
-            Message::SaveSessionReport => self.handle_save_session_report(),
-            Message::LatencyChecked(lat) => self.handle_latency_checked(lat),
-        }
-    }
-
-    fn subscription(&self) -> Subscription<Self::Message> {
-        // Run the tick every second
-        cosmic::iced::time::every(Duration::from_secs(1)).map(Message::Tick)
-    }
-
-    fn view(&self) -> Element<'_, Self::Message> {
-        view_main(self)
-    }
-}
-
-impl OpenVpnGui {
-
-    pub fn log(&mut self, msg: String) {
-        let timestamp = chrono::Local::now().format("%H:%M:%S");
-        let log_line = format!("[{}] {}", timestamp, msg);
-        println!("{}", log_line); // Also print to CLI
-        self.logs.push(log_line);
-        // Keep logs manageable
-        if self.logs.len() > 1000 {
-            self.logs.remove(0);
+            Message::SaveSessionReport => self.save_dialog(
+                "Export Session Report",
+                "openvpn_session_report",
+                &ExportFormat::REPORT_FORMATS,
+                Message::SessionReportPathChosen,
+            ),
+            Message::SessionReportPathChosen(path) => match path {
+                Some(path) => self.handle_save_session_report(path),
+                None => Task::none(),
+            },
+            Message::SessionReportReady(path, snapshot) => self.handle_session_report_ready(path, snapshot),
+            Message::CreateDiagnosticsBundle => self.save_dialog(
+                "Create Diagnostics Bundle",
+                "openvpn_diagnostics",
+                &ExportFormat::BUNDLE_FORMATS,
+                Message::BundlePathChosen,
+            ),
+            Message::BundlePathChosen(path) => match path {
+                Some(path) => self.handle_create_bundle(path),
+                None => Task::none(),
+            },
+            Message::BundleReady(path, snapshot, files) => self.handle_bundle_ready(path, snapshot, files),
+            Message::VpnGatewayFound(gw) => self.handle_vpn_gateway(gw),
+            Message::ProbeCompleted(name, rtt) => self.handle_probe_completed(name, rtt),
+            Message::DnsChecked(ok) => self.handle_dns_checked(ok),
+            Message::KillSwitchRulesBuilt(res) => self.handle_kill_switch_rules(res),
+            Message::KillSwitchApplied(res) => self.handle_kill_switch_applied(res),
+            Message::KillSwitchRemoved(res) => self.handle_kill_switch_removed(res),
+            Message::SplitTunnelApplied(res) => self.handle_split_tunnel_applied(res),
+            Message::SplitTunnelRemoved(res) => self.handle_split_tunnel_removed(res),
+            Message::LaunchCommandChanged(s) => {
+                self.launch_command = s;
+                Task::none()
+            }
+            Message::LaunchApp(side, command) => self.handle_launch_app(side, command),
+            Message::NamespaceReady(res) => self.handle_namespace_ready(res),
+            Message::NamespaceRemoved(res) => self.handle_namespace_removed(res),
+            Message::NetnsCommandChanged(s) => {
+                self.netns_command = s;
+                Task::none()
+            }
+            Message::RunInNamespace(command) => self.handle_run_in_namespace(command),
+            Message::NetworkEvent(event) => self.handle_network_event(event),
+            Message::NetworkFactsGathered(facts) => self.handle_network_facts(facts),
+            Message::PortalChecked(result) => self.handle_portal_checked(result),
+            Message::OpenPortal => {
+                if let Some(url) = &self.portal_url {
+                    let _ = webbrowser::open(url);
+                }
+                Task::none()
+            }
+            Message::SessionManaged(res) => {
+                match res {
+                    Ok(msg) => self.log(msg),
+                    Err(e) => {
+                        self.log_with(LogLevel::Error, LogSource::App, format!("Session management failed: {}", e));
+                        self.last_error = Some(e);
+                    }
+                }
+                Task::none()
+            }
+            Message::SessionLog(line) => {
+                let level = LogLevel::from_openvpn3(line.category.as_deref());
+                let message = self.redactor.redact(&line.message);
+                // Routine lines of a healthy session can read like failures: once it is up,
+                // only its errors are diagnosed
+                if self.state == ConnectionState::Connecting || level == LogLevel::Error {
+                    self.consider_diagnosis(diagnose(&message));
+                }
+                let mut record = LogRecord::new(level, LogSource::Openvpn3, message);
+                record.category = line.tag();
+                self.push_log(record);
+                Task::none()
+            }
+            Message::LogLevelToggled(level, shown) => {
+                self.log_filter.set_level(level, shown);
+                Task::none()
+            }
+            Message::LogSearchChanged(search) => {
+                self.log_filter.search = search;
+                Task::none()
+            }
+            Message::ToggleLogAutoScroll(val) => {
+                self.log_auto_scroll = val;
+                // Jump to the end straight away when turned back on
+                self.log_snapped_at = 0;
+                Task::none()
+            }
+            Message::LogScrolled(viewport) => {
+                self.log_scroll_offset = viewport.absolute_offset().y;
+                Task::none()
+            }
+            Message::CopyLogs => {
+                let text = self
+                    .logs
+                    .filtered(&self.log_filter)
+                    .map(|r| r.format_line())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                cosmic::iced::clipboard::write(text)
+            }
+            Message::SetSessionLogLevel(level) => {
+                self.settings.session_log_level = level;
+                self.settings.save();
+                Task::none()
+            }
+            Message::DismissDiagnosis => {
+                if let Some(diagnosis) = self.diagnosis.take() {
+                    self.dismissed_diagnoses.push(diagnosis.rule_id);
+                }
+                Task::none()
+            }
+            Message::DismissError => {
+                self.last_error = None;
+                Task::none()
+            }
+            Message::SetSleepAction(action) => self.update_triggers(|t| t.on_sleep = action),
+            Message::SetWakeAction(action) => self.update_triggers(|t| t.on_wake = action),
+            Message::ToggleRestartOnUplinkChange(val) => self.update_triggers(|t| t.restart_on_uplink_change = val),
+            Message::SetQuotaPeriod(period) => self.update_quota(|q| q.period = period),
+            Message::QuotaLimitChanged(input) => {
+                // Empty turns the quota off; anything that is not a number is ignored
+                let input = input.trim();
+                let limit = if input.is_empty() { Ok(0) } else { input.parse::<u64>() };
+                match limit {
+                    Ok(mb) => self.update_quota(|q| q.limit_mb = mb.min(Quota::MAX_LIMIT_MB)),
+                    Err(_) => Task::none(),
+                }
+            }
+            Message::ToggleQuotaDisconnect(val) => self.update_quota(|q| q.disconnect_at_limit = val),
+            Message::AppExited(res) => {
+                match res {
+                    Ok(msg) => self.log(msg),
+                    Err(e) => self.log_with(LogLevel::Warn, LogSource::App, e),
+                }
+                Task::none()
+            }
         }
     }
 
-    pub fn cleanup_connection(&mut self) {
-        self.state = ConnectionState::Disconnected;
-        self.session_path = None;
-        self.connection_start = None;
-        self.tunnel_ip = "-".to_string();
-        self.stats = NetworkStats::default();
-        self.is_asking_2fa = false;
-    }
-}
-
-// --- Message Handlers ---
-
-impl OpenVpnGui {
     fn handle_tick(&mut self) -> Task<Message> {
+        if self.tray.as_ref().is_some_and(|tray| tray.quit_requested()) {
+            self.save_before_exit();
+            std::process::exit(0);
+        }
         let mut cmds = Vec::new();
         
         // Update tray icon/tooltip
@@ -205,8 +713,8 @@ impl OpenVpnGui {
             }
         }
         
-        // 2. Update Stats if connected
-        if self.state == ConnectionState::Connected {
+        // 2. Update Stats if connected (a paused session has nothing to report)
+        if self.state == ConnectionState::Connected && !self.session_paused {
             if let Some(path) = &self.session_path {
                 cmds.push(Task::perform(
                     fetch_session_stats(path.clone()), 
@@ -217,16 +725,75 @@ impl OpenVpnGui {
 
         // 3. Check Tunnel IP occasionally
         if self.state == ConnectionState::Connected && self.tunnel_ip == "-" {
-            cmds.push(Task::perform(find_tunnel_ip(), |x| cosmic::Action::App(Message::TunnelIpFound(x))));
+            cmds.push(Task::perform(find_tunnel_ip(self.tunnel_device_name()), |x| cosmic::Action::App(Message::TunnelIpFound(x))));
+        }
+        // (the tunnel is not visible from here once it lives in the VPN namespace)
+        if self.state == ConnectionState::Connected && self.vpn_gateway.is_none() && !self.namespace_active {
+            cmds.push(Task::perform(find_vpn_gateway(self.tunnel_device_name()), |x| cosmic::Action::App(Message::VpnGatewayFound(x))));
         }
         
-        // 4. Check Public IP on startup or when connecting
-        if self.public_ip == "Checking..." {
-            cmds.push(Task::perform(fetch_public_ip(), |x| cosmic::Action::App(Message::PublicIpFound(x))));
+        // 4. Check Public IP on startup, on connect/disconnect and when the cache expires
+        if self.settings.probe_allowed(Probe::PublicIp)
+            && self.public_ip_tracker.start_if_due(&self.settings.public_ip) {
+            cmds.push(Task::perform(
+                lookup_public_ip(self.settings.public_ip.clone()),
+                |x| cosmic::Action::App(Message::PublicIpFound(x))
+            ));
+        }
+
+        // 5. Latency probes (each target runs on its own, without blocking the tick)
+        if self.settings.probe_allowed(Probe::Latency) {
+            let connected = self.state == ConnectionState::Connected && !self.namespace_active && !self.session_paused;
+            let jobs = self.probe_engine.due_jobs(&self.settings.probes, connected, self.vpn_gateway.as_deref());
+            for job in jobs {
+                let name = job.name.clone();
+                cmds.push(Task::perform(
+                    run_probe(job),
+                    move |x| cosmic::Action::App(Message::ProbeCompleted(name, x))
+                ));
+            }
+        }
+
+        // 6. DNS reachability check every 30s while connected
+        let dns_host = self.settings.health.dns_check_host.clone();
+        if self.state == ConnectionState::Connected
+            && !dns_host.is_empty()
+            && self.settings.probe_allowed(Probe::Dns)
+            && self.last_dns_check.is_none_or(|t| t.elapsed() >= Duration::from_secs(30)) {
+            self.last_dns_check = Some(Instant::now());
+            cmds.push(Task::perform(check_dns(dns_host), |x| cosmic::Action::App(Message::DnsChecked(x))));
         }
 
-        // 5. Ping for latency every tick (update live)
-        cmds.push(Task::perform(ping_latency(), |x| cosmic::Action::App(Message::LatencyChecked(x))));
+        // 7. Connection health (may restart a broken session)
+        if let Some(task) = self.update_health() {
+            cmds.push(task);
+        }
+
+        // 8. Restart after the uplink has settled
+        if let Some(task) = self.restart_after_uplink_change() {
+            cmds.push(task);
+        }
+
+        // 9. Re-evaluate trusted-network rules once connectivity has settled
+        if self.pending_rule_check.is_some_and(|t| t.elapsed() >= Duration::from_secs(3)) {
+            self.pending_rule_check = None;
+            cmds.push(Task::perform(gather_facts(), |x| cosmic::Action::App(Message::NetworkFactsGathered(x))));
+        }
+
+        // 10. Captive portal: retry while one holds the connection, check once if connecting stalls
+        if let Some(task) = self.portal_check_due() {
+            cmds.push(task);
+        }
+
+        // 11. Give up on a session that never finishes connecting
+        if let Some(task) = self.check_connect_deadline() {
+            cmds.push(task);
+        }
+
+        // 12. Write data usage back now and then
+        if let Err(e) = self.usage.save_if_due() {
+            self.log_with(LogLevel::Error, LogSource::App, format!("Failed to save data usage: {}", e));
+        }
         Task::batch(cmds)
     }
 
@@ -238,7 +805,7 @@ impl OpenVpnGui {
         if let Some(path) = path_opt {
             let path_str = path.to_string_lossy().to_string();
             self.config_path = Some(path_str.clone());
-            self.add_to_recent_configs(path_str);
+            self.profiles.add_recent(path_str);
             self.log(format!("Selected config: {:?}", path));
         }
         Task::none()
@@ -248,14 +815,45 @@ impl OpenVpnGui {
         match self.state {
             ConnectionState::Disconnected => {
                 if let Some(cfg) = self.config_path.clone() {
+                    let quota = self.quota();
+                    if quota.disconnect_at_limit && quota.exhausted(self.quota_usage()) {
+                        self.log_with(LogLevel::Warn, LogSource::App, format!(
+                            "Data quota of {} MB for {} is used up; not connecting",
+                            quota.limit_mb,
+                            quota.period.to_string().to_lowercase()
+                        ));
+                        return Task::none();
+                    }
                     self.state = ConnectionState::Connecting;
-                    self.log(format!("Starting VPN with {}", cfg));
-                    return Task::perform(start_vpn(cfg), |x| cosmic::Action::App(Message::VpnStarted(x)));
+                    self.last_error = None;
+                    self.dismissed_diagnoses.clear();
+                    self.connect_started = Some(Instant::now());
+                    self.portal_checked_during_connect = false;
+                    if self.portal_check_enabled() {
+                        self.log("Checking for a captive portal...".to_string());
+                        return self.portal_check_task();
+                    }
+                    return self.start_connect(cfg);
                 } else {
                     self.log("No config selected.".to_string());
                 }
             }
             ConnectionState::Connected | ConnectionState::Connecting => {
+                self.user_disconnect = true;
+                // Held back by a portal (or still checking): there is no session to stop yet
+                if self.session_path.is_none() && (self.portal_url.is_some() || self.portal_check_running) {
+                    self.log("Connection attempt cancelled".to_string());
+                    self.portal_url = None;
+                    self.user_disconnect = false;
+                    self.cleanup_connection();
+                    return Task::none();
+                }
+                self.portal_url = None;
+                // Still starting: kill session-start, then clean up whatever it registered by config
+                if let Some(handle) = self.connect_handle.take() {
+                    self.log("Cancelling connection attempt...".to_string());
+                    handle.abort();
+                }
                 if let Some(path) = self.session_path.clone() {
                     self.log("Disconnecting...".to_string());
                     return Task::perform(stop_vpn_by_path(path), |x| cosmic::Action::App(Message::VpnStopped(x)));
@@ -267,7 +865,8 @@ impl OpenVpnGui {
         Task::none()
     }
 
-    fn handle_vpn_started(&mut self, result: Result<(String, String), String>) -> Task<Message> {
+    fn handle_vpn_started(&mut self, result: Result<(String, String), VpnError>) -> Task<Message> {
+        self.connect_handle = None;
         match result {
             Ok((output, session_path)) => {
                 self.log("VPN session initiated. Waiting for authentication...".to_string());
@@ -275,12 +874,14 @@ impl OpenVpnGui {
                 // Check if authentication is required
                 if output.contains("CHALLENGE") || output.contains("password") || output.contains("Authentication") {
                     self.is_asking_2fa = true;
+                    self.auth_started.get_or_insert_with(Instant::now);
                     self.log("Authentication required - please enter your code".to_string());
                 }
                 
                 // Check if SSO/web authentication is required
                 if output.contains("AUTH_PENDING") || output.contains("Web based authentication") 
                     || output.contains("awaiting external authentication") {
+                    self.auth_started.get_or_insert_with(Instant::now);
                     self.log("Waiting for SSO authentication in browser...".to_string());
                 }
                 
@@ -288,18 +889,51 @@ impl OpenVpnGui {
                 self.state = ConnectionState::Connecting;
             }
             Err(e) => {
-                self.log(format!("Failed to start: {}", e));
+                self.log_with(LogLevel::Error, LogSource::App, format!("Failed to start: {}", e));
                 self.state = ConnectionState::Disconnected;
+                self.consider_diagnosis(diagnose_log(e.source()));
+                self.last_error = Some(e);
             }
         }
         Task::none()
     }
 
-    fn handle_vpn_stopped(&mut self, result: Result<String, String>) -> Task<Message> {
+    fn handle_vpn_stopped(&mut self, result: Result<String, VpnError>) -> Task<Message> {
         match result {
             Ok(msg) => self.log(msg),
-            Err(e) => self.log(format!("Error stopping: {}", e)),
+            Err(e) => self.log_with(LogLevel::Error, LogSource::App, format!("Error stopping: {}", e)),
         }
+        // However the session ended, what was set up for it goes (before any new session)
+        let teardown = self.teardown_session_setup();
+        if self.portal_url.is_some() && self.state == ConnectionState::Connecting {
+            // Dropped the half-open session for a captive portal: keep waiting for it to clear
+            self.cleanup_connection();
+            self.state = ConnectionState::Connecting;
+            return teardown;
+        }
+        if self.connect_timed_out {
+            self.connect_timed_out = false;
+            self.cleanup_connection();
+            return teardown.chain(self.reconnect_after_timeout());
+        }
+        if self.restart_pending {
+            // Health policy restart: bring the session straight back up
+            self.restart_pending = false;
+            self.disconnect_reason = Some("Restarted after the connection broke".to_string());
+            self.cleanup_connection();
+            return teardown.chain(self.handle_toggle_vpn());
+        }
+        let reason = if self.disconnected_for_sleep {
+            "System suspend"
+        } else if self.user_disconnect {
+            "Disconnected by user"
+        } else {
+            "Session ended"
+        };
+        self.disconnect_reason.get_or_insert_with(|| reason.to_string());
+        // Only a user-initiated disconnect lifts the kill switch
+        let lift_kill_switch = self.user_disconnect && self.kill_switch_active;
+        self.user_disconnect = false;
         // Show notification with icon path (16x16)
         let _ = Notification::new()
             .summary("OpenVPN3 GUI")
@@ -307,31 +941,86 @@ impl OpenVpnGui {
             .icon(concat!(env!("CARGO_MANIFEST_DIR"), "/icons/openvpn3-gui-16.png"))
             .show();
         self.cleanup_connection();
-        Task::none()
+
+        let mut tasks = vec![teardown];
+        if lift_kill_switch {
+            tasks.push(Task::perform(remove_kill_switch(), |x| cosmic::Action::App(Message::KillSwitchRemoved(x))));
+        }
+        Task::batch(tasks)
     }
 
-    fn handle_stats_updated(&mut self, stats_opt: Option<(u64, u64)>) -> Task<Message> {
-        if let Some((total_in, total_out)) = stats_opt {
-            // Calculate rates based on diff from previous
-            let diff_in = if total_in >= self.stats.bytes_in { 
-                total_in - self.stats.bytes_in 
-            } else { 
-                0 
-            };
-            let diff_out = if total_out >= self.stats.bytes_out { 
-                total_out - self.stats.bytes_out 
-            } else { 
-                0 
-            };
-            
-            self.stats.bytes_in = total_in;
-            self.stats.bytes_out = total_out;
-            self.stats.rate_in = diff_in as f32;
-            self.stats.rate_out = diff_out as f32;
+    /// Remove the split tunnel rules and the VPN namespace (with what runs in it) of the
+    /// session that ended. The flags are cleared right away so the next session sets
+    /// them up afresh.
+    fn teardown_session_setup(&mut self) -> Task<Message> {
+        let mut tasks = Vec::new();
+        if std::mem::take(&mut self.split_tunnel_active) {
+            tasks.push(Task::perform(remove_split_tunnel(), |x| cosmic::Action::App(Message::SplitTunnelRemoved(x))));
+        }
+        if std::mem::take(&mut self.namespace_active) {
+            tasks.push(Task::perform(teardown_namespace(), |x| cosmic::Action::App(Message::NamespaceRemoved(x))));
+        }
+        Task::batch(tasks)
+    }
 
-            // Update Graph
-            self.graph_data_in.push(self.stats.rate_in);
-            self.graph_data_out.push(self.stats.rate_out);
+    fn handle_stats_updated(&mut self, sample: Option<CounterSample>) -> Task<Message> {
+        let Some(sample) = sample else {
+            return Task::none();
+        };
+        let smoothing = if self.settings.rates.smoothing { self.settings.rates.smoothing_secs } else { 0.0 };
+        match self.rate_meter.update(sample, smoothing) {
+            // Older than the counters already shown
+            SampleOutcome::Stale => return Task::none(),
+            SampleOutcome::First => {}
+            SampleOutcome::Reset => {
+                self.log_with(LogLevel::Debug, LogSource::App, "Session counters went backwards; rates start over".to_string());
+            }
+            SampleOutcome::Rate(point) => {
+                self.stats.rate_in = point.rate_in;
+                self.stats.rate_out = point.rate_out;
+                if let Some(tracker) = &mut self.session_tracker {
+                    tracker.record_rates(point.rate_in, point.rate_out);
+                }
+                self.rate_history.push(point);
+            }
+        }
+
+        if sample.bytes_in != self.stats.bytes_in {
+            self.last_rx_change = Some(sample.at);
+        }
+        self.stats.bytes_in = sample.bytes_in;
+        self.stats.bytes_out = sample.bytes_out;
+        self.record_usage(sample.bytes_in, sample.bytes_out)
+    }
+
+    /// Add the session's new traffic to its profile's usage, then check that quota
+    fn record_usage(&mut self, total_in: u64, total_out: u64) -> Task<Message> {
+        let (Some(profile), Some(session)) = (self.session_profile.clone(), self.session_path.clone()) else {
+            return Task::none();
+        };
+        self.usage.record(&profile, &session, total_in, total_out);
+
+        let quota = self.quota_of(&profile);
+        if let Some(percent) = self.usage.check_quota(&profile, &quota, today()) {
+            let message = format!(
+                "{}% of the {} MB data quota used ({})",
+                percent,
+                quota.limit_mb,
+                quota.period.to_string().to_lowercase()
+            );
+            self.log_with(LogLevel::Warn, LogSource::App, message.clone());
+            let _ = Notification::new()
+                .summary("OpenVPN3 GUI")
+                .body(&message)
+                .icon(concat!(env!("CARGO_MANIFEST_DIR"), "/icons/openvpn3-gui-16.png"))
+                .show();
+        }
+
+        // Reaching the quota counts as a deliberate disconnect (lifts the kill switch)
+        let used = self.usage.period_usage(&profile, quota.period, today());
+        if quota.disconnect_at_limit && quota.exhausted(used) && self.state == ConnectionState::Connected {
+            self.disconnect_reason = Some("Data quota reached".to_string());
+            return self.handle_toggle_vpn();
         }
         Task::none()
     }
@@ -341,14 +1030,236 @@ impl OpenVpnGui {
         Task::none()
     }
 
+    fn handle_toggle_rate_smoothing(&mut self, val: bool) -> Task<Message> {
+        self.settings.rates.smoothing = val;
+        self.settings.save();
+        Task::none()
+    }
+
     fn handle_toggle_auto_reconnect(&mut self, val: bool) -> Task<Message> {
         self.auto_reconnect = val;
         Task::none()
     }
 
+    fn handle_toggle_kill_switch(&mut self, val: bool) -> Task<Message> {
+        self.settings.kill_switch.enabled = val;
+        self.settings.save();
+        if val {
+            self.log("Kill switch enabled".to_string());
+            self.kill_switch_task().unwrap_or_else(Task::none)
+        } else if self.kill_switch_active {
+            self.log("Kill switch disabled - removing firewall rules".to_string());
+            Task::perform(remove_kill_switch(), |x| cosmic::Action::App(Message::KillSwitchRemoved(x)))
+        } else {
+            Task::none()
+        }
+    }
+
+    /// Start installing the kill switch for the current session, if enabled
+    fn kill_switch_task(&self) -> Option<Task<Message>> {
+        if !self.settings.kill_switch.enabled || self.state != ConnectionState::Connected {
+            return None;
+        }
+        let config = self.config_path.clone()?;
+        Some(Task::perform(
+            build_rules(config, self.tunnel_device_name(), self.settings.kill_switch.clone()),
+            |x| cosmic::Action::App(Message::KillSwitchRulesBuilt(x))
+        ))
+    }
+
+    fn handle_kill_switch_rules(&mut self, res: Result<KillSwitchRules, String>) -> Task<Message> {
+        match res {
+            Ok(rules) => {
+                let servers = rules.servers.iter().map(|s| s.addr.to_string()).collect::<Vec<_>>().join(", ");
+                self.log(format!("Applying kill switch (device {}, servers {})", rules.tunnel_device, servers));
+                Task::perform(apply_kill_switch(rules), |x| cosmic::Action::App(Message::KillSwitchApplied(x)))
+            }
+            Err(e) => {
+                self.log_with(LogLevel::Error, LogSource::App, format!("Kill switch not applied: {}", e));
+                Task::none()
+            }
+        }
+    }
+
+    fn handle_kill_switch_applied(&mut self, res: Result<(), String>) -> Task<Message> {
+        match res {
+            Ok(()) => {
+                self.kill_switch_active = true;
+                self.log("Kill switch active - traffic outside the tunnel is blocked".to_string());
+            }
+            Err(e) => self.log_with(LogLevel::Error, LogSource::App, format!("Failed to apply kill switch: {}", e)),
+        }
+        Task::none()
+    }
+
+    fn handle_kill_switch_removed(&mut self, res: Result<(), String>) -> Task<Message> {
+        match res {
+            Ok(()) => {
+                self.kill_switch_active = false;
+                self.log("Kill switch removed".to_string());
+            }
+            Err(e) => self.log_with(LogLevel::Error, LogSource::App, format!("Failed to remove kill switch: {}", e)),
+        }
+        Task::none()
+    }
+
+    /// Split tunnel settings of the selected profile
+    pub fn split_tunnel_settings(&self) -> Option<&SplitTunnelSettings> {
+        let config = self.config_path.as_ref()?;
+        self.profiles.get(config).map(|p| &p.split_tunnel)
+    }
+
+    fn handle_toggle_split_tunnel(&mut self, val: bool) -> Task<Message> {
+        let Some(config) = self.config_path.clone() else {
+            return Task::none();
+        };
+        self.profiles.get_or_insert(&config).split_tunnel.enabled = val;
+        self.profiles.save();
+
+        if val && self.state == ConnectionState::Connected {
+            self.log("Split tunnelling enabled".to_string());
+            Task::perform(
+                apply_split_tunnel(self.tunnel_device_name()),
+                |x| cosmic::Action::App(Message::SplitTunnelApplied(x))
+            )
+        } else if !val && self.split_tunnel_active {
+            self.log("Split tunnelling disabled".to_string());
+            Task::perform(remove_split_tunnel(), |x| cosmic::Action::App(Message::SplitTunnelRemoved(x)))
+        } else {
+            Task::none()
+        }
+    }
+
+    fn handle_split_tunnel_applied(&mut self, res: Result<(), String>) -> Task<Message> {
+        match res {
+            Ok(()) => {
+                self.split_tunnel_active = true;
+                self.log("Split tunnelling active".to_string());
+            }
+            Err(e) => self.log_with(LogLevel::Error, LogSource::App, format!("Failed to set up split tunnelling: {}", e)),
+        }
+        Task::none()
+    }
+
+    fn handle_split_tunnel_removed(&mut self, res: Result<(), String>) -> Task<Message> {
+        match res {
+            Ok(()) => {
+                self.split_tunnel_active = false;
+                self.log("Split tunnelling removed".to_string());
+            }
+            Err(e) => self.log_with(LogLevel::Error, LogSource::App, format!("Failed to remove split tunnelling: {}", e)),
+        }
+        Task::none()
+    }
+
+    fn handle_launch_app(&mut self, side: SplitSide, command: String) -> Task<Message> {
+        let command = command.trim().to_string();
+        if command.is_empty() {
+            return Task::none();
+        }
+
+        // Remember the command as a launcher for this profile
+        if let Some(config) = self.config_path.clone() {
+            let split = &mut self.profiles.get_or_insert(&config).split_tunnel;
+            let apps = match side {
+                SplitSide::Inside => &mut split.inside_apps,
+                SplitSide::Outside => &mut split.outside_apps,
+            };
+            apps.retain(|c| c != &command);
+            apps.insert(0, command.clone());
+            apps.truncate(10);
+            self.profiles.save();
+        }
+
+        if !self.split_tunnel_active {
+            self.log("Split tunnelling is not active - the app will use the default route".to_string());
+        }
+        self.log(format!("Launching '{}' {}", command, side.label()));
+        self.launch_command.clear();
+        Task::perform(launch_app(side, command), |x| cosmic::Action::App(Message::AppExited(x)))
+    }
+
+    fn handle_toggle_namespace(&mut self, val: bool) -> Task<Message> {
+        let Some(config) = self.config_path.clone() else {
+            return Task::none();
+        };
+        self.profiles.get_or_insert(&config).namespace = val;
+        self.profiles.save();
+        if self.state != ConnectionState::Disconnected {
+            self.log("VPN namespace setting takes effect on the next connect".to_string());
+        }
+        Task::none()
+    }
+
+    fn handle_namespace_ready(&mut self, res: Result<String, String>) -> Task<Message> {
+        match res {
+            Ok(addrs) => {
+                self.namespace_active = true;
+                if let Some(ip) = addrs.lines().next().and_then(|a| a.split('/').next()) {
+                    self.tunnel_ip = ip.to_string();
+                }
+                self.log(format!(
+                    "VPN namespace '{}' ready - the tunnel is only reachable from inside it",
+                    NAMESPACE
+                ));
+            }
+            Err(e) => self.log_with(LogLevel::Error, LogSource::App, format!("Failed to set up VPN namespace: {}", e)),
+        }
+        Task::none()
+    }
+
+    fn handle_namespace_removed(&mut self, res: Result<(), String>) -> Task<Message> {
+        match res {
+            Ok(()) => {
+                self.namespace_active = false;
+                self.log(format!("VPN namespace '{}' removed", NAMESPACE));
+            }
+            Err(e) => self.log_with(LogLevel::Error, LogSource::App, format!("Failed to remove VPN namespace: {}", e)),
+        }
+        Task::none()
+    }
+
+    fn handle_run_in_namespace(&mut self, command: String) -> Task<Message> {
+        let command = command.trim().to_string();
+        if command.is_empty() {
+            return Task::none();
+        }
+        if !self.namespace_active {
+            self.log("VPN namespace is not active".to_string());
+            return Task::none();
+        }
+        self.log(format!("Running '{}' in VPN namespace", command));
+        self.netns_command.clear();
+        Task::perform(run_in_namespace(command), |x| cosmic::Action::App(Message::AppExited(x)))
+    }
+
+    /// Tunnel device reported by openvpn3, falling back to tun0
+    pub fn tunnel_device_name(&self) -> String {
+        self.tunnel_device.clone().unwrap_or_else(|| "tun0".to_string())
+    }
+
+    fn handle_toggle_privacy_mode(&mut self, val: bool) -> Task<Message> {
+        self.settings.privacy.enabled = val;
+        self.settings.save();
+        if val {
+            // Drop results gathered by probes that are now off
+            if !self.settings.probe_allowed(Probe::PublicIp) {
+                self.public_ip_disagreement = None;
+            }
+            if !self.settings.probe_allowed(Probe::Latency) {
+                self.probe_engine.clear();
+            }
+            self.log("Privacy mode enabled - outbound probes disabled".to_string());
+        } else {
+            self.public_ip_tracker.request_refresh();
+            self.log("Privacy mode disabled".to_string());
+        }
+        Task::none()
+    }
+
     fn handle_session_status(&mut self, status_opt: Option<String>) -> Task<Message> {
-        // Only process if we're in Connecting state
-        if self.state != ConnectionState::Connecting {
+        // Only process if we're in Connecting state (and not already giving up)
+        if self.state != ConnectionState::Connecting || self.connect_failed {
             return Task::none();
         }
         
@@ -360,24 +1271,49 @@ impl OpenVpnGui {
                 || status_lower.contains("connection, client connected") {
                 self.log("VPN Connected Successfully!".to_string());
                 self.state = ConnectionState::Connected;
+                self.last_error = None;
+                self.diagnosis = None;
+                self.reconnect_attempts = 0;
                 self.connection_start = Some(Instant::now());
+                self.session_tracker = Some(SessionTracker::start());
+                self.rate_history.start_session(Instant::now());
+                self.disconnect_reason = None;
+                self.last_rx_change = Some(Instant::now());
+                self.tunnel_device = extract_tunnel_device(&status);
                 // Show notification with icon path (16x16)
                 let _ = Notification::new()
                     .summary("OpenVPN3 GUI")
                     .body("VPN Connected Successfully!")
                     .icon(concat!(env!("CARGO_MANIFEST_DIR"), "/icons/openvpn3-gui-16.png"))
                     .show();
-                // Also trigger IP checks
-                return Task::batch(vec![
-                    Task::perform(find_tunnel_ip(), |x| cosmic::Action::App(Message::TunnelIpFound(x))),
-                    Task::perform(fetch_public_ip(), |x| cosmic::Action::App(Message::PublicIpFound(x))),
-                ]);
+                // Also trigger IP checks (public IP is picked up by the next tick)
+                self.public_ip_tracker.request_refresh();
+                let mut tasks = vec![
+                    Task::perform(find_tunnel_ip(self.tunnel_device_name()), |x| cosmic::Action::App(Message::TunnelIpFound(x))),
+                ];
+                if let Some(task) = self.kill_switch_task() {
+                    tasks.push(task);
+                }
+                if self.config_path.as_ref().and_then(|c| self.profiles.get(c)).is_some_and(|p| p.namespace) {
+                    tasks.push(Task::perform(
+                        setup_namespace(self.tunnel_device_name()),
+                        |x| cosmic::Action::App(Message::NamespaceReady(x))
+                    ));
+                }
+                if self.split_tunnel_settings().is_some_and(|s| s.enabled) {
+                    tasks.push(Task::perform(
+                        apply_split_tunnel(self.tunnel_device_name()),
+                        |x| cosmic::Action::App(Message::SplitTunnelApplied(x))
+                    ));
+                }
+                return Task::batch(tasks);
             }
             
             // Check for authentication requirements
             if (status_lower.contains("challenge") || status_lower.contains("enter") && status_lower.contains("token"))
                 && !self.is_asking_2fa {
                 self.is_asking_2fa = true;
+                self.auth_started.get_or_insert_with(Instant::now);
                 self.log("2FA/Challenge required".to_string());
             }
             
@@ -385,16 +1321,27 @@ impl OpenVpnGui {
             if status_lower.contains("auth_pending") 
                 || status_lower.contains("web based authentication")
                 || status_lower.contains("awaiting external authentication") {
-                if !self.logs.iter().any(|l| l.contains("SSO authentication")) {
+                self.auth_started.get_or_insert_with(Instant::now);
+                if !self.logs.iter().any(|r| r.message.contains("SSO authentication")) {
                     self.log("Complete SSO authentication in your browser...".to_string());
                 }
             }
             
             // Check for failures
-            if status_lower.contains("auth_failed") || status_lower.contains("authentication failed") {
-                self.log("Authentication failed".to_string());
-                self.state = ConnectionState::Disconnected;
-                self.session_path = None;
+            self.consider_diagnosis(diagnose_log(&status));
+            if let Some(error) = VpnError::classify(&status).filter(|e| {
+                matches!(
+                    e,
+                    VpnError::AuthFailed { .. } | VpnError::CertificateExpired { .. } | VpnError::NetworkUnreachable { .. }
+                )
+            }) {
+                self.log_with(LogLevel::Error, LogSource::Openvpn3, error.title().to_string());
+                self.last_error = Some(error);
+                // Stop the half-open session; VpnStopped cleans up as for any other ending
+                self.connect_failed = true;
+                if let Some(path) = self.session_path.clone() {
+                    return Task::perform(stop_vpn_by_path(path), |x| cosmic::Action::App(Message::VpnStopped(x)));
+                }
             }
         }
         Task::none()
@@ -407,23 +1354,68 @@ impl OpenVpnGui {
         Task::none()
     }
 
-    fn handle_public_ip(&mut self, ip: Option<String>) -> Task<Message> {
-        if let Some(ip) = ip {
-            self.public_ip = ip;
+    fn handle_public_ip(&mut self, lookup: PublicIpLookup) -> Task<Message> {
+        self.public_ip_tracker.finish();
+        match lookup.ip {
+            Some(ip) => self.public_ip = ip,
+            None if self.public_ip == "Checking..." => self.public_ip = "Unknown".to_string(),
+            None => {}
         }
+        if let Some(msg) = &lookup.disagreement {
+            if Some(msg) != self.public_ip_disagreement.as_ref() {
+                self.log(msg.clone());
+            }
+        }
+        self.public_ip_disagreement = lookup.disagreement;
+        if lookup.problem.is_some() && lookup.problem != self.public_ip_problem {
+            self.log_with(LogLevel::Warn, LogSource::App, lookup.problem.clone().unwrap_or_default());
+        }
+        self.public_ip_problem = lookup.problem;
         Task::none()
     }
 
-    fn handle_save_logs(&mut self) -> Task<Message> {
-        let logs_content = self.logs.join("\n");
+    /// Ask where to save an export, starting in the last folder used
+    fn save_dialog(
+        &self,
+        title: &'static str,
+        name: &str,
+        formats: &'static [ExportFormat],
+        chosen: fn(Option<std::path::PathBuf>) -> Message,
+    ) -> Task<Message> {
+        let dir = self
+            .settings
+            .last_export_dir
+            .clone()
+            .filter(|d| d.is_dir())
+            .unwrap_or_else(default_export_dir);
         let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let filename = format!("openvpn_logs_{}.txt", timestamp);
-        
-        if let Err(e) = std::fs::write(&filename, logs_content) {
-            self.log(format!("Failed to save logs: {}", e));
-        } else {
-            self.log(format!("Logs saved to: {}", filename));
+        let file_name = format!("{}_{}.{}", name, timestamp, formats[0].extension());
+        Task::perform(pick_save_path(title, dir, file_name, formats), move |x| cosmic::Action::App(chosen(x)))
+    }
+
+    /// Write an export through the redactor and remember its folder
+    fn write_export(&mut self, what: &str, path: &std::path::Path, format: ExportFormat, content: &str) {
+        let written = write_redacted(path, format, content, &self.redactor);
+        self.finish_export(what, path, written);
+    }
+
+    /// Report where an export went and remember its folder
+    fn finish_export(&mut self, what: &str, path: &std::path::Path, written: std::io::Result<()>) {
+        if let Err(e) = written {
+            self.log_with(LogLevel::Error, LogSource::App, format!("Failed to save {}: {}", what, e));
+            return;
+        }
+        self.log(format!("Saved {} to: {}", what, path.display()));
+        if let Some(dir) = path.parent() {
+            self.settings.last_export_dir = Some(dir.to_path_buf());
+            self.settings.save();
         }
+    }
+
+    fn handle_save_logs(&mut self, path: std::path::PathBuf) -> Task<Message> {
+        let format = ExportFormat::from_path(&path, &ExportFormat::LOG_FORMATS);
+        let logs_content = logs_as(format, &self.logs, &self.redactor);
+        self.write_export("logs", &path, format, &logs_content);
         Task::none()
     }
 
@@ -435,21 +1427,30 @@ impl OpenVpnGui {
     fn handle_submit_code(&mut self) -> Task<Message> {
         if let Some(path) = self.session_path.clone() {
             let code_clone = self.input_code.clone();
-            self.log(format!("Submitting challenge response: {}", code_clone));
+            // Masked wherever openvpn3 or the server echoes it back
+            self.redactor.add_secret(&code_clone);
+            self.log("Submitting challenge response".to_string());
             Task::perform(submit_challenge(path, code_clone), |x| cosmic::Action::App(Message::AuthCodeResult(x)))
         } else {
             Task::none()
         }
     }
 
-    fn handle_auth_result(&mut self, res: Result<String, String>) -> Task<Message> {
+    fn handle_auth_result(&mut self, res: Result<String, VpnError>) -> Task<Message> {
         match res {
             Ok(out) => { 
                 self.log(format!("Auth Result: {}", out)); 
                 self.input_code.clear(); 
                 self.is_asking_2fa = false; 
+                // Back to the transport phase with a fresh budget
+                self.auth_started = None;
+                self.connect_started = Some(Instant::now());
+            }
+            Err(e) => {
+                self.log_with(LogLevel::Error, LogSource::App, format!("Auth Error: {}", e));
+                self.consider_diagnosis(diagnose_log(e.source()));
+                self.last_error = Some(e);
             }
-            Err(e) => self.log(format!("Auth Error: {}", e)),
         }
         Task::none()
     }
@@ -466,80 +1467,38 @@ impl OpenVpnGui {
 
     fn handle_select_recent(&mut self, path: String) -> Task<Message> {
         self.config_path = Some(path.clone());
-        self.add_to_recent_configs(path);
+        self.profiles.add_recent(path);
         self.log(format!("Selected recent config: {}", self.config_path.as_ref().unwrap()));
         Task::none()
     }
 
     fn handle_clear_recent(&mut self) -> Task<Message> {
-        self.recent_configs.clear();
-        Self::save_recent_configs(&self.recent_configs);
+        self.profiles.clear_recent();
         self.log("Recent configs cleared".to_string());
         Task::none()
     }
 
-    // Helper methods for recent configs
-    fn load_recent_configs() -> Vec<String> {
-        let config_path = Self::get_config_file_path();
-        if let Ok(contents) = std::fs::read_to_string(&config_path) {
-            contents
-                .lines()
-                .map(|s| s.to_string())
-                .filter(|s| !s.is_empty())
-                .take(10) // Keep only last 10
-                .collect()
-        } else {
-            Vec::new()
-        }
-    }
-
-    fn save_recent_configs(configs: &[String]) {
-        let config_path = Self::get_config_file_path();
-        let contents = configs.join("\n");
-        let _ = std::fs::write(&config_path, contents);
-    }
-
-    fn get_config_file_path() -> std::path::PathBuf {
-        if let Some(mut path) = dirs::config_dir() {
-            path.push("openvpn-gui");
-            std::fs::create_dir_all(&path).ok();
-            path.push("recent_configs.txt");
-            path
-        } else {
-            std::path::PathBuf::from("recent_configs.txt")
-        }
-    }
-
-    fn add_to_recent_configs(&mut self, path: String) {
-        // Remove if already exists (to move it to top)
-        self.recent_configs.retain(|p| p != &path);
-        
-        // Add to front
-        self.recent_configs.insert(0, path);
-        
-        // Keep only last 10
-        self.recent_configs.truncate(10);
-        
-        // Save to disk
-        Self::save_recent_configs(&self.recent_configs);
-    }
-    
     fn update_tray(&mut self) {
         if let Some(ref mut tray) = self.tray {
             // Update icon based on connection state
             let connected = self.state == ConnectionState::Connected;
             tray.update_icon(connected);
+            tray.update_health(self.health.as_ref().map(|h| h.level));
             
             // Update tooltip with status
             let tooltip = match self.state {
                 ConnectionState::Connected => {
-                    if let Some(start) = self.connection_start {
+                    let base = if let Some(start) = self.connection_start {
                         let duration = start.elapsed();
                         let mins = duration.as_secs() / 60;
                         let secs = duration.as_secs() % 60;
                         format!("OpenVPN3 GUI - Connected ({}:{:02})", mins, secs)
                     } else {
                         "OpenVPN3 GUI - Connected".to_string()
+                    };
+                    match &self.health {
+                        Some(health) => format!("{} - {}", base, health.summary()),
+                        None => base,
                     }
                 }
                 ConnectionState::Connecting => "OpenVPN3 GUI - Connecting...".to_string(),
@@ -549,47 +1508,550 @@ impl OpenVpnGui {
         }
     }
 
-    fn handle_save_session_report(&mut self) -> Task<Message> {
-        // Compose session report
-        let config = self.config_path.clone().unwrap_or_else(|| "-".to_string());
-        let duration = if let Some(start) = self.connection_start {
-            let elapsed = start.elapsed().as_secs();
-            format!("{:02}:{:02}:{:02}", elapsed / 3600, (elapsed % 3600) / 60, elapsed % 60)
-        } else {
-            "-".to_string()
+    /// Capture counters, routes and DNS, then write the report
+    fn handle_save_session_report(&mut self, path: std::path::PathBuf) -> Task<Message> {
+        Task::perform(capture(self.session_path.clone()), move |snapshot| {
+            cosmic::Action::App(Message::SessionReportReady(path, snapshot))
+        })
+    }
+
+    fn handle_session_report_ready(&mut self, path: std::path::PathBuf, snapshot: NetworkSnapshot) -> Task<Message> {
+        let report = self.session_report(snapshot);
+        let format = ExportFormat::from_path(&path, &ExportFormat::REPORT_FORMATS);
+        let content = report_as(format, &report, &self.redactor);
+        self.write_export("session report", &path, format, &content);
+        Task::none()
+    }
+
+    /// Gather the system side in the background, then assemble the bundle
+    fn handle_create_bundle(&mut self, path: std::path::PathBuf) -> Task<Message> {
+        let session_path = self.session_path.clone();
+        self.log("Collecting diagnostics...".to_string());
+        Task::perform(
+            async move {
+                let snapshot = capture(session_path.clone()).await;
+                let files = collect_system_files(session_path).await;
+                (snapshot, files)
+            },
+            move |(snapshot, files)| cosmic::Action::App(Message::BundleReady(path, snapshot, files)),
+        )
+    }
+
+    /// Add the session report, logs, settings and profile to the system files and write the tarball
+    fn handle_bundle_ready(
+        &mut self,
+        path: std::path::PathBuf,
+        snapshot: NetworkSnapshot,
+        mut files: Vec<BundleFile>,
+    ) -> Task<Message> {
+        let report = self.session_report(snapshot).redacted(&self.redactor);
+        let session_log = self
+            .logs
+            .iter()
+            .filter(|r| r.source == LogSource::Openvpn3)
+            .map(|r| r.format_full())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let config_path = self.config_path.clone().unwrap_or_default();
+        let config = std::fs::read_to_string(&config_path)
+            .unwrap_or_else(|e| format!("Failed to read {}: {}", config_path, e));
+        let profile = self
+            .profiles
+            .get(&config_path)
+            .map(|p| self.redactor.redacted_json(p))
+            .unwrap_or_else(|| "No saved profile settings".to_string());
+
+        files.extend([
+            BundleFile::redacted("report.json", "Session report", report.to_json()),
+            BundleFile::redacted("report.html", "Session report with traffic graph", report.to_html()),
+            BundleFile::new("logs/app.log", "All log records in memory", self.logs.iter().map(|r| r.format_full()).collect::<Vec<_>>().join("\n")),
+            BundleFile::new("logs/session.log", "openvpn3 session log", session_log),
+            BundleFile::new("config/profile.ovpn", "Profile config (keys and credentials removed)", config),
+            BundleFile::redacted("config/profile.json", "Profile settings", profile),
+            BundleFile::redacted("config/settings.json", "App settings", self.redactor.redacted_json(&self.settings)),
+        ]);
+
+        let written = write_bundle(&path, &files, &self.redactor);
+        self.finish_export("diagnostics bundle", &path, written);
+        Task::none()
+    }
+
+    /// Everything known about the current session
+    fn session_report(&self, network: NetworkSnapshot) -> SessionReport {
+        let config_path = self.config_path.clone().unwrap_or_default();
+        let profile = self.profiles.get(&config_path);
+        let remotes = std::fs::read_to_string(&config_path)
+            .map(|config| {
+                parse_remotes(&config)
+                    .iter()
+                    .map(|r| format!("{}:{}/{}", r.host, r.port, if r.tcp { "tcp" } else { "udp" }))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let elapsed = self.connection_start.map(|start| start.elapsed());
+
+        SessionReport {
+            profile: ProfileInfo {
+                name: std::path::Path::new(&config_path)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+                config_path: config_path.clone(),
+                remotes,
+                namespace: profile.is_some_and(|p| p.namespace),
+                split_tunnel: self.split_tunnel_settings().is_some_and(|s| s.enabled),
+                kill_switch: self.kill_switch_active,
+            },
+            timing: Timing {
+                generated: chrono::Local::now().to_rfc3339(),
+                connected_since: elapsed
+                    .and_then(|e| chrono::Duration::from_std(e).ok())
+                    .map(|e| (chrono::Local::now() - e).to_rfc3339()),
+                duration_secs: elapsed.map(|e| e.as_secs()),
+                state: format!("{:?}", self.state),
+            },
+            addresses: Addresses {
+                tunnel_device: self.tunnel_device.clone(),
+                tunnel_ip: self.tunnel_ip.clone(),
+                vpn_gateway: self.vpn_gateway.clone(),
+                public_ip: self.public_ip.clone(),
+            },
+            traffic: Traffic {
+                bytes_in: self.stats.bytes_in,
+                bytes_out: self.stats.bytes_out,
+                rate_in: self.stats.rate_in,
+                rate_out: self.stats.rate_out,
+                history: self.rate_history.series(Instant::now(), GraphWindow::Session.span()),
+            },
+            latency: self
+                .probe_engine
+                .stats()
+                .into_iter()
+                .map(|(target, stats)| LatencySummary {
+                    target: target.name.clone(),
+                    role: format!("{:?}", target.role),
+                    min_ms: stats.min_ms,
+                    avg_ms: stats.avg_ms,
+                    max_ms: stats.max_ms,
+                    jitter_ms: stats.jitter_ms,
+                    loss_pct: stats.loss_pct,
+                    samples: stats.samples,
+                })
+                .collect(),
+            health: self.health.as_ref().map(|h| HealthSummary {
+                level: format!("{:?}", h.level),
+                reasons: h.reasons.clone(),
+            }),
+            network,
+            log: self.logs.iter().map(ReportLogLine::from).collect(),
+        }
+    }
+
+    fn handle_vpn_gateway(&mut self, gw: Option<String>) -> Task<Message> {
+        if let Some(gw) = gw {
+            self.log_with(LogLevel::Info, LogSource::Probe, format!("VPN gateway: {}", gw));
+            self.vpn_gateway = Some(gw);
+        }
+        Task::none()
+    }
+
+    fn handle_probe_completed(&mut self, name: String, rtt: Option<f32>) -> Task<Message> {
+        self.probe_engine.record(&name, rtt);
+        // Session history averages the main target's latency
+        let primary = self.probe_engine.primary_stats().is_some_and(|(t, _)| t.name == name);
+        if let (true, Some(rtt), Some(tracker)) = (primary, rtt, &mut self.session_tracker) {
+            tracker.record_latency(rtt);
+        }
+        Task::none()
+    }
+
+    fn handle_dns_checked(&mut self, ok: bool) -> Task<Message> {
+        if self.state == ConnectionState::Connected {
+            self.dns_ok = Some(ok);
+        }
+        Task::none()
+    }
+
+    /// Re-assess connection health and apply the restart policy
+    fn update_health(&mut self) -> Option<Task<Message>> {
+        if self.state != ConnectionState::Connected || self.session_paused {
+            return None;
+        }
+
+        let inputs = HealthInputs {
+            probe: self
+                .probe_engine
+                .primary_stats()
+                .map(|(target, stats)| (target.name.clone(), stats)),
+            stalled_for: self.last_rx_change.map(|t| t.elapsed()),
+            // Same conditions under which the tunnel probes run
+            probe_traffic: self.settings.probe_allowed(Probe::Latency) && !self.namespace_active && !self.session_paused,
+            dns_ok: self.dns_ok,
         };
-        let tunnel_ip = &self.tunnel_ip;
-        let public_ip = &self.public_ip;
-        let stats = &self.stats;
-        let log_excerpt = self.logs.iter().rev().take(20).cloned().collect::<Vec<_>>().into_iter().rev().collect::<Vec<_>>().join("\n");
-        let report = format!(
-            "OpenVPN3 Session Report\n\
-            Config: {}\n\
-            Duration: {}\n\
-            Tunnel IP: {}\n\
-            Public IP: {}\n\
-            Bytes In: {}\n\
-            Bytes Out: {}\n\
-            Log Excerpt:\n{}\n",
-            config, duration, tunnel_ip, public_ip, stats.bytes_in, stats.bytes_out, log_excerpt
-        );
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let filename = format!("openvpn_session_report_{}.txt", timestamp);
-        if let Err(e) = std::fs::write(&filename, report) {
-            self.log(format!("Failed to save session report: {}", e));
+        let report = assess(&inputs, &self.settings.health);
+
+        if self.health.as_ref().map(|h| h.level) != Some(report.level) {
+            let level = match report.level {
+                HealthLevel::Good => LogLevel::Info,
+                HealthLevel::Degraded => LogLevel::Warn,
+                HealthLevel::Broken => LogLevel::Error,
+            };
+            self.log_with(level, LogSource::Probe, format!("Connection health: {}", report.summary()));
+        }
+        if report.level == HealthLevel::Broken {
+            self.broken_since.get_or_insert_with(Instant::now);
         } else {
-            // Get absolute path
-            let abs_path = std::env::current_dir()
-                .map(|p| p.join(&filename))
-                .unwrap_or_else(|_| std::path::PathBuf::from(&filename));
-            self.log(format!("Session report saved to: {}", abs_path.display()));
+            self.broken_since = None;
+        }
+        self.health = Some(report);
+
+        let restart_after = self.settings.health.restart_after_broken_secs;
+        let broken_for = self.broken_since.map(|t| t.elapsed());
+        if restart_after > 0
+            && !self.restart_pending
+            && broken_for.is_some_and(|d| d >= Duration::from_secs(restart_after)) {
+            if let Some(path) = self.session_path.clone() {
+                self.log_with(LogLevel::Warn, LogSource::App, format!("Connection broken for {}s - restarting session", restart_after));
+                self.restart_pending = true;
+                return Some(Task::perform(stop_vpn_by_path(path), |x| cosmic::Action::App(Message::VpnStopped(x))));
+            }
+        }
+        None
+    }
+
+    /// Network triggers of the selected profile
+    pub fn network_triggers(&self) -> NetworkTriggers {
+        self.config_path
+            .as_ref()
+            .and_then(|c| self.profiles.get(c))
+            .map(|p| p.triggers.clone())
+            .unwrap_or_default()
+    }
+
+    /// Quota of the selected profile
+    pub fn quota(&self) -> Quota {
+        self.config_path.as_deref().map(|c| self.quota_of(c)).unwrap_or_default()
+    }
+
+    fn quota_of(&self, profile: &str) -> Quota {
+        self.profiles.get(profile).map(|p| p.quota.clone()).unwrap_or_default()
+    }
+
+    /// Usage of the selected profile in its quota period
+    pub fn quota_usage(&self) -> Usage {
+        self.config_path
+            .as_ref()
+            .map(|c| self.usage.period_usage(c, self.quota().period, today()))
+            .unwrap_or_default()
+    }
+
+    fn update_quota(&mut self, change: impl FnOnce(&mut Quota)) -> Task<Message> {
+        if let Some(config) = self.config_path.clone() {
+            change(&mut self.profiles.get_or_insert(&config).quota);
+            self.profiles.save();
         }
         Task::none()
     }
 
-    // Add a new message handler for latency
-    fn handle_latency_checked(&mut self, lat: Option<u32>) -> Task<Message> {
-        self.latency_ms = lat;
+    fn update_triggers(&mut self, change: impl FnOnce(&mut NetworkTriggers)) -> Task<Message> {
+        if let Some(config) = self.config_path.clone() {
+            change(&mut self.profiles.get_or_insert(&config).triggers);
+            self.profiles.save();
+        }
         Task::none()
     }
+
+    fn handle_network_event(&mut self, event: NetworkEvent) -> Task<Message> {
+        let triggers = self.network_triggers();
+        match event {
+            NetworkEvent::UplinkChanged(what) => {
+                // Whatever the session does, the public address may have changed
+                self.public_ip_tracker.request_refresh();
+                self.pending_rule_check = Some(Instant::now());
+                if self.state == ConnectionState::Connected
+                    && !self.session_paused
+                    && triggers.restart_on_uplink_change {
+                    if self.pending_uplink_change.is_none() {
+                        self.log(format!("Network changed ({})", what));
+                    }
+                    // Debounced: a Wi-Fi switch emits a burst of events
+                    self.pending_uplink_change = Some(Instant::now());
+                }
+                Task::none()
+            }
+            NetworkEvent::Sleep => {
+                let Some(path) = self.session_path.clone() else {
+                    return Task::none();
+                };
+                if self.state == ConnectionState::Disconnected {
+                    return Task::none();
+                }
+                match triggers.on_sleep {
+                    SleepAction::Nothing => Task::none(),
+                    SleepAction::Pause => {
+                        self.log("System going to sleep - pausing session".to_string());
+                        self.session_paused = true;
+                        self.pending_uplink_change = None;
+                        Task::perform(manage_session(path, "pause"), |x| cosmic::Action::App(Message::SessionManaged(x)))
+                    }
+                    SleepAction::Disconnect => {
+                        // Not a user disconnect: the kill switch stays up while asleep
+                        self.log("System going to sleep - disconnecting".to_string());
+                        self.disconnected_for_sleep = true;
+                        Task::perform(stop_vpn_by_path(path), |x| cosmic::Action::App(Message::VpnStopped(x)))
+                    }
+                }
+            }
+            NetworkEvent::Wake => {
+                self.public_ip_tracker.request_refresh();
+                self.pending_rule_check = Some(Instant::now());
+                if self.disconnected_for_sleep {
+                    self.disconnected_for_sleep = false;
+                    if triggers.on_wake != WakeAction::Nothing && self.state == ConnectionState::Disconnected {
+                        self.log("System resumed - reconnecting".to_string());
+                        return self.handle_toggle_vpn();
+                    }
+                    return Task::none();
+                }
+                if !self.session_paused {
+                    return Task::none();
+                }
+                let Some(path) = self.session_path.clone() else {
+                    return Task::none();
+                };
+                let action = match triggers.on_wake {
+                    WakeAction::Nothing => {
+                        self.log("System resumed - session left paused".to_string());
+                        return Task::none();
+                    }
+                    WakeAction::Resume => "resume",
+                    WakeAction::Restart => "restart",
+                };
+                self.log(format!("System resumed - {} session", action));
+                self.session_paused = false;
+                self.reset_session_measurements();
+                Task::perform(manage_session(path, action), |x| cosmic::Action::App(Message::SessionManaged(x)))
+            }
+        }
+    }
+
+    /// Restart the session once the uplink has been quiet for a few seconds
+    fn restart_after_uplink_change(&mut self) -> Option<Task<Message>> {
+        let changed_at = self.pending_uplink_change?;
+        if changed_at.elapsed() < Duration::from_secs(3) {
+            return None;
+        }
+        self.pending_uplink_change = None;
+        let path = self.session_path.clone()?;
+        if self.state != ConnectionState::Connected || self.restart_pending {
+            return None;
+        }
+        self.log("Uplink changed - restarting session".to_string());
+        self.reset_session_measurements();
+        Some(Task::perform(manage_session(path, "restart"), |x| cosmic::Action::App(Message::SessionManaged(x))))
+    }
+
+    /// Forget probe windows and health history that describe the old path
+    fn reset_session_measurements(&mut self) {
+        self.probe_engine.reset_tunnel_targets();
+        self.vpn_gateway = None;
+        self.health = None;
+        self.broken_since = None;
+        self.last_rx_change = None;
+        self.dns_ok = None;
+        self.last_dns_check = None;
+    }
+
+    /// Evaluate the rules against fresh facts; act only when the decision changes,
+    /// so a manual connect or disconnect is not undone on the same network
+    fn handle_network_facts(&mut self, facts: NetworkFacts) -> Task<Message> {
+        let decision = evaluate(&self.profiles.rules, &facts);
+        if self.network_facts.as_ref() != Some(&facts) {
+            self.log(format!("Network: {}", facts.summary()));
+        }
+        self.network_facts = Some(facts);
+        if self.rule_decision.as_ref() == Some(&decision) {
+            return Task::none();
+        }
+        self.log(format!("Network rule: {}", decision.summary()));
+        let action = decision.action;
+        let profile = decision.profile.clone();
+        let rule = decision.rule.clone().unwrap_or_default();
+        self.rule_decision = Some(decision);
+
+        match action {
+            Some(RuleAction::Connect) if self.state == ConnectionState::Disconnected => {
+                if let Some(profile) = profile {
+                    self.config_path = Some(profile.clone());
+                    self.profiles.add_recent(profile);
+                }
+                self.handle_toggle_vpn()
+            }
+            Some(RuleAction::Disconnect) if self.state != ConnectionState::Disconnected => {
+                // A trusted network counts as a deliberate disconnect (lifts the kill switch)
+                self.pending_uplink_change = None;
+                self.disconnect_reason = Some(format!("Trusted-network rule \"{}\"", rule));
+                self.handle_toggle_vpn()
+            }
+            _ => Task::none(),
+        }
+    }
+
+    fn start_connect(&mut self, config: String) -> Task<Message> {
+        self.log(format!("Starting VPN with {}", config));
+        self.session_profile = Some(config.clone());
+        let (task, handle) = Task::perform(start_vpn(config), |x| cosmic::Action::App(Message::VpnStarted(x))).abortable();
+        self.connect_handle = Some(handle);
+        task
+    }
+
+    fn portal_check_enabled(&self) -> bool {
+        self.settings.captive_portal.enabled && self.settings.probe_allowed(Probe::CaptivePortal)
+    }
+
+    fn portal_check_task(&mut self) -> Task<Message> {
+        self.portal_check_running = true;
+        self.last_portal_check = Some(Instant::now());
+        Task::perform(
+            check_portal(self.settings.captive_portal.clone()),
+            |x| cosmic::Action::App(Message::PortalChecked(x))
+        )
+    }
+
+    fn portal_check_due(&mut self) -> Option<Task<Message>> {
+        if self.state != ConnectionState::Connecting || self.portal_check_running || !self.portal_check_enabled() {
+            return None;
+        }
+        let settings = &self.settings.captive_portal;
+        if self.portal_url.is_some() {
+            let retry = Duration::from_secs(settings.retry_secs.max(1));
+            if self.session_path.is_none() && self.last_portal_check.is_none_or(|t| t.elapsed() >= retry) {
+                return Some(self.portal_check_task());
+            }
+            return None;
+        }
+        let timeout = Duration::from_secs(settings.connecting_timeout_secs);
+        if self.session_path.is_some()
+            && !self.portal_checked_during_connect
+            && self.connect_started.is_some_and(|t| t.elapsed() >= timeout) {
+            self.portal_checked_during_connect = true;
+            self.log(format!("Still connecting after {}s - checking for a captive portal", timeout.as_secs()));
+            return Some(self.portal_check_task());
+        }
+        None
+    }
+
+    fn handle_portal_checked(&mut self, result: PortalCheck) -> Task<Message> {
+        self.portal_check_running = false;
+        // Cancelled (or connected) while the check was running
+        if self.state != ConnectionState::Connecting {
+            return Task::none();
+        }
+
+        match result {
+            PortalCheck::Portal(url) => {
+                if self.portal_url.is_none() {
+                    self.log_with(LogLevel::Warn, LogSource::App, format!("Captive portal detected - sign in at {}; the VPN connects once it is cleared", url));
+                    let _ = Notification::new()
+                        .summary("OpenVPN3 GUI")
+                        .body("Captive portal detected. Sign in to the network to connect.")
+                        .icon(concat!(env!("CARGO_MANIFEST_DIR"), "/icons/openvpn3-gui-16.png"))
+                        .show();
+                    let _ = webbrowser::open(&url);
+                }
+                self.portal_url = Some(url);
+                // A session stuck behind the portal will not get through; drop it and retry later
+                if let Some(path) = self.session_path.clone() {
+                    return Task::perform(stop_vpn_by_path(path), |x| cosmic::Action::App(Message::VpnStopped(x)));
+                }
+                Task::none()
+            }
+            PortalCheck::Clear | PortalCheck::Failed(_) => {
+                if let PortalCheck::Failed(e) = &result {
+                    self.log_with(LogLevel::Error, LogSource::App, format!("Captive portal check failed ({})", e));
+                }
+                if self.portal_url.take().is_some() {
+                    self.log("Captive portal cleared".to_string());
+                    self.connect_started = Some(Instant::now());
+                }
+                // Checks made during a slow connect leave the session alone
+                match (&self.session_path, self.config_path.clone()) {
+                    (None, Some(config)) => self.start_connect(config),
+                    _ => Task::none(),
+                }
+            }
+        }
+    }
+
+    /// Tear down a session that has used up its transport or auth budget
+    fn check_connect_deadline(&mut self) -> Option<Task<Message>> {
+        // A captive portal holds the attempt on purpose; it has its own retry loop. A
+        // failed attempt is already being stopped.
+        if self.state != ConnectionState::Connecting
+            || self.portal_url.is_some()
+            || self.connect_timed_out
+            || self.connect_failed
+        {
+            return None;
+        }
+        let (phase, since, budget) = match self.auth_started {
+            Some(since) => ("authentication", since, self.settings.connect.auth_timeout_secs),
+            None => ("connection", self.connect_started?, self.settings.connect.transport_timeout_secs),
+        };
+        if budget == 0 || since.elapsed() < Duration::from_secs(budget) {
+            return None;
+        }
+
+        self.log_with(LogLevel::Warn, LogSource::App, format!("No {} after {}s - giving up on this attempt", phase, budget));
+        self.last_error = Some(VpnError::Timeout {
+            source: format!("The {} phase did not complete within {}s", phase, budget),
+        });
+        self.connect_timed_out = true;
+        if let Some(handle) = self.connect_handle.take() {
+            handle.abort();
+        }
+        if let Some(path) = self.session_path.clone() {
+            Some(Task::perform(stop_vpn_by_path(path), |x| cosmic::Action::App(Message::VpnStopped(x))))
+        } else {
+            self.config_path
+                .clone()
+                .map(|cfg| Task::perform(stop_vpn_by_config(cfg), |x| cosmic::Action::App(Message::VpnStopped(x))))
+        }
+    }
+
+    /// Reconnect policy after a connect timeout: retry while auto-reconnect allows it
+    fn reconnect_after_timeout(&mut self) -> Task<Message> {
+        let max = self.settings.connect.max_reconnect_attempts;
+        if !self.auto_reconnect || self.reconnect_attempts >= max {
+            if self.auto_reconnect {
+                self.log_with(LogLevel::Warn, LogSource::App, format!("Giving up after {} reconnect attempts", max));
+            }
+            self.reconnect_attempts = 0;
+            return Task::none();
+        }
+        self.reconnect_attempts += 1;
+        self.log(format!("Auto-reconnect: attempt {} of {}", self.reconnect_attempts, max));
+        self.handle_toggle_vpn()
+    }
+
+    /// Show a diagnosis card unless it is already shown or was dismissed
+    fn consider_diagnosis(&mut self, diagnosis: Option<Diagnosis>) {
+        let Some(diagnosis) = diagnosis else {
+            return;
+        };
+        if self.dismissed_diagnoses.contains(&diagnosis.rule_id)
+            || self.diagnosis.as_ref().is_some_and(|d| d.rule_id == diagnosis.rule_id) {
+            return;
+        }
+        self.log_with(LogLevel::Warn, LogSource::App, format!("Diagnosis: {}", diagnosis.cause));
+        self.diagnosis = Some(diagnosis);
+    }
+}
+
+/// Scroll the log pane to the newest record
+fn scroll_logs_to_end() -> Task<Message> {
+    cosmic::iced::widget::scrollable::snap_to(
+        crate::ui::log_pane_id(),
+        cosmic::iced::widget::scrollable::RelativeOffset::END,
+    )
 }
@@ -0,0 +1,130 @@
+// Diagnostics bundle - everything support asks for, in one redacted tar.gz
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::redact::Redactor;
+use crate::vpn::process::run;
+
+/// Deadline for each command
+const BUNDLE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Top-level folder inside the archive
+const BUNDLE_ROOT: &str = "openvpn-gui-diagnostics";
+
+/// One file in the bundle
+#[derive(Debug, Clone)]
+pub struct BundleFile {
+    /// Path inside the bundle, e.g. "system/routes.txt"
+    pub name: String,
+    /// What it is, for the manifest
+    pub description: String,
+    pub content: String,
+    /// Redacted field by field when it was built (JSON, HTML); the text pass would break it
+    pub redacted: bool,
+}
+
+impl BundleFile {
+    pub fn new(name: &str, description: &str, content: String) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            content,
+            redacted: false,
+        }
+    }
+
+    /// A file whose content is redacted already
+    pub fn redacted(name: &str, description: &str, content: String) -> Self {
+        Self {
+            redacted: true,
+            ..Self::new(name, description, content)
+        }
+    }
+}
+
+/// Output of a command (stdout, or the error) with the command line on top
+async fn command_output(program: &str, args: &[&str]) -> String {
+    let output = match run(program, args, BUNDLE_TIMEOUT).await {
+        Ok(out) if out.stderr.trim().is_empty() => out.stdout,
+        Ok(out) => format!("{}\n--- stderr ---\n{}", out.stdout, out.stderr),
+        Err(e) => format!("Failed: {}\n{}", e, e.stderr()),
+    };
+    format!("$ {} {}\n{}", program, args.join(" "), output)
+}
+
+/// Collect the system side: openvpn3, sessions, networking, kernel and distro
+pub async fn collect_system_files(session_path: Option<String>) -> Vec<BundleFile> {
+    let mut service = command_output("busctl", &["--system", "--no-pager", "list"]).await;
+    // Only the openvpn3 D-Bus services are of interest
+    service = service
+        .lines()
+        .filter(|l| l.starts_with('$') || l.starts_with("NAME") || l.contains("net.openvpn.v3"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut stats = String::new();
+    if let Some(path) = &session_path {
+        stats = command_output("openvpn3", &["session-stats", "--session-path", path]).await;
+    }
+
+    let os_release = tokio::fs::read_to_string("/etc/os-release")
+        .await
+        .unwrap_or_else(|e| format!("Failed to read /etc/os-release: {}", e));
+    let resolv_conf = tokio::fs::read_to_string("/etc/resolv.conf")
+        .await
+        .unwrap_or_else(|e| format!("Failed to read /etc/resolv.conf: {}", e));
+
+    vec![
+        BundleFile::new("openvpn3/version.txt", "openvpn3 version", command_output("openvpn3", &["version"]).await),
+        BundleFile::new("openvpn3/services.txt", "openvpn3 D-Bus services", service),
+        BundleFile::new("openvpn3/sessions.txt", "openvpn3 sessions-list", command_output("openvpn3", &["sessions-list"]).await),
+        BundleFile::new("openvpn3/session-stats.txt", "openvpn3 session-stats of the current session", stats),
+        BundleFile::new("network/routes.txt", "IPv4 routes", command_output("ip", &["route", "show", "table", "all"]).await),
+        BundleFile::new("network/routes6.txt", "IPv6 routes", command_output("ip", &["-6", "route", "show", "table", "all"]).await),
+        BundleFile::new("network/rules.txt", "Policy routing rules", command_output("ip", &["rule", "show"]).await),
+        BundleFile::new("network/addresses.txt", "Interface addresses", command_output("ip", &["addr", "show"]).await),
+        BundleFile::new("network/resolvectl.txt", "systemd-resolved state", command_output("resolvectl", &["status"]).await),
+        BundleFile::new("network/resolv.conf", "/etc/resolv.conf", resolv_conf),
+        BundleFile::new("system/uname.txt", "Kernel", command_output("uname", &["-a"]).await),
+        BundleFile::new("system/os-release.txt", "Distribution", os_release),
+    ]
+}
+
+/// Write `files` (redacted) and a manifest into a gzipped tarball
+pub fn write_bundle(path: &Path, files: &[BundleFile], redactor: &Redactor) -> std::io::Result<()> {
+    let files: Vec<BundleFile> = files
+        .iter()
+        .map(|f| BundleFile {
+            content: if f.redacted { f.content.clone() } else { redactor.redact(&f.content) },
+            ..f.clone()
+        })
+        .collect();
+
+    let mut manifest = format!(
+        "OpenVPN GUI diagnostics bundle\nCreated: {}\nApp version: {}\n\n",
+        chrono::Local::now().to_rfc3339(),
+        env!("CARGO_PKG_VERSION")
+    );
+    for file in &files {
+        manifest.push_str(&format!("{:<32} {:>9} bytes  {}\n", file.name, file.content.len(), file.description));
+    }
+
+    let encoder = GzEncoder::new(std::fs::File::create(path)?, Compression::default());
+    let mut archive = tar::Builder::new(encoder);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let manifest = BundleFile::new("MANIFEST.txt", "This list", manifest);
+    for file in std::iter::once(&manifest).chain(&files) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(file.content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(now);
+        archive.append_data(&mut header, format!("{}/{}", BUNDLE_ROOT, file.name), file.content.as_bytes())?;
+    }
+    archive.into_inner()?.finish()?;
+    Ok(())
+}
@@ -0,0 +1,120 @@
+// Export locations and formats for saved logs and session reports
+
+use std::path::{Path, PathBuf};
+
+use crate::log_files::log_dir;
+use crate::logging::LogBuffer;
+use crate::redact::Redactor;
+use crate::report::SessionReport;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Text,
+    /// One JSON object per log record
+    JsonLines,
+    Json,
+    Markdown,
+    /// Self-contained page with the traffic graph embedded as SVG
+    Html,
+    /// Gzipped tarball (diagnostics bundle)
+    TarGz,
+}
+
+impl ExportFormat {
+    pub const LOG_FORMATS: [ExportFormat; 2] = [ExportFormat::Text, ExportFormat::JsonLines];
+    pub const REPORT_FORMATS: [ExportFormat; 3] = [ExportFormat::Html, ExportFormat::Markdown, ExportFormat::Json];
+    pub const BUNDLE_FORMATS: [ExportFormat; 1] = [ExportFormat::TarGz];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Json => "json",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::TarGz => "tar.gz",
+        }
+    }
+
+    /// Formats a pass over the finished text would corrupt
+    pub fn is_structured(self) -> bool {
+        matches!(self, ExportFormat::Json | ExportFormat::JsonLines | ExportFormat::Html)
+    }
+
+    /// Filter name in the save dialog
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "Plain text",
+            ExportFormat::JsonLines => "JSON Lines",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Html => "HTML",
+            ExportFormat::TarGz => "Gzipped tar archive",
+        }
+    }
+
+    /// Format chosen in the dialog, going by the file's extension (the first offered one otherwise)
+    pub fn from_path(path: &Path, offered: &[ExportFormat]) -> ExportFormat {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        offered
+            .iter()
+            .copied()
+            .find(|f| f.extension().eq_ignore_ascii_case(extension))
+            .unwrap_or(offered[0])
+    }
+}
+
+/// Where the save dialog starts when nothing has been saved yet
+pub fn default_export_dir() -> PathBuf {
+    dirs::document_dir().unwrap_or_else(log_dir)
+}
+
+/// Ask where to save; `formats` become the dialog's file type filters
+pub async fn pick_save_path(
+    title: &'static str,
+    dir: PathBuf,
+    file_name: String,
+    formats: &'static [ExportFormat],
+) -> Option<PathBuf> {
+    let mut dialog = rfd::AsyncFileDialog::new()
+        .set_title(title)
+        .set_directory(dir)
+        .set_file_name(file_name);
+    for format in formats {
+        dialog = dialog.add_filter(format.label(), &[format.extension()]);
+    }
+    let path = dialog.save_file().await?.path().to_path_buf();
+
+    // No extension typed: use the default format's
+    if path.extension().is_none() {
+        Some(path.with_extension(formats[0].extension()))
+    } else {
+        Some(path)
+    }
+}
+
+/// Saved-logs content in the given format. Records are redacted before they are
+/// formatted, since a secret escaped into JSON no longer looks like one.
+pub fn logs_as(format: ExportFormat, logs: &LogBuffer, redactor: &Redactor) -> String {
+    let records = logs.iter().map(|r| redactor.redact_record(r.clone()));
+    match format {
+        ExportFormat::JsonLines | ExportFormat::Json => records
+            .map(|r| r.to_json().to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ExportFormat::Text | ExportFormat::Markdown | ExportFormat::Html | ExportFormat::TarGz => records
+            .map(|r| r.format_line())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// Session report content in the given format, rendered from a redacted copy
+pub fn report_as(format: ExportFormat, report: &SessionReport, redactor: &Redactor) -> String {
+    let report = report.redacted(redactor);
+    match format {
+        ExportFormat::Json | ExportFormat::JsonLines => report.to_json(),
+        ExportFormat::Html => report.to_html(),
+        ExportFormat::Markdown | ExportFormat::Text | ExportFormat::TarGz => report.to_markdown(),
+    }
+}
@@ -0,0 +1,369 @@
+// Connection history - one JSON line per finished session in $XDG_STATE_HOME/openvpn-gui/history.jsonl
+
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::log_files::state_dir;
+
+/// A finished session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Identifies the entry while the app runs (assigned by the store, not saved):
+    /// sessions started in the same second share `started`
+    #[serde(skip)]
+    pub id: u64,
+    /// Unix time the session connected
+    pub started: i64,
+    pub ended: i64,
+    /// Config path of the profile
+    pub profile: String,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Highest rates seen (bytes/sec)
+    pub peak_rate_in: f32,
+    pub peak_rate_out: f32,
+    pub avg_latency_ms: Option<f32>,
+    pub disconnect_reason: String,
+}
+
+impl HistoryEntry {
+    pub fn duration_secs(&self) -> u64 {
+        (self.ended - self.started).max(0) as u64
+    }
+
+    /// File name of the profile without the extension
+    pub fn profile_name(&self) -> String {
+        std::path::Path::new(&self.profile)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.profile.clone())
+    }
+
+    pub fn started_local(&self) -> String {
+        format_time(self.started)
+    }
+
+    pub fn ended_local(&self) -> String {
+        format_time(self.ended)
+    }
+
+    fn started_date(&self) -> Option<NaiveDate> {
+        Local.timestamp_opt(self.started, 0).single().map(|t| t.date_naive())
+    }
+}
+
+fn format_time(unix: i64) -> String {
+    Local
+        .timestamp_opt(unix, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Running totals for the session in progress
+#[derive(Debug, Clone)]
+pub struct SessionTracker {
+    started: i64,
+    peak_rate_in: f32,
+    peak_rate_out: f32,
+    latency_total_ms: f64,
+    latency_samples: u32,
+}
+
+impl SessionTracker {
+    pub fn start() -> Self {
+        Self {
+            started: Local::now().timestamp(),
+            peak_rate_in: 0.0,
+            peak_rate_out: 0.0,
+            latency_total_ms: 0.0,
+            latency_samples: 0,
+        }
+    }
+
+    pub fn record_rates(&mut self, rate_in: f32, rate_out: f32) {
+        self.peak_rate_in = self.peak_rate_in.max(rate_in);
+        self.peak_rate_out = self.peak_rate_out.max(rate_out);
+    }
+
+    pub fn record_latency(&mut self, rtt_ms: f32) {
+        self.latency_total_ms += rtt_ms as f64;
+        self.latency_samples += 1;
+    }
+
+    pub fn finish(self, profile: String, bytes_in: u64, bytes_out: u64, reason: String) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            started: self.started,
+            ended: Local::now().timestamp(),
+            profile,
+            bytes_in,
+            bytes_out,
+            peak_rate_in: self.peak_rate_in,
+            peak_rate_out: self.peak_rate_out,
+            avg_latency_ms: (self.latency_samples > 0)
+                .then(|| (self.latency_total_ms / self.latency_samples as f64) as f32),
+            disconnect_reason: reason,
+        }
+    }
+}
+
+/// How long entries are kept (0 = forever)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention(pub u64);
+
+impl Retention {
+    pub const CHOICES: [Retention; 5] = [Retention(7), Retention(30), Retention(90), Retention(365), Retention(0)];
+}
+
+impl fmt::Display for Retention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => write!(f, "Keep forever"),
+            days => write!(f, "{} days", days),
+        }
+    }
+}
+
+/// Which entries the History page shows. Dates are typed as YYYY-MM-DD; anything else is ignored.
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    /// Config path, or None for every profile
+    pub profile: Option<String>,
+    pub from: String,
+    pub to: String,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        if self.profile.as_ref().is_some_and(|p| *p != entry.profile) {
+            return false;
+        }
+        let date = entry.started_date();
+        let from = NaiveDate::parse_from_str(self.from.trim(), "%Y-%m-%d").ok();
+        let to = NaiveDate::parse_from_str(self.to.trim(), "%Y-%m-%d").ok();
+        from.is_none_or(|from| date.is_some_and(|d| d >= from)) && to.is_none_or(|to| date.is_some_and(|d| d <= to))
+    }
+}
+
+/// History entries in memory, mirrored to the JSONL file
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    /// Oldest first
+    pub entries: Vec<HistoryEntry>,
+    path: PathBuf,
+    next_id: u64,
+}
+
+impl HistoryStore {
+    /// Read the history file
+    pub fn load() -> Self {
+        Self::load_from(state_dir().join("history.jsonl"))
+    }
+
+    /// Read a history file; lines that do not parse are skipped
+    fn load_from(path: PathBuf) -> Self {
+        let mut entries: Vec<HistoryEntry> = std::fs::read_to_string(&path)
+            .map(|contents| {
+                contents
+                    .lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        for (id, entry) in entries.iter_mut().enumerate() {
+            entry.id = id as u64;
+        }
+        let next_id = entries.len() as u64;
+        Self { entries, path, next_id }
+    }
+
+    pub fn append(&mut self, mut entry: HistoryEntry) -> std::io::Result<()> {
+        entry.id = self.next_id;
+        self.next_id += 1;
+        let line = serde_json::to_string(&entry).map_err(std::io::Error::other)?;
+        self.entries.push(entry);
+        create_parent(&self.path)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)
+    }
+
+    pub fn delete(&mut self, id: u64) -> std::io::Result<()> {
+        self.entries.retain(|e| e.id != id);
+        self.save()
+    }
+
+    pub fn clear(&mut self) -> std::io::Result<()> {
+        self.entries.clear();
+        self.save()
+    }
+
+    /// Drop entries older than `days` (0 keeps everything)
+    pub fn apply_retention(&mut self, days: u64) -> std::io::Result<()> {
+        if days == 0 {
+            return Ok(());
+        }
+        let cutoff = Local::now().timestamp() - (days * 24 * 60 * 60) as i64;
+        let before = self.entries.len();
+        self.entries.retain(|e| e.ended >= cutoff);
+        if self.entries.len() != before {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Config paths that appear in the history
+    pub fn profiles(&self) -> Vec<String> {
+        let mut profiles: Vec<String> = self.entries.iter().map(|e| e.profile.clone()).collect();
+        profiles.sort();
+        profiles.dedup();
+        profiles
+    }
+
+    /// Matching entries, newest first
+    pub fn filtered<'a>(&'a self, filter: &'a HistoryFilter) -> impl Iterator<Item = &'a HistoryEntry> + 'a {
+        self.entries.iter().rev().filter(move |e| filter.matches(e))
+    }
+
+    /// Rewrite the whole file
+    fn save(&self) -> std::io::Result<()> {
+        let mut contents = String::new();
+        for entry in &self.entries {
+            contents.push_str(&serde_json::to_string(entry).map_err(std::io::Error::other)?);
+            contents.push('\n');
+        }
+        create_parent(&self.path)?;
+        std::fs::write(&self.path, contents)
+    }
+}
+
+fn create_parent(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(dir) => std::fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A store backed by a fresh file in the temp directory
+    fn temp_store(name: &str) -> HistoryStore {
+        let dir = std::env::temp_dir().join(format!("openvpn-gui-history-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        HistoryStore::load_from(dir.join("history.jsonl"))
+    }
+
+    fn entry(profile: &str, started: i64, ended: i64) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            started,
+            ended,
+            profile: profile.to_string(),
+            bytes_in: 1_500_000,
+            bytes_out: 250_000,
+            peak_rate_in: 120_000.0,
+            peak_rate_out: 8_000.5,
+            avg_latency_ms: Some(23.5),
+            disconnect_reason: "Disconnected by user".to_string(),
+        }
+    }
+
+    fn unix(date: &str, time: &str) -> i64 {
+        let naive = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap().and_time(time.parse().unwrap());
+        Local.from_local_datetime(&naive).single().unwrap().timestamp()
+    }
+
+    #[test]
+    fn entries_survive_a_reload() {
+        let mut store = temp_store("reload");
+        let mut first = entry("/home/me/work.ovpn", 1_700_000_000, 1_700_003_600);
+        first.avg_latency_ms = None;
+        store.append(first).unwrap();
+        store.append(entry("/home/me/home.ovpn", 1_700_010_000, 1_700_010_042)).unwrap();
+
+        let path = store.path.clone();
+        // A damaged line does not take the rest with it
+        let mut contents = std::fs::read_to_string(&path).unwrap();
+        contents.insert_str(0, "{\"started\": \n");
+        std::fs::write(&path, contents).unwrap();
+
+        let reloaded = HistoryStore::load_from(path);
+        assert_eq!(reloaded.entries, store.entries);
+        assert_eq!(reloaded.entries[1].duration_secs(), 42);
+        assert_eq!(reloaded.entries[1].profile_name(), "home");
+    }
+
+    #[test]
+    fn same_second_sessions_are_deleted_one_at_a_time() {
+        let mut store = temp_store("delete");
+        store.append(entry("/home/me/work.ovpn", 1_700_000_000, 1_700_000_000)).unwrap();
+        // Restarted straight away
+        store.append(entry("/home/me/work.ovpn", 1_700_000_000, 1_700_000_900)).unwrap();
+        let ids: Vec<u64> = store.entries.iter().map(|e| e.id).collect();
+        assert_ne!(ids[0], ids[1]);
+
+        store.delete(ids[0]).unwrap();
+        assert_eq!(store.entries.len(), 1);
+        assert_eq!(store.entries[0].ended, 1_700_000_900);
+        assert_eq!(HistoryStore::load_from(store.path.clone()).entries.len(), 1);
+
+        // New entries never reuse an id still in the list
+        store.append(entry("/home/me/work.ovpn", 1_700_001_000, 1_700_001_100)).unwrap();
+        assert_ne!(store.entries[1].id, store.entries[0].id);
+    }
+
+    #[test]
+    fn retention_drops_old_entries() {
+        let mut store = temp_store("retention");
+        let now = Local::now().timestamp();
+        let day = 24 * 60 * 60;
+        store.append(entry("/home/me/work.ovpn", now - 40 * day, now - 40 * day + 60)).unwrap();
+        store.append(entry("/home/me/work.ovpn", now - 10 * day, now - 10 * day + 60)).unwrap();
+        store.append(entry("/home/me/work.ovpn", now - 60, now)).unwrap();
+
+        store.apply_retention(0).unwrap();
+        assert_eq!(store.entries.len(), 3);
+        store.apply_retention(30).unwrap();
+        assert_eq!(store.entries.len(), 2);
+        store.apply_retention(7).unwrap();
+        assert_eq!(store.entries.len(), 1);
+        assert_eq!(HistoryStore::load_from(store.path.clone()).entries.len(), 1);
+    }
+
+    #[test]
+    fn filter_by_profile_and_dates() {
+        let mut store = temp_store("filter");
+        store.append(entry("/home/me/work.ovpn", unix("2024-03-01", "09:00:00"), unix("2024-03-01", "17:00:00"))).unwrap();
+        store.append(entry("/home/me/home.ovpn", unix("2024-03-02", "20:00:00"), unix("2024-03-02", "22:00:00"))).unwrap();
+        store.append(entry("/home/me/work.ovpn", unix("2024-03-04", "23:59:00"), unix("2024-03-05", "01:00:00"))).unwrap();
+        let started = |filter: &HistoryFilter| store.filtered(filter).map(|e| e.started_local()).collect::<Vec<_>>();
+
+        // Newest first
+        assert_eq!(
+            started(&HistoryFilter::default()),
+            ["2024-03-04 23:59:00", "2024-03-02 20:00:00", "2024-03-01 09:00:00"]
+        );
+        let work = HistoryFilter { profile: Some("/home/me/work.ovpn".to_string()), ..Default::default() };
+        assert_eq!(started(&work), ["2024-03-04 23:59:00", "2024-03-01 09:00:00"]);
+
+        // Both ends are inclusive and go by the day the session started
+        let range = HistoryFilter { from: "2024-03-02".to_string(), to: " 2024-03-04 ".to_string(), ..Default::default() };
+        assert_eq!(started(&range), ["2024-03-04 23:59:00", "2024-03-02 20:00:00"]);
+        let work_range = HistoryFilter { to: "2024-03-03".to_string(), ..work };
+        assert_eq!(started(&work_range), ["2024-03-01 09:00:00"]);
+
+        // Half-typed dates are ignored
+        let typing = HistoryFilter { from: "2024-03-0".to_string(), ..Default::default() };
+        assert_eq!(started(&typing).len(), 3);
+
+        assert_eq!(store.profiles(), ["/home/me/home.ovpn", "/home/me/work.ovpn"]);
+    }
+}
@@ -0,0 +1,69 @@
+// systemd journal output over the native protocol socket
+
+use std::os::unix::net::UnixDatagram;
+
+use crate::logging::{LogLevel, LogRecord};
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Sends records to journald with structured fields
+pub struct Journal {
+    socket: Option<UnixDatagram>,
+}
+
+impl Journal {
+    /// Without a journal (no systemd, sandboxed) records are silently dropped
+    pub fn new() -> Self {
+        Self {
+            socket: UnixDatagram::unbound().ok(),
+        }
+    }
+
+    /// `fields` are extra KEY=value pairs (PROFILE, SESSION_PATH, STATE)
+    pub fn send(&self, record: &LogRecord, fields: &[(&str, &str)]) {
+        let Some(socket) = &self.socket else {
+            return;
+        };
+
+        let priority = match record.level {
+            LogLevel::Error => "3",
+            LogLevel::Warn => "4",
+            LogLevel::Info => "6",
+            LogLevel::Debug => "7",
+        };
+        let source = record.source.to_string();
+
+        let mut datagram = Vec::new();
+        append_field(&mut datagram, "MESSAGE", &record.message);
+        append_field(&mut datagram, "PRIORITY", priority);
+        append_field(&mut datagram, "SYSLOG_IDENTIFIER", "openvpn-gui");
+        append_field(&mut datagram, "LOG_SOURCE", &source);
+        if let Some(category) = &record.category {
+            append_field(&mut datagram, "LOG_CATEGORY", category);
+        }
+        for (key, value) in fields {
+            append_field(&mut datagram, key, value);
+        }
+
+        let _ = socket.send_to(&datagram, JOURNAL_SOCKET);
+    }
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Native protocol: `KEY=value\n`, or `KEY\n<u64 LE length><value>\n` when the value has newlines
+fn append_field(datagram: &mut Vec<u8>, key: &str, value: &str) {
+    datagram.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        datagram.push(b'\n');
+        datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        datagram.push(b'=');
+    }
+    datagram.extend_from_slice(value.as_bytes());
+    datagram.push(b'\n');
+}
@@ -0,0 +1,259 @@
+// On-disk logs - rotating files under $XDG_STATE_HOME/openvpn-gui/logs
+
+use chrono::{DateTime, Local};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::time::{Duration, SystemTime};
+
+use crate::logging::LogRecord;
+use crate::settings::LoggingSettings;
+
+/// Name of the file currently written to
+const CURRENT_LOG: &str = "openvpn-gui.log";
+/// Prefix of rotated files: openvpn-gui-YYYYMMDD-HHMMSS[-N].log[.gz]
+const ROTATED_PREFIX: &str = "openvpn-gui-";
+
+/// The app's state directory, `$XDG_STATE_HOME/openvpn-gui`
+pub fn state_dir() -> PathBuf {
+    let mut path = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_else(std::env::temp_dir);
+    path.push("openvpn-gui");
+    path
+}
+
+/// Directory holding the log files
+pub fn log_dir() -> PathBuf {
+    state_dir().join("logs")
+}
+
+/// Appends records to `openvpn-gui.log`, rotating it by size and by day
+pub struct LogFileWriter {
+    dir: PathBuf,
+    settings: LoggingSettings,
+    file: Option<File>,
+    size: u64,
+    opened: DateTime<Local>,
+    /// Background worker that compresses rotated files and prunes old ones, one job at a time
+    housekeeping: Option<Sender<PathBuf>>,
+}
+
+impl LogFileWriter {
+    pub fn new(settings: &LoggingSettings) -> Self {
+        Self {
+            dir: log_dir(),
+            settings: settings.clone(),
+            file: None,
+            size: 0,
+            opened: Local::now(),
+            housekeeping: None,
+        }
+    }
+
+    pub fn write(&mut self, record: &LogRecord) {
+        if !self.settings.file {
+            return;
+        }
+        let line = format!("{}\n", record.format_full());
+        if let Err(e) = self.append(line.as_bytes()) {
+            // Give up on this line; the next one tries to reopen the file
+            eprintln!("Failed to write log file: {}", e);
+            self.file = None;
+        }
+    }
+
+    fn append(&mut self, line: &[u8]) -> std::io::Result<()> {
+        let max_size = self.settings.max_file_kb * 1024;
+        let new_day = self.opened.date_naive() != Local::now().date_naive();
+        if self.file.is_some() && (new_day || self.size + line.len() as u64 > max_size) {
+            self.file = None;
+            self.rotate()?;
+        }
+
+        if self.file.is_none() {
+            self.open()?;
+        }
+        if let Some(file) = &mut self.file {
+            file.write_all(line)?;
+            self.size += line.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn open(&mut self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(CURRENT_LOG);
+
+        // Left over from an earlier day or already full: start a fresh file
+        if let Ok(meta) = std::fs::metadata(&path) {
+            let modified: DateTime<Local> = meta.modified()?.into();
+            if modified.date_naive() != Local::now().date_naive()
+                || meta.len() >= self.settings.max_file_kb * 1024
+            {
+                self.rotate()?;
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        self.size = file.metadata()?.len();
+        self.opened = Local::now();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    /// Move the current file aside, compress it in the background and prune old files
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let current = self.dir.join(CURRENT_LOG);
+        if !current.exists() {
+            return Ok(());
+        }
+        let rotated = rotated_path(&self.dir, Local::now());
+        std::fs::rename(&current, &rotated)?;
+
+        if self.housekeeping().send(rotated).is_err() {
+            // The worker is gone; the next rotation starts a new one
+            eprintln!("Log file housekeeping stopped");
+            self.housekeeping = None;
+        }
+        Ok(())
+    }
+
+    /// The housekeeping worker, started on first use. Running the jobs one after another
+    /// keeps pruning from deleting a file that is still being compressed.
+    fn housekeeping(&mut self) -> &Sender<PathBuf> {
+        self.housekeeping.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel::<PathBuf>();
+            let dir = self.dir.clone();
+            let settings = self.settings.clone();
+            std::thread::spawn(move || {
+                for rotated in receiver {
+                    if settings.compress {
+                        if let Err(e) = compress(&rotated) {
+                            eprintln!("Failed to compress {}: {}", rotated.display(), e);
+                        }
+                    }
+                    prune(&dir, &settings);
+                }
+            });
+            sender
+        })
+    }
+}
+
+/// A name for a file rotated at `now` that no earlier rotation used, compressed or
+/// not (rotations within the same second get a counter)
+fn rotated_path(dir: &Path, now: DateTime<Local>) -> PathBuf {
+    let stamp = format!("{}{}", ROTATED_PREFIX, now.format("%Y%m%d-%H%M%S"));
+    let taken = |path: &Path| path.exists() || path.with_extension("log.gz").exists();
+    let mut path = dir.join(format!("{}.log", stamp));
+    let mut n = 1;
+    while taken(&path) {
+        path = dir.join(format!("{}-{}.log", stamp, n));
+        n += 1;
+    }
+    path
+}
+
+/// Replace `path` with `path.gz`
+fn compress(path: &Path) -> std::io::Result<()> {
+    let mut gz_path = path.as_os_str().to_owned();
+    gz_path.push(".gz");
+
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(&gz_path)?, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    std::fs::remove_file(path)
+}
+
+/// Keep at most `max_files` rotated files, none older than `max_age_days`
+fn prune(dir: &Path, settings: &LoggingSettings) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut rotated: Vec<(PathBuf, SystemTime)> = entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with(ROTATED_PREFIX))
+        .filter_map(|e| Some((e.path(), e.metadata().ok()?.modified().ok()?)))
+        .collect();
+    // Newest first
+    rotated.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+
+    let max_age = Duration::from_secs(settings.max_age_days * 24 * 60 * 60);
+    for (i, (path, modified)) in rotated.iter().enumerate() {
+        let too_old = modified.elapsed().is_ok_and(|age| age > max_age);
+        if i >= settings.max_files || too_old {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::{LogLevel, LogSource};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("openvpn-gui-logs-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn rotated_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| n.starts_with(ROTATED_PREFIX))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn rotations_in_the_same_second_get_distinct_names() {
+        let dir = temp_dir("names");
+        let now = Local::now();
+        let first = rotated_path(&dir, now);
+        std::fs::write(&first, "one").unwrap();
+        let second = rotated_path(&dir, now);
+        assert_ne!(first, second);
+        // A compressed file holds on to its name too
+        std::fs::write(second.with_extension("log.gz"), "two").unwrap();
+        let third = rotated_path(&dir, now);
+        assert!(third != first && third != second);
+        assert!(third.to_string_lossy().ends_with("-2.log"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn quick_rotations_keep_every_file() {
+        let dir = temp_dir("rotate");
+        let settings = LoggingSettings {
+            max_file_kb: 1,
+            compress: false,
+            ..Default::default()
+        };
+        let mut writer = LogFileWriter { dir: dir.clone(), ..LogFileWriter::new(&settings) };
+        let record = LogRecord::new(LogLevel::Info, LogSource::App, "x".repeat(600));
+        // Each record fills the file, so every write after the first rotates
+        for _ in 0..4 {
+            writer.write(&record);
+        }
+
+        let rotated = rotated_names(&dir);
+        assert_eq!(rotated.len(), 3, "{:?}", rotated);
+        let lines: usize = rotated
+            .iter()
+            .chain([&CURRENT_LOG.to_string()])
+            .map(|n| std::fs::read_to_string(dir.join(n)).unwrap().lines().count())
+            .sum();
+        assert_eq!(lines, 4);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
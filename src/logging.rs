@@ -0,0 +1,300 @@
+// Structured log records, the in-memory ring buffer and log pane filtering
+
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Records kept in memory; older ones are dropped first
+pub const LOG_CAPACITY: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error];
+
+    /// Map an openvpn3 log category (DEBUG, VERB1, INFO, WARNING, ERROR, FATAL, ...) to a level
+    pub fn from_openvpn3(category: Option<&str>) -> LogLevel {
+        match category.map(|c| c.to_ascii_uppercase()).as_deref() {
+            Some("DEBUG") | Some("VERB1") | Some("VERB2") => LogLevel::Debug,
+            Some("WARN") | Some("WARNING") => LogLevel::Warn,
+            Some("ERROR") | Some("CRIT") | Some("FATAL") => LogLevel::Error,
+            _ => LogLevel::Info,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        })
+    }
+}
+
+/// Where a record came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogSource {
+    App,
+    Openvpn3,
+    Probe,
+    Tray,
+}
+
+impl fmt::Display for LogSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogSource::App => "app",
+            LogSource::Openvpn3 => "openvpn3",
+            LogSource::Probe => "probe",
+            LogSource::Tray => "tray",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Local>,
+    pub level: LogLevel,
+    pub source: LogSource,
+    /// Finer tag within the source, e.g. "CLIENT:INFO" for openvpn3
+    pub category: Option<String>,
+    /// D-Bus session path the record belongs to
+    pub session: Option<String>,
+    pub message: String,
+}
+
+impl LogRecord {
+    pub fn new(level: LogLevel, source: LogSource, message: String) -> Self {
+        Self {
+            timestamp: Local::now(),
+            level,
+            source,
+            category: None,
+            session: None,
+            message,
+        }
+    }
+
+    /// One-line form used in the pane, saved logs and reports
+    pub fn format_line(&self) -> String {
+        let source = match &self.category {
+            Some(category) => format!("{} {}", self.source, category),
+            None => self.source.to_string(),
+        };
+        format!(
+            "[{}] {:<5} [{}] {}",
+            self.timestamp.format("%H:%M:%S"),
+            self.level,
+            source,
+            self.message
+        )
+    }
+
+    /// Machine-readable form used by JSON exports
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "timestamp": self.timestamp.to_rfc3339(),
+            "level": self.level.to_string(),
+            "source": self.source.to_string(),
+            "category": self.category,
+            "session": self.session,
+            "message": self.message,
+        })
+    }
+
+    /// Form written to log files: full date and the session, if any
+    pub fn format_full(&self) -> String {
+        let mut line = format!("{} {}", self.timestamp.format("%Y-%m-%d"), self.format_line());
+        if let Some(session) = &self.session {
+            line.push_str(&format!(" (session {})", session));
+        }
+        line
+    }
+}
+
+/// Fixed-capacity ring buffer of records
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    records: VecDeque<LogRecord>,
+    capacity: usize,
+    /// Records ever pushed (lets the pane notice new records after the buffer is full)
+    pushed: u64,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            pushed: 0,
+        }
+    }
+
+    pub fn push(&mut self, record: LogRecord) {
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+        self.pushed += 1;
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &LogRecord> {
+        self.records.iter()
+    }
+
+    pub fn pushed(&self) -> u64 {
+        self.pushed
+    }
+
+    /// Every record as text, oldest first (saved logs)
+    pub fn export(&self) -> String {
+        self.records.iter().map(|r| r.format_line()).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Records passing the pane's filter, oldest first
+    pub fn filtered<'a>(&'a self, filter: &'a LogFilter) -> impl Iterator<Item = &'a LogRecord> + 'a {
+        self.records.iter().filter(move |r| filter.matches(r))
+    }
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(LOG_CAPACITY)
+    }
+}
+
+/// What the log pane shows
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    pub levels: Vec<LogLevel>,
+    /// Case-insensitive substring of the formatted line
+    pub search: String,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            // Debug output (verbose openvpn3 levels) is opt-in
+            levels: vec![LogLevel::Info, LogLevel::Warn, LogLevel::Error],
+            search: String::new(),
+        }
+    }
+}
+
+impl LogFilter {
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        if !self.levels.contains(&record.level) {
+            return false;
+        }
+        if self.search.is_empty() {
+            return true;
+        }
+        record
+            .format_line()
+            .to_lowercase()
+            .contains(&self.search.to_lowercase())
+    }
+
+    pub fn set_level(&mut self, level: LogLevel, shown: bool) {
+        self.levels.retain(|l| *l != level);
+        if shown {
+            self.levels.push(level);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(level: LogLevel, source: LogSource, message: &str) -> LogRecord {
+        LogRecord::new(level, source, message.to_string())
+    }
+
+    fn messages<'a>(records: impl Iterator<Item = &'a LogRecord>) -> Vec<&'a str> {
+        records.map(|r| r.message.as_str()).collect()
+    }
+
+    #[test]
+    fn full_buffer_drops_the_oldest() {
+        let mut buffer = LogBuffer::new(3);
+        for i in 1..=5 {
+            buffer.push(record(LogLevel::Info, LogSource::App, &format!("line {}", i)));
+        }
+        assert_eq!(messages(buffer.iter()), ["line 3", "line 4", "line 5"]);
+        // The count keeps going so the pane still sees new records
+        assert_eq!(buffer.pushed(), 5);
+        assert_eq!(buffer.export().lines().count(), 3);
+
+        let mut tiny = LogBuffer::new(0);
+        tiny.push(record(LogLevel::Info, LogSource::App, "first"));
+        tiny.push(record(LogLevel::Info, LogSource::App, "second"));
+        assert_eq!(messages(tiny.iter()), ["second"]);
+    }
+
+    fn sample() -> LogBuffer {
+        let mut buffer = LogBuffer::new(10);
+        buffer.push(record(LogLevel::Debug, LogSource::Openvpn3, "Sending PUSH_REQUEST"));
+        buffer.push(record(LogLevel::Info, LogSource::App, "VPN Connected Successfully!"));
+        let mut tagged = record(LogLevel::Warn, LogSource::Openvpn3, "Ignoring option 'comp-lzo'");
+        tagged.category = Some("CLIENT:WARNING".to_string());
+        buffer.push(tagged);
+        buffer.push(record(LogLevel::Error, LogSource::Probe, "Gateway probe lost"));
+        buffer.push(record(LogLevel::Info, LogSource::Tray, "Quit requested"));
+        buffer
+    }
+
+    #[test]
+    fn filter_by_level() {
+        let buffer = sample();
+        let mut filter = LogFilter::default();
+        // Debug is opt-in
+        assert_eq!(buffer.filtered(&filter).count(), 4);
+
+        filter.set_level(LogLevel::Debug, true);
+        assert_eq!(buffer.filtered(&filter).count(), 5);
+        filter.set_level(LogLevel::Info, false);
+        filter.set_level(LogLevel::Warn, false);
+        assert_eq!(messages(buffer.filtered(&filter)), ["Sending PUSH_REQUEST", "Gateway probe lost"]);
+        // Toggling twice keeps one entry
+        filter.set_level(LogLevel::Error, true);
+        assert_eq!(filter.levels.iter().filter(|l| **l == LogLevel::Error).count(), 1);
+    }
+
+    #[test]
+    fn search_matches_message_source_and_category() {
+        let buffer = sample();
+        let search = |text: &str| {
+            let filter = LogFilter { search: text.to_string(), ..Default::default() };
+            messages(buffer.filtered(&filter)).into_iter().map(str::to_string).collect::<Vec<_>>()
+        };
+        assert_eq!(search("connected"), ["VPN Connected Successfully!"]);
+        // Source and category are part of the line
+        assert_eq!(search("openvpn3"), ["Ignoring option 'comp-lzo'"]);
+        assert_eq!(search("[probe]"), ["Gateway probe lost"]);
+        assert_eq!(search("tray"), ["Quit requested"]);
+        assert_eq!(search("client:warning"), ["Ignoring option 'comp-lzo'"]);
+        assert_eq!(search("ERROR"), ["Gateway probe lost"]);
+        // Hidden levels stay hidden whatever the search
+        assert!(search("push_request").is_empty());
+        assert!(search("nothing like this").is_empty());
+    }
+
+    #[test]
+    fn openvpn3_categories() {
+        assert_eq!(LogLevel::from_openvpn3(Some("verb2")), LogLevel::Debug);
+        assert_eq!(LogLevel::from_openvpn3(Some("WARNING")), LogLevel::Warn);
+        assert_eq!(LogLevel::from_openvpn3(Some("FATAL")), LogLevel::Error);
+        assert_eq!(LogLevel::from_openvpn3(Some("INFO")), LogLevel::Info);
+        assert_eq!(LogLevel::from_openvpn3(None), LogLevel::Info);
+    }
+}
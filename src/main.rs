@@ -1,12 +1,23 @@
 // Main entry point for OpenVPN GUI with COSMIC DE integration
 
 mod app;
+mod bundle;
+mod export;
+mod history;
+mod journal;
+mod log_files;
+mod logging;
 mod models;
+mod profiles;
+mod redact;
+mod report;
+mod settings;
 mod utils;
 mod vpn;
 mod ui;
 mod icon;
 mod tray;
+mod usage;
 
 use app::OpenVpnGui;
 
@@ -3,6 +3,22 @@
 use std::path::PathBuf;
 use std::time::Instant;
 
+use crate::bundle::BundleFile;
+use crate::history::Retention;
+use crate::usage::Period;
+use crate::logging::LogLevel;
+use crate::settings::GraphWindow;
+use crate::vpn::captive_portal::PortalCheck;
+use crate::vpn::killswitch::KillSwitchRules;
+use crate::vpn::error::VpnError;
+use crate::vpn::netwatch::{NetworkEvent, SleepAction, WakeAction};
+use crate::vpn::public_ip::PublicIpLookup;
+use crate::vpn::rates::CounterSample;
+use crate::vpn::session_log::SessionLogLine;
+use crate::vpn::snapshot::NetworkSnapshot;
+use crate::vpn::split_tunnel::SplitSide;
+use crate::vpn::trust::NetworkFacts;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConnectionState {
     Disconnected,
@@ -37,22 +53,50 @@ pub enum Message {
     ToggleVpn,
     
     // Async Results
-    VpnStarted(Result<(String, String), String>), // (Output, SessionPath)
-    VpnStopped(Result<String, String>),
-    StatsUpdated(Option<(u64, u64)>), // (Total In, Total Out)
+    VpnStarted(Result<(String, String), VpnError>), // (Output, SessionPath)
+    VpnStopped(Result<String, VpnError>),
+    StatsUpdated(Option<CounterSample>),
     SessionStatusChecked(Option<String>), // Session status output for monitoring
     TunnelIpFound(Option<String>),
-    PublicIpFound(Option<String>),
+    PublicIpFound(PublicIpLookup),
     SaveLogs,
+    LogsSavePathChosen(Option<PathBuf>),
+    OpenLogFolder,
     SaveSessionReport,
+    SessionReportPathChosen(Option<PathBuf>),
+    SessionReportReady(PathBuf, NetworkSnapshot),
+    CreateDiagnosticsBundle,
+    BundlePathChosen(Option<PathBuf>),
+    BundleReady(PathBuf, NetworkSnapshot, Vec<BundleFile>),
     
     // UI Interaction
     ToggleGraph(bool),
+    ToggleRateSmoothing(bool),
+    SetGraphWindow(GraphWindow),
+    ToggleGraphStacked(bool),
+    /// Cursor position over the graph (None once it leaves)
+    GraphHover(Option<cosmic::iced::Point>),
     ToggleAutoReconnect(bool),
+    ToggleKillSwitch(bool),
+    ToggleSplitTunnel(bool),
+    ToggleNamespace(bool),
+    TogglePrivacyMode(bool),
+    ToggleCaptivePortal(bool),
     InputCodeChanged(String),
     SubmitCode,
-    AuthCodeResult(Result<String, String>),
+    AuthCodeResult(Result<String, VpnError>),
     ShowAbout,
+    // History page
+    ShowHistory,
+    CloseHistory,
+    HistoryProfileFilter(String),
+    HistoryFromChanged(String),
+    HistoryToChanged(String),
+    SelectHistoryEntry(u64),
+    DeleteHistoryEntry(u64),
+    ClearHistory,
+    SetHistoryRetention(Retention),
+    ToggleHistory(bool),
     CloseAbout,
     
     // Recent Files
@@ -63,7 +107,59 @@ pub enum Message {
     ShowSessions,
     SessionsListed(String),
     CloseSessions,
+    VpnGatewayFound(Option<String>),
+
+    // Latency probes
+    ProbeCompleted(String, Option<f32>), // (Target name, RTT in ms)
+    DnsChecked(bool),
+
+    // Kill switch
+    KillSwitchRulesBuilt(Result<KillSwitchRules, String>),
+    KillSwitchApplied(Result<(), String>),
+    KillSwitchRemoved(Result<(), String>),
+
+    // Split tunnelling
+    SplitTunnelApplied(Result<(), String>),
+    SplitTunnelRemoved(Result<(), String>),
+    LaunchCommandChanged(String),
+    LaunchApp(SplitSide, String),
+    AppExited(Result<String, String>),
+
+    // VPN network namespace
+    NamespaceReady(Result<String, String>), // Addresses moved into the namespace
+    NamespaceRemoved(Result<(), String>),
+    NetnsCommandChanged(String),
+    RunInNamespace(String),
+
+    // Network changes, suspend and resume
+    NetworkEvent(NetworkEvent),
+    SessionManaged(Result<String, VpnError>),
+    DismissError,
+    DismissDiagnosis,
+
+    // openvpn3 session log
+    SessionLog(SessionLogLine),
+    SetSessionLogLevel(u8),
+
+    // Log pane
+    LogLevelToggled(LogLevel, bool),
+    LogSearchChanged(String),
+    ToggleLogAutoScroll(bool),
+    LogScrolled(cosmic::iced::widget::scrollable::Viewport),
+    CopyLogs,
+    SetSleepAction(SleepAction),
+    SetWakeAction(WakeAction),
+    ToggleRestartOnUplinkChange(bool),
+
+    // Data quota of the selected profile
+    SetQuotaPeriod(Period),
+    QuotaLimitChanged(String),
+    ToggleQuotaDisconnect(bool),
+
+    // Trusted-network rules
+    NetworkFactsGathered(NetworkFacts),
 
-    // Latency update
-    LatencyChecked(Option<u32>),
+    // Captive portal
+    PortalChecked(PortalCheck),
+    OpenPortal,
 }
@@ -0,0 +1,111 @@
+// Per-profile settings keyed by .ovpn config path, recent configs and network rules
+
+use serde::{Deserialize, Serialize};
+
+use crate::usage::Quota;
+use crate::vpn::netwatch::NetworkTriggers;
+use crate::vpn::split_tunnel::SplitTunnelSettings;
+use crate::vpn::trust::NetworkRule;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub config_path: String,
+    pub split_tunnel: SplitTunnelSettings,
+    /// Move the tunnel into an isolated network namespace once connected
+    pub namespace: bool,
+    /// Reactions to suspend/resume and uplink changes
+    pub triggers: NetworkTriggers,
+    /// Data cap for metered links
+    pub quota: Quota,
+}
+
+const MAX_RECENT: usize = 10;
+
+/// Profiles stored in `~/.config/openvpn-gui/profiles.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileStore {
+    pub profiles: Vec<Profile>,
+    /// Most recently used config paths, newest first
+    pub recent: Vec<String>,
+    /// Trusted-network rules, first match wins
+    pub rules: Vec<NetworkRule>,
+}
+
+impl ProfileStore {
+    pub fn load() -> Self {
+        let mut store: Self = std::fs::read_to_string(Self::file_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        store.migrate_recent_configs();
+        store
+    }
+
+    /// Import the old recent_configs.txt once, then remove it
+    fn migrate_recent_configs(&mut self) {
+        let Some(mut old) = dirs::config_dir() else {
+            return;
+        };
+        old.push("openvpn-gui/recent_configs.txt");
+        let Ok(contents) = std::fs::read_to_string(&old) else {
+            return;
+        };
+        for path in contents.lines().filter(|s| !s.is_empty()) {
+            if !self.recent.iter().any(|p| p == path) {
+                self.recent.push(path.to_string());
+            }
+        }
+        self.recent.truncate(MAX_RECENT);
+        self.save();
+        let _ = std::fs::remove_file(old);
+    }
+
+    /// Move (or add) a config to the top of the recent list
+    pub fn add_recent(&mut self, config_path: String) {
+        self.recent.retain(|p| p != &config_path);
+        self.recent.insert(0, config_path);
+        self.recent.truncate(MAX_RECENT);
+        self.save();
+    }
+
+    pub fn clear_recent(&mut self) {
+        self.recent.clear();
+        self.save();
+    }
+
+    pub fn save(&self) {
+        if let Ok(contents) = serde_json::to_string_pretty(self) {
+            let _ = std::fs::write(Self::file_path(), contents);
+        }
+    }
+
+    pub fn get(&self, config_path: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.config_path == config_path)
+    }
+
+    /// Profile for the config, created with defaults if it does not exist yet
+    pub fn get_or_insert(&mut self, config_path: &str) -> &mut Profile {
+        if let Some(idx) = self.profiles.iter().position(|p| p.config_path == config_path) {
+            &mut self.profiles[idx]
+        } else {
+            self.profiles.push(Profile {
+                config_path: config_path.to_string(),
+                ..Default::default()
+            });
+            self.profiles.last_mut().unwrap()
+        }
+    }
+
+    fn file_path() -> std::path::PathBuf {
+        if let Some(mut path) = dirs::config_dir() {
+            path.push("openvpn-gui");
+            std::fs::create_dir_all(&path).ok();
+            path.push("profiles.json");
+            path
+        } else {
+            std::path::PathBuf::from("profiles.json")
+        }
+    }
+}
@@ -0,0 +1,363 @@
+// Session report - one model rendered as JSON, Markdown or a self-contained HTML page
+
+use plotters::prelude::*;
+use serde::Serialize;
+
+use crate::logging::LogRecord;
+use crate::redact::Redactor;
+use crate::utils::{format_bytes, format_duration};
+use crate::vpn::rates::RateSeries;
+use crate::vpn::snapshot::NetworkSnapshot;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub config_path: String,
+    /// `host:port/proto` for each remote in the config
+    pub remotes: Vec<String>,
+    pub namespace: bool,
+    pub split_tunnel: bool,
+    pub kill_switch: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Timing {
+    pub generated: String,
+    pub connected_since: Option<String>,
+    pub duration_secs: Option<u64>,
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Addresses {
+    pub tunnel_device: Option<String>,
+    pub tunnel_ip: String,
+    pub vpn_gateway: Option<String>,
+    pub public_ip: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Traffic {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Bytes/sec at the time of the report
+    pub rate_in: f32,
+    pub rate_out: f32,
+    /// Rates over the session (oldest first), timed relative to the report, drawn in the HTML graph
+    pub history: RateSeries,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencySummary {
+    pub target: String,
+    pub role: String,
+    pub min_ms: Option<f32>,
+    pub avg_ms: Option<f32>,
+    pub max_ms: Option<f32>,
+    pub jitter_ms: Option<f32>,
+    pub loss_pct: f32,
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSummary {
+    pub level: String,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportLogLine {
+    pub timestamp: String,
+    pub level: String,
+    pub source: String,
+    pub category: Option<String>,
+    pub message: String,
+}
+
+impl From<&LogRecord> for ReportLogLine {
+    fn from(record: &LogRecord) -> Self {
+        Self {
+            timestamp: record.timestamp.to_rfc3339(),
+            level: record.level.to_string(),
+            source: record.source.to_string(),
+            category: record.category.clone(),
+            message: record.message.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionReport {
+    pub profile: ProfileInfo,
+    pub timing: Timing,
+    pub addresses: Addresses,
+    pub traffic: Traffic,
+    pub latency: Vec<LatencySummary>,
+    pub health: Option<HealthSummary>,
+    /// Session counters, routes and DNS
+    pub network: NetworkSnapshot,
+    /// Every log record in memory (already redacted)
+    pub log: Vec<ReportLogLine>,
+}
+
+const LATENCY_COLUMNS: [&str; 8] = ["Target", "Role", "Min", "Avg", "Max", "Jitter", "Loss", "Samples"];
+
+impl SessionReport {
+    /// A copy with every free-text field masked. Secrets have to be caught before
+    /// rendering: once escaped for JSON or HTML they no longer match the patterns.
+    pub fn redacted(&self, redactor: &Redactor) -> SessionReport {
+        let text = |s: &String| redactor.redact(s);
+        let texts = |v: &Vec<String>| v.iter().map(text).collect::<Vec<_>>();
+        // Redacted as one text, so a block spanning several lines is still recognised
+        let lines = |v: &Vec<String>| redactor.redact(&v.join("\n")).lines().map(str::to_string).collect::<Vec<_>>();
+        let mut report = self.clone();
+        report.profile.name = text(&self.profile.name);
+        report.profile.config_path = text(&self.profile.config_path);
+        report.profile.remotes = texts(&self.profile.remotes);
+        report.addresses.tunnel_device = self.addresses.tunnel_device.as_ref().map(text);
+        report.addresses.tunnel_ip = text(&self.addresses.tunnel_ip);
+        report.addresses.vpn_gateway = self.addresses.vpn_gateway.as_ref().map(text);
+        report.addresses.public_ip = text(&self.addresses.public_ip);
+        for latency in &mut report.latency {
+            latency.target = text(&latency.target);
+        }
+        if let Some(health) = &mut report.health {
+            health.reasons = texts(&health.reasons);
+        }
+        report.network.routes = lines(&self.network.routes);
+        report.network.dns = lines(&self.network.dns);
+        for counter in &mut report.network.counters {
+            counter.name = text(&counter.name);
+        }
+        for line in &mut report.log {
+            line.category = line.category.as_ref().map(text);
+            line.message = text(&line.message);
+        }
+        report
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# OpenVPN3 Session Report: {}\n", self.profile.name);
+        for (title, rows) in self.fields() {
+            md.push_str(&format!("\n## {}\n\n| | |\n|---|---|\n", title));
+            for (key, value) in rows {
+                md.push_str(&format!("| {} | {} |\n", key, value.replace('|', "\\|")));
+            }
+        }
+
+        md.push_str("\n## Latency\n\n");
+        if self.latency.is_empty() {
+            md.push_str("No probe results.\n");
+        } else {
+            md.push_str(&format!("| {} |\n|{}\n", LATENCY_COLUMNS.join(" | "), "---|".repeat(LATENCY_COLUMNS.len())));
+            for row in self.latency_rows() {
+                md.push_str(&format!("| {} |\n", row.join(" | ")));
+            }
+        }
+
+        for (title, lines) in self.blocks() {
+            md.push_str(&format!("\n## {}\n\n```\n{}\n```\n", title, lines.join("\n")));
+        }
+        md
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut body = format!("<h1>OpenVPN3 Session Report: {}</h1>\n", escape_html(&self.profile.name));
+
+        body.push_str("<h2>Traffic Graph</h2>\n<figure>");
+        body.push_str(&traffic_svg(&self.traffic.history));
+        body.push_str("<figcaption><span class=\"in\">&#9632; In</span> <span class=\"out\">&#9632; Out</span></figcaption></figure>\n");
+
+        for (title, rows) in self.fields() {
+            body.push_str(&format!("<h2>{}</h2>\n<table>\n", title));
+            for (key, value) in rows {
+                body.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", escape_html(&key), escape_html(&value)));
+            }
+            body.push_str("</table>\n");
+        }
+
+        body.push_str("<h2>Latency</h2>\n");
+        if self.latency.is_empty() {
+            body.push_str("<p>No probe results.</p>\n");
+        } else {
+            body.push_str("<table>\n<tr>");
+            for column in LATENCY_COLUMNS {
+                body.push_str(&format!("<th>{}</th>", column));
+            }
+            body.push_str("</tr>\n");
+            for row in self.latency_rows() {
+                body.push_str("<tr>");
+                for cell in row {
+                    body.push_str(&format!("<td>{}</td>", escape_html(&cell)));
+                }
+                body.push_str("</tr>\n");
+            }
+            body.push_str("</table>\n");
+        }
+
+        for (title, lines) in self.blocks() {
+            body.push_str(&format!("<h2>{}</h2>\n<pre>{}</pre>\n", title, escape_html(&lines.join("\n"))));
+        }
+
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>OpenVPN3 Session Report: {}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            escape_html(&self.profile.name),
+            REPORT_CSS,
+            body
+        )
+    }
+
+    /// Key/value sections shared by the Markdown and HTML renderings
+    fn fields(&self) -> Vec<(&'static str, Vec<(String, String)>)> {
+        let or_dash = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+        let yes_no = |v: bool| if v { "yes" } else { "no" }.to_string();
+        let row = |k: &str, v: String| (k.to_string(), v);
+
+        let mut traffic = vec![
+            row("Bytes in", format!("{} ({})", format_bytes(self.traffic.bytes_in as f32), self.traffic.bytes_in)),
+            row("Bytes out", format!("{} ({})", format_bytes(self.traffic.bytes_out as f32), self.traffic.bytes_out)),
+            row("Rate in", format!("{}/s", format_bytes(self.traffic.rate_in))),
+            row("Rate out", format!("{}/s", format_bytes(self.traffic.rate_out))),
+        ];
+        traffic.extend(self.network.counters.iter().map(|c| row(&c.name, c.value.to_string())));
+
+        let health = match &self.health {
+            Some(h) => vec![row("Level", h.level.clone()), row("Reasons", h.reasons.join("; "))],
+            None => vec![row("Level", "-".to_string())],
+        };
+
+        vec![
+            (
+                "Profile",
+                vec![
+                    row("Name", self.profile.name.clone()),
+                    row("Config", self.profile.config_path.clone()),
+                    row("Remotes", self.profile.remotes.join(", ")),
+                    row("Namespace", yes_no(self.profile.namespace)),
+                    row("Split tunnel", yes_no(self.profile.split_tunnel)),
+                    row("Kill switch", yes_no(self.profile.kill_switch)),
+                ],
+            ),
+            (
+                "Timing",
+                vec![
+                    row("Report generated", self.timing.generated.clone()),
+                    row("Connected since", or_dash(&self.timing.connected_since)),
+                    row("Duration", self.timing.duration_secs.map(format_duration).unwrap_or_else(|| "-".to_string())),
+                    row("State", self.timing.state.clone()),
+                ],
+            ),
+            (
+                "Addresses",
+                vec![
+                    row("Tunnel device", or_dash(&self.addresses.tunnel_device)),
+                    row("Tunnel IP", self.addresses.tunnel_ip.clone()),
+                    row("VPN gateway", or_dash(&self.addresses.vpn_gateway)),
+                    row("Public IP", self.addresses.public_ip.clone()),
+                ],
+            ),
+            ("Traffic", traffic),
+            ("Health", health),
+        ]
+    }
+
+    fn latency_rows(&self) -> Vec<[String; 8]> {
+        let ms = |v: Option<f32>| v.map(|v| format!("{:.1} ms", v)).unwrap_or_else(|| "-".to_string());
+        self.latency
+            .iter()
+            .map(|l| {
+                [
+                    l.target.clone(),
+                    l.role.clone(),
+                    ms(l.min_ms),
+                    ms(l.avg_ms),
+                    ms(l.max_ms),
+                    ms(l.jitter_ms),
+                    format!("{:.0}%", l.loss_pct),
+                    l.samples.to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    /// Preformatted sections: routes, DNS and the log
+    fn blocks(&self) -> Vec<(&'static str, Vec<String>)> {
+        let log = self
+            .log
+            .iter()
+            .map(|l| match &l.category {
+                Some(category) => format!("{} {:<5} [{} {}] {}", l.timestamp, l.level, l.source, category, l.message),
+                None => format!("{} {:<5} [{}] {}", l.timestamp, l.level, l.source, l.message),
+            })
+            .collect();
+        vec![
+            ("Routes", self.network.routes.clone()),
+            ("DNS", self.network.dns.clone()),
+            ("Log", log),
+        ]
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const REPORT_CSS: &str = "body{font-family:sans-serif;max-width:960px;margin:2em auto;color:#222}\
+table{border-collapse:collapse;margin-bottom:1em}th,td{border:1px solid #ccc;padding:4px 8px;text-align:left}\
+th{background:#f4f4f4}pre{background:#f4f4f4;padding:1em;overflow-x:auto;font-size:12px}\
+.in{color:#42a5f5}.out{color:#ef5350}";
+
+/// Line colours of the report graph, matching `.in` and `.out` in `REPORT_CSS`. The page
+/// is read outside the app, so it keeps fixed colours rather than the theme's.
+const REPORT_IN: RGBColor = RGBColor(0x42, 0xa5, 0xf5);
+const REPORT_OUT: RGBColor = RGBColor(0xef, 0x53, 0x50);
+
+/// The traffic graph as an inline SVG element, in the report's colours
+pub fn traffic_svg(history: &RateSeries) -> String {
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, (800, 240)).into_drawing_area();
+        let _ = root.fill(&WHITE);
+
+        let oldest = history.points.iter().map(|p| p.secs_ago).fold(1.0f32, f32::max);
+        let max = history
+            .points
+            .iter()
+            .map(|p| p.rate_in.max(p.rate_out))
+            .fold(0.0f32, f32::max)
+            .max(1024.0);
+
+        let chart = ChartBuilder::on(&root)
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(-oldest..0.0, 0.0..max);
+        if let Ok(mut chart) = chart {
+            let _ = chart
+                .configure_mesh()
+                .x_desc("seconds before the report")
+                .y_label_formatter(&|v| format!("{}/s", format_bytes(*v)))
+                .draw();
+            // Missed readings show as gaps rather than joined lines
+            for run in history.runs() {
+                let _ = chart.draw_series(LineSeries::new(
+                    run.iter().map(|p| (-p.secs_ago, p.rate_in)),
+                    &REPORT_IN,
+                ));
+                let _ = chart.draw_series(LineSeries::new(
+                    run.iter().map(|p| (-p.secs_ago, p.rate_out)),
+                    &REPORT_OUT,
+                ));
+            }
+        }
+        let _ = root.present();
+    }
+    svg
+}
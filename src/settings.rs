@@ -0,0 +1,283 @@
+// Persistent application settings
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+use crate::vpn::captive_portal::CaptivePortalSettings;
+use crate::vpn::health::HealthSettings;
+use crate::vpn::killswitch::KillSwitchSettings;
+use crate::vpn::probes::ProbeSettings;
+use crate::vpn::public_ip::PublicIpSettings;
+
+/// Outbound network checks the GUI makes on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Probe {
+    PublicIp,
+    Latency,
+    Dns,
+    CaptivePortal,
+}
+
+impl Probe {
+    pub const ALL: [Probe; 4] = [Probe::PublicIp, Probe::Latency, Probe::Dns, Probe::CaptivePortal];
+
+    /// What the user loses when this probe is disabled
+    pub fn description(&self) -> &'static str {
+        match self {
+            Probe::PublicIp => "public IP",
+            Probe::Latency => "latency",
+            Probe::Dns => "DNS reachability",
+            Probe::CaptivePortal => "captive portal detection",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    /// Disable every probe that is not explicitly allowed below
+    pub enabled: bool,
+    /// Probes an administrator has turned back on while privacy mode is enabled
+    pub allowed_probes: Vec<Probe>,
+}
+
+/// What is masked in logs and exports besides passwords, tokens, keys and certificates
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionSettings {
+    /// Mask the id part of openvpn3 session paths
+    pub redact_session_ids: bool,
+}
+
+/// Log files under `$XDG_STATE_HOME/openvpn-gui/logs` and journald output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingSettings {
+    /// Write records to `openvpn-gui.log`
+    pub file: bool,
+    /// Rotate once the current file reaches this size (it is also rotated daily)
+    pub max_file_kb: u64,
+    /// Rotated files kept
+    pub max_files: usize,
+    /// Rotated files older than this are deleted
+    pub max_age_days: u64,
+    /// Gzip rotated files
+    pub compress: bool,
+    /// Also send records to the systemd journal
+    pub journald: bool,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            file: true,
+            max_file_kb: 1024,
+            max_files: 10,
+            max_age_days: 14,
+            compress: true,
+            journald: false,
+        }
+    }
+}
+
+/// How transfer rates are computed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateSettings {
+    /// Smooth rates with an exponential moving average
+    pub smoothing: bool,
+    /// Time constant of the average
+    pub smoothing_secs: f32,
+}
+
+impl Default for RateSettings {
+    fn default() -> Self {
+        Self {
+            smoothing: false,
+            smoothing_secs: 5.0,
+        }
+    }
+}
+
+/// Time span shown in the traffic graph
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphWindow {
+    #[default]
+    Minute,
+    FiveMinutes,
+    Hour,
+    /// Since the session connected
+    Session,
+}
+
+impl GraphWindow {
+    pub const ALL: [GraphWindow; 4] = [GraphWindow::Minute, GraphWindow::FiveMinutes, GraphWindow::Hour, GraphWindow::Session];
+
+    /// None for the session, whose length varies
+    pub fn span(self) -> Option<Duration> {
+        match self {
+            GraphWindow::Minute => Some(Duration::from_secs(60)),
+            GraphWindow::FiveMinutes => Some(Duration::from_secs(5 * 60)),
+            GraphWindow::Hour => Some(Duration::from_secs(60 * 60)),
+            GraphWindow::Session => None,
+        }
+    }
+}
+
+impl fmt::Display for GraphWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphWindow::Minute => write!(f, "1 min"),
+            GraphWindow::FiveMinutes => write!(f, "5 min"),
+            GraphWindow::Hour => write!(f, "1 hour"),
+            GraphWindow::Session => write!(f, "Session"),
+        }
+    }
+}
+
+/// Traffic graph display
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphSettings {
+    pub window: GraphWindow,
+    /// Filled areas with out stacked on in, instead of two lines
+    pub stacked: bool,
+}
+
+/// Connection history kept in `$XDG_STATE_HOME/openvpn-gui/history.jsonl`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    /// Record finished sessions
+    pub enabled: bool,
+    /// Entries older than this are deleted (0 keeps them forever)
+    pub retention_days: u64,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_days: 90,
+        }
+    }
+}
+
+/// Deadlines for getting a session up. The clock restarts when the phase changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectSettings {
+    /// Reaching the server and bringing the tunnel up
+    pub transport_timeout_secs: u64,
+    /// Waiting for the user to finish 2FA or browser sign-in
+    pub auth_timeout_secs: u64,
+    /// Auto-reconnect retries after a connect timeout before giving up
+    pub max_reconnect_attempts: u32,
+}
+
+impl Default for ConnectSettings {
+    fn default() -> Self {
+        Self {
+            transport_timeout_secs: 60,
+            auth_timeout_secs: 300,
+            max_reconnect_attempts: 3,
+        }
+    }
+}
+
+/// User settings stored in `~/.config/openvpn-gui/settings.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub public_ip: PublicIpSettings,
+    pub privacy: PrivacySettings,
+    pub redaction: RedactionSettings,
+    pub logging: LoggingSettings,
+    pub history: HistorySettings,
+    pub rates: RateSettings,
+    pub graph: GraphSettings,
+    /// Latency probe targets (point them at internal hosts to keep probes in-house)
+    pub probes: ProbeSettings,
+    pub health: HealthSettings,
+    pub kill_switch: KillSwitchSettings,
+    pub captive_portal: CaptivePortalSettings,
+    pub connect: ConnectSettings,
+    /// Verbosity of the openvpn3 session log shown in the log pane (0-6)
+    pub session_log_level: u8,
+    /// Folder the last log or report was saved to; the save dialog starts there
+    pub last_export_dir: Option<std::path::PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            public_ip: PublicIpSettings::default(),
+            privacy: PrivacySettings::default(),
+            redaction: RedactionSettings::default(),
+            logging: LoggingSettings::default(),
+            history: HistorySettings::default(),
+            rates: RateSettings::default(),
+            graph: GraphSettings::default(),
+            probes: ProbeSettings::default(),
+            health: HealthSettings::default(),
+            kill_switch: KillSwitchSettings::default(),
+            captive_portal: CaptivePortalSettings::default(),
+            connect: ConnectSettings::default(),
+            session_log_level: 3,
+            last_export_dir: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Whether the given probe may run under the current privacy settings
+    pub fn probe_allowed(&self, probe: Probe) -> bool {
+        !self.privacy.enabled || self.privacy.allowed_probes.contains(&probe)
+    }
+
+    /// Probes currently blocked by privacy mode
+    pub fn disabled_probes(&self) -> Vec<Probe> {
+        Probe::ALL
+            .into_iter()
+            .filter(|p| !self.probe_allowed(*p))
+            .collect()
+    }
+
+    /// Load settings from disk, falling back to defaults
+    pub fn load() -> Self {
+        let path = Self::file_path();
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Ignoring invalid settings file {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => {
+                // Write the defaults so the file is there to be edited
+                let settings = Self::default();
+                settings.save();
+                settings
+            }
+        }
+    }
+
+    /// Write settings to disk
+    pub fn save(&self) {
+        if let Ok(contents) = serde_json::to_string_pretty(self) {
+            let _ = std::fs::write(Self::file_path(), contents);
+        }
+    }
+
+    fn file_path() -> std::path::PathBuf {
+        if let Some(mut path) = dirs::config_dir() {
+            path.push("openvpn-gui");
+            std::fs::create_dir_all(&path).ok();
+            path.push("settings.json");
+            path
+        } else {
+            std::path::PathBuf::from("settings.json")
+        }
+    }
+}
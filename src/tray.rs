@@ -4,43 +4,68 @@ use ksni;
 use ksni::blocking::TrayMethods;
 use std::sync::{Arc, Mutex};
 
+use crate::vpn::health::HealthLevel;
+
 #[derive(Clone)]
 pub struct TrayState {
     pub connected: bool,
     pub tooltip: String,
+    pub health: Option<HealthLevel>,
+    /// "Quit" was picked; the app exits on its next tick, once it has saved its state
+    pub quit_requested: bool,
 }
 
 pub struct SystemTray {
     state: Arc<Mutex<TrayState>>,
+    handle: Arc<Mutex<Option<ksni::blocking::Handle<OpenvpnTray>>>>,
 }
 
 pub struct OpenvpnTray {
     state: Arc<Mutex<TrayState>>,
 }
 
-impl ksni::Tray for OpenvpnTray {
-    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
-        use image::ImageReader;
-        use std::path::Path;
-        // Load the 16x16 PNG icon from disk for tray
-        let icon_path = concat!(env!("CARGO_MANIFEST_DIR"), "/icons/openvpn3-gui-16.png");
-        let img = ImageReader::open(Path::new(icon_path)).unwrap().decode().unwrap().to_rgba8();
-        let (width, height) = img.dimensions();
-        let rgba_data = img.into_raw();
-        let mut argb_data = Vec::with_capacity(rgba_data.len());
-        for chunk in rgba_data.chunks(4) {
-            if chunk.len() == 4 {
-                argb_data.push(chunk[3]); // A
-                argb_data.push(chunk[0]); // R
-                argb_data.push(chunk[1]); // G
-                argb_data.push(chunk[2]); // B
+/// Load the 16x16 tray icon as ARGB, with a health dot in the bottom-right corner
+fn load_icon(health: Option<HealthLevel>) -> ksni::Icon {
+    use image::ImageReader;
+    use std::path::Path;
+    // Load the 16x16 PNG icon from disk for tray
+    let icon_path = concat!(env!("CARGO_MANIFEST_DIR"), "/icons/openvpn3-gui-16.png");
+    let mut img = ImageReader::open(Path::new(icon_path)).unwrap().decode().unwrap().to_rgba8();
+    let (width, height) = img.dimensions();
+
+    if let Some(level) = health {
+        let [r, g, b] = level.color_rgb();
+        let radius = width as f32 * 0.22;
+        let (cx, cy) = (width as f32 - radius - 0.5, height as f32 - radius - 0.5);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+            if (dx * dx + dy * dy).sqrt() <= radius {
+                *pixel = image::Rgba([r, g, b, 255]);
             }
         }
-        vec![ksni::Icon {
-            width: width as i32,
-            height: height as i32,
-            data: argb_data,
-        }]
+    }
+
+    let rgba_data = img.into_raw();
+    let mut argb_data = Vec::with_capacity(rgba_data.len());
+    for chunk in rgba_data.chunks(4) {
+        if chunk.len() == 4 {
+            argb_data.push(chunk[3]); // A
+            argb_data.push(chunk[0]); // R
+            argb_data.push(chunk[1]); // G
+            argb_data.push(chunk[2]); // B
+        }
+    }
+    ksni::Icon {
+        width: width as i32,
+        height: height as i32,
+        data: argb_data,
+    }
+}
+
+impl ksni::Tray for OpenvpnTray {
+    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
+        let health = self.state.lock().unwrap().health;
+        vec![load_icon(health)]
     }
 
     fn title(&self) -> String {
@@ -65,29 +90,10 @@ impl ksni::Tray for OpenvpnTray {
     }
 
     fn tool_tip(&self) -> ksni::ToolTip {
-        use image::ImageReader;
-        use std::path::Path;
-        let icon_path = concat!(env!("CARGO_MANIFEST_DIR"), "/icons/openvpn3-gui-16.png");
-        let img = ImageReader::open(Path::new(icon_path)).unwrap().decode().unwrap().to_rgba8();
-        let (width, height) = img.dimensions();
-        let rgba_data = img.into_raw();
-        let mut argb_data = Vec::with_capacity(rgba_data.len());
-        for chunk in rgba_data.chunks(4) {
-            if chunk.len() == 4 {
-                argb_data.push(chunk[3]); // A
-                argb_data.push(chunk[0]); // R
-                argb_data.push(chunk[1]); // G
-                argb_data.push(chunk[2]); // B
-            }
-        }
         let state = self.state.lock().unwrap();
         ksni::ToolTip {
             icon_name: String::new(),
-            icon_pixmap: vec![ksni::Icon {
-                width: width as i32,
-                height: height as i32,
-                data: argb_data,
-            }],
+            icon_pixmap: vec![load_icon(state.health)],
             title: state.tooltip.clone(),
             description: String::from("OpenVPN3 GUI"),
         }
@@ -106,8 +112,8 @@ impl ksni::Tray for OpenvpnTray {
             MenuItem::Separator,
             StandardItem {
                 label: "Quit".into(),
-                activate: Box::new(|_| {
-                    std::process::exit(0);
+                activate: Box::new(|tray: &mut Self| {
+                    tray.state.lock().unwrap().quit_requested = true;
                 }),
                 ..Default::default()
             }.into(),
@@ -120,32 +126,68 @@ impl SystemTray {
         let state = Arc::new(Mutex::new(TrayState {
             connected: false,
             tooltip: "OpenVPN3 GUI - Disconnected".into(),
+            health: None,
+            quit_requested: false,
         }));
 
         let service = OpenvpnTray {
             state: state.clone(),
         };
+        let handle = Arc::new(Mutex::new(None));
+        let handle_slot = handle.clone();
 
         // Spawn the tray service in a separate thread with its own Tokio runtime
         std::thread::spawn(move || {
             // Use spawn_without_dbus_name to avoid issues in some environments
-            if let Err(e) = service.spawn() {
-                eprintln!("Failed to spawn tray service: {}", e);
+            match service.spawn() {
+                Ok(h) => *handle_slot.lock().unwrap() = Some(h),
+                Err(e) => eprintln!("Failed to spawn tray service: {}", e),
             }
         });
 
         Ok(SystemTray { 
-            state
+            state,
+            handle,
         })
     }
 
     pub fn update_icon(&mut self, connected: bool) {
-        let mut state = self.state.lock().unwrap();
-        state.connected = connected;
+        let changed = {
+            let mut state = self.state.lock().unwrap();
+            let changed = state.connected != connected;
+            state.connected = connected;
+            changed
+        };
+        if changed {
+            self.refresh();
+        }
+    }
+
+    pub fn update_health(&mut self, health: Option<HealthLevel>) {
+        let changed = {
+            let mut state = self.state.lock().unwrap();
+            let changed = state.health != health;
+            state.health = health;
+            changed
+        };
+        if changed {
+            self.refresh();
+        }
     }
 
     pub fn update_tooltip(&mut self, text: &str) {
         let mut state = self.state.lock().unwrap();
         state.tooltip = text.to_string();
     }
+
+    pub fn quit_requested(&self) -> bool {
+        self.state.lock().unwrap().quit_requested
+    }
+
+    /// Ask the tray host to re-read icon and status
+    fn refresh(&self) {
+        if let Some(handle) = self.handle.lock().unwrap().as_ref() {
+            handle.update(|_| {});
+        }
+    }
 }
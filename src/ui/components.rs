@@ -1,17 +1,32 @@
 // UI Components and View Logic
 
-use cosmic::iced::widget::{button, checkbox, column, container, row, scrollable, text, text_input};
+use cosmic::iced::widget::{button, checkbox, column, container, mouse_area, pick_list, row, scrollable, text, text_input};
 use cosmic::iced::{Color, Length};
 use cosmic::widget::Space;
 use cosmic::Element;
+use std::time::Instant;
 
 use crate::app::OpenVpnGui;
+use crate::logging::LogLevel;
 use crate::models::{ConnectionState, Message};
-use crate::utils::format_bytes;
-use crate::ui::NetworkGraph;
+use crate::settings::{GraphWindow, Probe};
+use crate::vpn::diagnosis::Diagnosis;
+use crate::vpn::error::VpnError;
+use crate::vpn::netwatch::{SleepAction, WakeAction};
+use crate::vpn::probes::ProbeKind;
+use crate::vpn::session_log::LOG_LEVELS;
+use crate::vpn::split_tunnel::SplitSide;
+use crate::usage::{today, Period};
+use crate::utils::{format_bytes, format_duration};
+use crate::ui::{GraphColors, NetworkGraph};
+use crate::ui::history::build_history_page;
 
 /// Main view function
 pub fn view_main(app: &OpenVpnGui) -> Element<'_, Message> {
+    if app.show_history {
+        return container(build_history_page(app)).into();
+    }
+
     let main_view = container(build_main_content(app));
 
     // About overlay
@@ -35,11 +50,59 @@ fn build_main_content(app: &OpenVpnGui) -> Element<'_, Message> {
         Space::with_height(Length::Fixed(10.0)),
         build_options(app),
         Space::with_height(Length::Fixed(10.0)),
+        build_network_triggers(app),
+        build_quota(app),
+        build_network_rule_status(app),
+        Space::with_height(Length::Fixed(10.0)),
         build_stats_display(app),
+        Space::with_height(Length::Fixed(5.0)),
+        build_probe_display(app),
         Space::with_height(Length::Fixed(10.0)),
     ]
     .padding(20);
 
+    // Split tunnel launcher (Conditional)
+    if app.split_tunnel_settings().is_some_and(|s| s.enabled) {
+        content = content
+            .push(build_split_tunnel(app))
+            .push(Space::with_height(Length::Fixed(10.0)));
+    }
+
+    // VPN namespace runner (Conditional)
+    if app.namespace_active {
+        content = content
+            .push(build_namespace_runner(app))
+            .push(Space::with_height(Length::Fixed(10.0)));
+    }
+
+    // Privacy mode notice (Conditional)
+    if let Some(notice) = build_privacy_notice(app) {
+        content = content
+            .push(notice)
+            .push(Space::with_height(Length::Fixed(10.0)));
+    }
+
+    // Last error with a suggested fix
+    if let Some(error) = &app.last_error {
+        content = content
+            .push(build_error_banner(error))
+            .push(Space::with_height(Length::Fixed(10.0)));
+    }
+
+    // Likely cause of a failure, from the session log
+    if let Some(diagnosis) = &app.diagnosis {
+        content = content
+            .push(build_diagnosis_card(diagnosis))
+            .push(Space::with_height(Length::Fixed(10.0)));
+    }
+
+    // Captive portal holding the connection
+    if app.portal_url.is_some() {
+        content = content
+            .push(build_portal_notice())
+            .push(Space::with_height(Length::Fixed(10.0)));
+    }
+
     // 2FA Input (Conditional)
     if app.is_asking_2fa {
         content = content
@@ -50,49 +113,143 @@ fn build_main_content(app: &OpenVpnGui) -> Element<'_, Message> {
     // Network Graph
     if app.show_graph {
         content = content
-            .push(
-                plotters_iced::ChartWidget::new(NetworkGraph {
-                    data_in: &app.graph_data_in,
-                    data_out: &app.graph_data_out,
-                })
-                .width(Length::Fill)
-                .height(Length::Fixed(90.0))
-            )
+            .push(build_graph_options(app))
+            .push(build_graph(app))
             .push(Space::with_height(Length::Fixed(5.0)));
     }
 
-    // Logs Area - using text widget
-    content = content.push(
-        text(app.logs.join("\n")).size(12).font(cosmic::iced::Font::MONOSPACE)
-    );
+    // Logs Area
+    content = content.push(build_log_toolbar(app)).push(build_log_pane(app));
 
     content.into()
 }
 
+/// Height of one line in the log pane
+const LOG_ROW_HEIGHT: f32 = 16.0;
+/// Visible height of the log pane
+const LOG_PANE_HEIGHT: f32 = 240.0;
+
+/// Scrollable id of the log pane, used to snap it to the newest record
+pub fn log_pane_id() -> scrollable::Id {
+    scrollable::Id::new("log-pane")
+}
+
+/// Level filters, search, auto-scroll, copy and the openvpn3 log level
+fn build_log_toolbar(app: &OpenVpnGui) -> Element<'_, Message> {
+    let mut levels = row![].spacing(10).align_y(cosmic::iced::Alignment::Center);
+    for level in LogLevel::ALL {
+        levels = levels.push(
+            checkbox(level.to_string(), app.log_filter.levels.contains(&level))
+                .on_toggle(move |shown| Message::LogLevelToggled(level, shown))
+                .size(14)
+                .text_size(12),
+        );
+    }
+
+    column![
+        row![
+            levels,
+            Space::with_width(Length::Fill),
+            checkbox("Auto-scroll", app.log_auto_scroll)
+                .on_toggle(Message::ToggleLogAutoScroll)
+                .size(14)
+                .text_size(12),
+            button(text("Copy").size(12)).on_press(Message::CopyLogs),
+        ]
+        .spacing(10)
+        .align_y(cosmic::iced::Alignment::Center),
+        row![
+            text_input("Search logs...", &app.log_filter.search)
+                .on_input(Message::LogSearchChanged)
+                .size(12)
+                .width(Length::Fill),
+            text("openvpn3 log level:").size(12),
+            pick_list(&LOG_LEVELS[..], Some(app.settings.session_log_level), Message::SetSessionLogLevel),
+        ]
+        .spacing(10)
+        .align_y(cosmic::iced::Alignment::Center),
+    ]
+    .spacing(5)
+    .into()
+}
+
+/// Filtered records; only the rows in view are built, the rest is empty space
+fn build_log_pane(app: &OpenVpnGui) -> Element<'_, Message> {
+    let records: Vec<_> = app.logs.filtered(&app.log_filter).collect();
+    let total = records.len();
+
+    let visible_rows = (LOG_PANE_HEIGHT / LOG_ROW_HEIGHT).ceil() as usize + 1;
+    let first = if app.log_auto_scroll {
+        total.saturating_sub(visible_rows)
+    } else {
+        ((app.log_scroll_offset / LOG_ROW_HEIGHT).floor() as usize).min(total)
+    };
+    let last = (first + visible_rows).min(total);
+
+    let mut lines = column![Space::with_height(Length::Fixed(first as f32 * LOG_ROW_HEIGHT))];
+    for record in &records[first..last] {
+        let line = text(record.format_line())
+            .size(12)
+            .font(cosmic::iced::Font::MONOSPACE)
+            .wrapping(cosmic::iced::widget::text::Wrapping::None)
+            .height(Length::Fixed(LOG_ROW_HEIGHT));
+        let line: Element<'_, Message> = match record.level {
+            LogLevel::Error => line.color(Color::from_rgb(0.9, 0.3, 0.3)).into(),
+            LogLevel::Warn => line.color(Color::from_rgb(0.9, 0.7, 0.2)).into(),
+            LogLevel::Debug => line.color(Color::from_rgb(0.6, 0.6, 0.6)).into(),
+            LogLevel::Info => line.into(),
+        };
+        lines = lines.push(line);
+    }
+    lines = lines.push(Space::with_height(Length::Fixed((total - last) as f32 * LOG_ROW_HEIGHT)));
+
+    container(
+        scrollable(lines.width(Length::Fill))
+            .id(log_pane_id())
+            .on_scroll(Message::LogScrolled)
+            .height(Length::Fixed(LOG_PANE_HEIGHT))
+            .width(Length::Fill),
+    )
+    .padding(5)
+    .into()
+}
+
 /// Status header with connection state and IPs
 fn build_status_header(app: &OpenVpnGui) -> Element<'_, Message> {
-    let duration_text = if let Some(start) = app.connection_start {
-        let elapsed = start.elapsed().as_secs();
-        format!(
-            "{:02}:{:02}:{:02}",
-            elapsed / 3600,
-            (elapsed % 3600) / 60,
-            elapsed % 60
-        )
+    let duration_text = app
+        .connection_start
+        .map(|start| format_duration(start.elapsed().as_secs()))
+        .unwrap_or_default();
+
+    let duration_text = if app.session_paused {
+        format!("{} ⏸ Paused", duration_text).trim().to_string()
     } else {
-        String::new()
+        duration_text
+    };
+
+    // Kill switch state is shown next to the connection state
+    let duration_text = if app.kill_switch_active {
+        format!("{} 🛡 Kill switch active", duration_text).trim().to_string()
+    } else {
+        duration_text
     };
 
     // Create colored status display based on connection state
     let row = match app.state {
         ConnectionState::Connected => row![
-            cosmic::widget::text("●").size(24).class(cosmic::theme::Text::Accent),
+            match &app.health {
+                Some(health) => {
+                    let [r, g, b] = health.level.color_rgb();
+                    cosmic::widget::text("●").size(24).class(cosmic::theme::Text::Color(Color::from_rgb8(r, g, b)))
+                }
+                None => cosmic::widget::text("●").size(24).class(cosmic::theme::Text::Accent),
+            },
             cosmic::widget::text(format!("{:?}", app.state)).size(18).class(cosmic::theme::Text::Accent),
             text(duration_text).size(16),
             Space::with_width(Length::Fill),
             column![
                 text(format!("Tunnel IP: {}", app.tunnel_ip)).size(12),
-                text(format!("Public IP: {}", app.public_ip)).size(12),
+                text(public_ip_text(app)).size(12),
             ]
             .spacing(2)
         ],
@@ -103,7 +260,7 @@ fn build_status_header(app: &OpenVpnGui) -> Element<'_, Message> {
             Space::with_width(Length::Fill),
             column![
                 text(format!("Tunnel IP: {}", app.tunnel_ip)).size(12),
-                text(format!("Public IP: {}", app.public_ip)).size(12),
+                text(public_ip_text(app)).size(12),
             ]
             .spacing(2)
         ],
@@ -111,7 +268,25 @@ fn build_status_header(app: &OpenVpnGui) -> Element<'_, Message> {
     .spacing(10)
     .align_y(cosmic::iced::Alignment::Center);
 
-    row.into()
+    // Health explanation under the header while connected
+    if let (ConnectionState::Connected, Some(health)) = (app.state, &app.health) {
+        column![row, text(format!("Health: {}", health.summary())).size(12)]
+            .spacing(2)
+            .into()
+    } else {
+        row.into()
+    }
+}
+
+/// Public IP line, flagged when the lookup endpoints disagree
+fn public_ip_text(app: &OpenVpnGui) -> String {
+    if !app.settings.probe_allowed(Probe::PublicIp) {
+        "Public IP: unavailable (privacy mode)".to_string()
+    } else if app.public_ip_disagreement.is_some() {
+        format!("Public IP: {} (endpoints disagree)", app.public_ip)
+    } else {
+        format!("Public IP: {}", app.public_ip)
+    }
 }
 
 /// Config file selector
@@ -127,9 +302,10 @@ fn build_config_selector(app: &OpenVpnGui) -> Element<'_, Message> {
     .spacing(10);
 
     // Add recent configs dropdown if we have any
-    if !app.recent_configs.is_empty() {
+    if !app.profiles.recent.is_empty() {
         let recent_list = app
-            .recent_configs
+            .profiles
+            .recent
             .iter()
             .map(|path| {
                 let display_name = std::path::Path::new(path)
@@ -196,8 +372,11 @@ fn build_controls(app: &OpenVpnGui) -> Element<'_, Message> {
             .on_press(Message::ToggleVpn),
         Space::with_width(Length::Fill),
         button("Save Logs").on_press(Message::SaveLogs),
+        button("Open Log Folder").on_press(Message::OpenLogFolder),
+        button("History").on_press(Message::ShowHistory),
         button("About").on_press(Message::ShowAbout),
         button("Export Session Report").on_press(Message::SaveSessionReport),
+        button("Diagnostics Bundle").on_press(Message::CreateDiagnosticsBundle),
         show_sessions_button()
     ]
     .spacing(10)
@@ -206,16 +385,115 @@ fn build_controls(app: &OpenVpnGui) -> Element<'_, Message> {
 
 /// Settings checkboxes
 fn build_options(app: &OpenVpnGui) -> Element<'_, Message> {
+    let split_enabled = app.split_tunnel_settings().is_some_and(|s| s.enabled);
+    let namespace_enabled = app
+        .config_path
+        .as_ref()
+        .and_then(|c| app.profiles.get(c))
+        .is_some_and(|p| p.namespace);
+    let mut split_checkbox = checkbox("Split Tunnel", split_enabled);
+    let mut namespace_checkbox = checkbox("VPN Namespace", namespace_enabled);
+    // Both are stored per profile, so they need a config
+    if app.config_path.is_some() {
+        split_checkbox = split_checkbox.on_toggle(Message::ToggleSplitTunnel);
+        namespace_checkbox = namespace_checkbox.on_toggle(Message::ToggleNamespace);
+    }
+
     row![
         checkbox("Show Graph", app.show_graph)
             .on_toggle(Message::ToggleGraph),
+        checkbox("Smooth Rates", app.settings.rates.smoothing)
+            .on_toggle(Message::ToggleRateSmoothing),
         checkbox("Auto-Reconnect", app.auto_reconnect)
             .on_toggle(Message::ToggleAutoReconnect),
+        checkbox("Kill Switch", app.settings.kill_switch.enabled)
+            .on_toggle(Message::ToggleKillSwitch),
+        split_checkbox,
+        namespace_checkbox,
+        checkbox("Privacy Mode", app.settings.privacy.enabled)
+            .on_toggle(Message::TogglePrivacyMode),
+        checkbox("Portal Check", app.settings.captive_portal.enabled)
+            .on_toggle(Message::ToggleCaptivePortal),
     ]
     .spacing(20)
     .into()
 }
 
+/// Per-profile reactions to suspend/resume and network changes
+fn build_network_triggers(app: &OpenVpnGui) -> Element<'_, Message> {
+    let triggers = app.network_triggers();
+    // Stored per profile, so they need a config
+    if app.config_path.is_none() {
+        return Space::with_height(Length::Fixed(0.0)).into();
+    }
+
+    row![
+        text("On sleep:").size(14),
+        pick_list(&SleepAction::ALL[..], Some(triggers.on_sleep), Message::SetSleepAction),
+        text("On wake:").size(14),
+        pick_list(&WakeAction::ALL[..], Some(triggers.on_wake), Message::SetWakeAction),
+        checkbox("Restart on network change", triggers.restart_on_uplink_change)
+            .on_toggle(Message::ToggleRestartOnUplinkChange),
+    ]
+    .spacing(10)
+    .align_y(cosmic::iced::Alignment::Center)
+    .into()
+}
+
+/// Data quota of the selected profile, with its day/week/month usage
+fn build_quota(app: &OpenVpnGui) -> Element<'_, Message> {
+    // Stored per profile, so it needs a config
+    let Some(config) = app.config_path.as_deref() else {
+        return Space::with_height(Length::Fixed(0.0)).into();
+    };
+    let quota = app.quota();
+    let limit = if quota.enabled() { quota.limit_mb.to_string() } else { String::new() };
+    let rollups = Period::ALL
+        .iter()
+        .map(|p| format!("{}: {}", p, format_bytes(app.usage.period_usage(config, *p, today()).total() as f32)))
+        .collect::<Vec<_>>()
+        .join("  ·  ");
+
+    row![
+        text("Quota (MB):").size(14),
+        text_input("None", &limit)
+            .on_input(Message::QuotaLimitChanged)
+            .width(Length::Fixed(90.0)),
+        pick_list(&Period::ALL[..], Some(quota.period), Message::SetQuotaPeriod),
+        checkbox("Disconnect at 100%", quota.disconnect_at_limit)
+            .on_toggle(Message::ToggleQuotaDisconnect),
+        Space::with_width(Length::Fill),
+        text(rollups).size(12),
+    ]
+    .spacing(10)
+    .align_y(cosmic::iced::Alignment::Center)
+    .into()
+}
+
+/// Current network and the trusted-network rule that applies to it
+fn build_network_rule_status(app: &OpenVpnGui) -> Element<'_, Message> {
+    if app.profiles.rules.is_empty() {
+        return Space::with_height(Length::Fixed(0.0)).into();
+    }
+    let network = app
+        .network_facts
+        .as_ref()
+        .map(|f| f.summary())
+        .unwrap_or_else(|| "checking...".to_string());
+    let decision = app
+        .rule_decision
+        .as_ref()
+        .map(|d| d.summary())
+        .unwrap_or_else(|| "-".to_string());
+
+    column![
+        text(format!("Network: {}", network)).size(12),
+        text(format!("Rule: {}", decision)).size(12),
+    ]
+    .spacing(2)
+    .into()
+}
+
 /// Network statistics display
 fn build_stats_display(app: &OpenVpnGui) -> Element<'_, Message> {
     row![
@@ -226,9 +504,13 @@ fn build_stats_display(app: &OpenVpnGui) -> Element<'_, Message> {
             format_bytes(app.stats.bytes_in as f32),
             format_bytes(app.stats.bytes_out as f32)
         )),
+        build_period_usage(app),
         Space::with_width(Length::Fill),
-        if let Some(lat) = app.latency_ms {
-            text(format!("Latency: {} ms", lat))
+        if !app.settings.probe_allowed(Probe::Latency) {
+            text("Latency: unavailable (privacy mode)")
+                .size(14)
+        } else if let Some(lat) = app.probe_engine.primary_latency() {
+            text(format!("Latency: {:.0} ms", lat))
                 .size(14)
         } else {
             text("Latency: -- ms")
@@ -239,6 +521,188 @@ fn build_stats_display(app: &OpenVpnGui) -> Element<'_, Message> {
     .into()
 }
 
+/// Window picker and style for the traffic graph
+fn build_graph_options(app: &OpenVpnGui) -> Element<'_, Message> {
+    row![
+        text("Graph:").size(13),
+        pick_list(&GraphWindow::ALL[..], Some(app.settings.graph.window), Message::SetGraphWindow),
+        checkbox("Stacked", app.settings.graph.stacked)
+            .on_toggle(Message::ToggleGraphStacked),
+    ]
+    .spacing(10)
+    .align_y(cosmic::iced::Alignment::Center)
+    .into()
+}
+
+fn build_graph(app: &OpenVpnGui) -> Element<'_, Message> {
+    let now = Instant::now();
+    let window = app.settings.graph.window;
+    // The session window runs from the connect to now (a minute before any session)
+    let span_secs = window
+        .span()
+        .map(|span| span.as_secs_f32())
+        .or_else(|| app.rate_history.session_secs(now))
+        .unwrap_or(60.0);
+
+    mouse_area(
+        plotters_iced::ChartWidget::new(NetworkGraph {
+            series: app.rate_history.series(now, window.span()),
+            span_secs,
+            stacked: app.settings.graph.stacked,
+            hover: app.graph_hover,
+            colors: GraphColors::from_theme(),
+        })
+        .width(Length::Fill)
+        .height(Length::Fixed(160.0)),
+    )
+    .on_move(|position| Message::GraphHover(Some(position)))
+    .on_exit(Message::GraphHover(None))
+    .into()
+}
+
+/// Totals of the quota period, with the share of the quota used
+fn build_period_usage(app: &OpenVpnGui) -> Element<'_, Message> {
+    if app.config_path.is_none() {
+        return Space::with_width(Length::Fixed(0.0)).into();
+    }
+    let quota = app.quota();
+    let used = app.quota_usage();
+    let label = format!(
+        "{}: ↓ {} ↑ {}",
+        quota.period,
+        format_bytes(used.bytes_in as f32),
+        format_bytes(used.bytes_out as f32)
+    );
+    if !quota.enabled() {
+        return text(label).into();
+    }
+
+    let percent = quota.percent(used);
+    let color = if percent >= 100 {
+        Color::from_rgb(1.0, 0.3, 0.3)
+    } else if quota.warn_at.iter().any(|t| percent >= *t as u64) {
+        Color::from_rgb(1.0, 0.7, 0.2)
+    } else {
+        Color::from_rgb(0.7, 0.7, 0.7)
+    };
+    row![
+        text(label),
+        text(format!("({}% of {})", percent, format_bytes(quota.limit_bytes() as f32))).color(color),
+    ]
+    .spacing(6)
+    .into()
+}
+
+/// Launch applications inside or outside the tunnel
+fn build_split_tunnel(app: &OpenVpnGui) -> Element<'_, Message> {
+    let launcher = row![
+        text_input("Command to launch (e.g. firefox)", &app.launch_command)
+            .on_input(Message::LaunchCommandChanged)
+            .on_submit(Message::LaunchApp(SplitSide::Inside, app.launch_command.clone())),
+        button("Launch inside VPN")
+            .on_press(Message::LaunchApp(SplitSide::Inside, app.launch_command.clone())),
+        button("Launch outside VPN")
+            .on_press(Message::LaunchApp(SplitSide::Outside, app.launch_command.clone())),
+    ]
+    .spacing(10);
+
+    // Remembered launchers for this profile
+    let mut shortcuts = Vec::new();
+    if let Some(split) = app.split_tunnel_settings() {
+        for (side, apps) in [(SplitSide::Inside, &split.inside_apps), (SplitSide::Outside, &split.outside_apps)] {
+            for command in apps {
+                let arrow = if side == SplitSide::Inside { "🔒" } else { "🌐" };
+                shortcuts.push(
+                    button(text(format!("{} {}", arrow, command)).size(12))
+                        .on_press(Message::LaunchApp(side, command.clone()))
+                        .padding(4)
+                        .into()
+                );
+            }
+        }
+    }
+
+    let status = if app.split_tunnel_active {
+        "Split tunnel: active"
+    } else {
+        "Split tunnel: inactive (applied when connected)"
+    };
+
+    column![
+        text(status).size(12),
+        launcher,
+        row(shortcuts).spacing(5),
+    ]
+    .spacing(5)
+    .into()
+}
+
+/// Run commands inside the isolated VPN namespace
+fn build_namespace_runner(app: &OpenVpnGui) -> Element<'_, Message> {
+    row![
+        text_input("Command to run in VPN namespace", &app.netns_command)
+            .on_input(Message::NetnsCommandChanged)
+            .on_submit(Message::RunInNamespace(app.netns_command.clone())),
+        button("Run in VPN namespace")
+            .on_press(Message::RunInNamespace(app.netns_command.clone())),
+    ]
+    .spacing(10)
+    .into()
+}
+
+/// Lists the information hidden because privacy mode disabled its probe
+fn build_privacy_notice(app: &OpenVpnGui) -> Option<Element<'_, Message>> {
+    let disabled = app.settings.disabled_probes();
+    if disabled.is_empty() {
+        return None;
+    }
+
+    let unavailable = disabled
+        .iter()
+        .map(|p| p.description())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(
+        text(format!(
+            "🔒 Privacy mode: no third-party network checks. Unavailable: {}",
+            unavailable
+        ))
+        .size(12)
+        .into()
+    )
+}
+
+/// Per-target probe statistics (min/avg/max RTT, jitter, loss)
+fn build_probe_display(app: &OpenVpnGui) -> Element<'_, Message> {
+    let fmt = |v: Option<f32>| v.map_or("--".to_string(), |v| format!("{:.1}", v));
+
+    let lines = app
+        .probe_engine
+        .stats()
+        .into_iter()
+        .map(|(target, stats)| {
+            let kind = match target.kind {
+                ProbeKind::Icmp => "ICMP".to_string(),
+                ProbeKind::Tcp { port } => format!("TCP/{}", port),
+            };
+            text(format!(
+                "{} ({}): {}/{}/{} ms min/avg/max, jitter {} ms, loss {:.0}%",
+                target.name,
+                kind,
+                fmt(stats.min_ms),
+                fmt(stats.avg_ms),
+                fmt(stats.max_ms),
+                fmt(stats.jitter_ms),
+                stats.loss_pct
+            ))
+            .size(12)
+            .into()
+        })
+        .collect::<Vec<Element<'_, Message>>>();
+
+    column(lines).spacing(2).into()
+}
+
 /// Authentication notice with input
 fn build_auth_notice(app: &OpenVpnGui) -> Element<'_, Message> {
     container(
@@ -269,6 +733,88 @@ fn build_auth_notice(app: &OpenVpnGui) -> Element<'_, Message> {
     .into()
 }
 
+/// Error banner: what failed, the raw message and how to fix it
+fn build_error_banner(error: &VpnError) -> Element<'_, Message> {
+    container(
+        column![
+            row![
+                text(format!("✖ {}", error.title())).size(16),
+                Space::with_width(Length::Fill),
+                button(text("Dismiss").size(12)).on_press(Message::DismissError).padding(4),
+            ]
+            .align_y(cosmic::iced::Alignment::Center),
+            text(error.remediation()).size(13),
+            text(error.source()).size(11).font(cosmic::iced::Font::MONOSPACE),
+        ]
+        .spacing(5)
+    )
+    .style(|_theme| container::Style {
+        background: Some(cosmic::iced::Background::Color(Color::from_rgb8(60, 40, 40))),
+        border: cosmic::iced::Border {
+            color: Color::from_rgb8(239, 83, 80),
+            width: 2.0,
+            radius: 4.0.into(),
+        },
+        ..Default::default()
+    })
+    .padding(15)
+    .into()
+}
+
+/// Diagnosis card: plain-language cause, suggested fix and the log line behind it
+fn build_diagnosis_card(diagnosis: &Diagnosis) -> Element<'_, Message> {
+    container(
+        column![
+            row![
+                text("💡 Likely cause").size(16),
+                Space::with_width(Length::Fill),
+                button(text("Dismiss").size(12)).on_press(Message::DismissDiagnosis).padding(4),
+            ]
+            .align_y(cosmic::iced::Alignment::Center),
+            text(diagnosis.cause).size(13),
+            text(format!("Suggested fix: {}", diagnosis.fix)).size(13),
+            text(&diagnosis.evidence).size(11).font(cosmic::iced::Font::MONOSPACE),
+        ]
+        .spacing(5)
+    )
+    .style(|_theme| container::Style {
+        background: Some(cosmic::iced::Background::Color(Color::from_rgb8(40, 48, 60))),
+        border: cosmic::iced::Border {
+            color: Color::from_rgb8(100, 150, 220),
+            width: 2.0,
+            radius: 4.0.into(),
+        },
+        ..Default::default()
+    })
+    .padding(15)
+    .into()
+}
+
+/// Captive portal banner
+fn build_portal_notice<'a>() -> Element<'a, Message> {
+    container(
+        column![
+            text("⚠ Captive Portal Detected")
+                .size(16),
+            text("Sign in to the network. The VPN connects automatically once the portal is cleared.")
+                .size(12),
+            button("Open Sign-in Page").on_press(Message::OpenPortal).padding(10),
+        ]
+        .spacing(5)
+    )
+    .style(|_theme| container::Style {
+        background: Some(cosmic::iced::Background::Color(Color::from_rgb8(60, 50, 40))),
+        border: cosmic::iced::Border {
+            color: Color::from_rgb8(255, 193, 7),
+            width: 2.0,
+            radius: 4.0.into(),
+        },
+        ..Default::default()
+    })
+    .padding(15)
+    .into()
+}
+
 /// About modal dialog
 fn build_about_modal<'a>(_app: &OpenVpnGui) -> Element<'a, Message> {
     // COSMIC handles themes automatically through system settings
@@ -1,57 +1,194 @@
 // Network Graph with Plotters
 
-use circular_queue::CircularQueue;
+use cosmic::iced::Point;
 use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
 use plotters_iced::{Chart, ChartBuilder, DrawingBackend};
 
 use crate::models::Message;
+use crate::utils::format_bytes;
+use crate::vpn::rates::{RateSeries, RelativePoint};
 
-pub const GRAPH_WINDOW: usize = 60; // 60 seconds history
+/// Colours taken from the active COSMIC theme
+#[derive(Debug, Clone, Copy)]
+pub struct GraphColors {
+    pub rate_in: RGBColor,
+    pub rate_out: RGBColor,
+    pub text: RGBColor,
+    pub background: RGBColor,
+}
+
+impl GraphColors {
+    pub fn from_theme() -> Self {
+        let theme = cosmic::theme::active();
+        let cosmic = theme.cosmic();
+        let rgb = |c: cosmic::cosmic_theme::palette::Srgba| {
+            RGBColor((c.red * 255.0) as u8, (c.green * 255.0) as u8, (c.blue * 255.0) as u8)
+        };
+        Self {
+            rate_in: rgb(cosmic.accent_color()),
+            rate_out: rgb(cosmic.destructive_color()),
+            text: rgb(cosmic.on_bg_color()),
+            background: rgb(cosmic.bg_color()),
+        }
+    }
+}
+
+pub struct NetworkGraph {
+    /// Oldest first, placed by how long ago they were read
+    pub series: RateSeries,
+    /// Seconds shown, ending now
+    pub span_secs: f32,
+    /// Filled areas with out stacked on in
+    pub stacked: bool,
+    /// Cursor position over the graph
+    pub hover: Option<Point>,
+    pub colors: GraphColors,
+}
 
-pub struct NetworkGraph<'a> {
-    pub data_in: &'a CircularQueue<f32>,
-    pub data_out: &'a CircularQueue<f32>,
+/// Largest unit the peak rate reaches, as (divisor, label)
+fn rate_unit(max: f32) -> (f32, &'static str) {
+    if max >= 1073741824.0 {
+        (1073741824.0, "GB/s")
+    } else if max >= 1048576.0 {
+        (1048576.0, "MB/s")
+    } else if max >= 1024.0 {
+        (1024.0, "KB/s")
+    } else {
+        (1.0, "B/s")
+    }
+}
+
+/// Time axis label for an offset from now (negative seconds)
+fn format_offset(secs: f32) -> String {
+    let ago = (-secs).round() as u64;
+    match ago {
+        0 => "now".to_string(),
+        s if s < 60 => format!("-{}s", s),
+        s if s < 3600 && s % 60 == 0 => format!("-{}m", s / 60),
+        s if s < 3600 => format!("-{}m{:02}s", s / 60, s % 60),
+        s => format!("-{}h{:02}m", s / 3600, (s % 3600) / 60),
+    }
 }
 
-impl<'a> Chart<Message> for NetworkGraph<'a> {
+impl NetworkGraph {
+    /// Height of a point: in, and out on top of it when stacked
+    fn heights(&self, p: &RelativePoint) -> (f32, f32) {
+        if self.stacked {
+            (p.rate_in, p.rate_in + p.rate_out)
+        } else {
+            (p.rate_in, p.rate_out)
+        }
+    }
+}
+
+impl Chart<Message> for NetworkGraph {
     type State = ();
 
     fn build_chart<DB: DrawingBackend>(&self, _state: &Self::State, mut builder: ChartBuilder<DB>) {
+        let colors = self.colors;
+        let span = self.span_secs.max(1.0);
 
-        // Find max for scaling
-        let max_in = self.data_in.iter().fold(0.0f32, |a, &b| a.max(b));
-        let max_out = self.data_out.iter().fold(0.0f32, |a, &b| a.max(b));
-        let global_max = max_in.max(max_out).max(1024.0); // Minimum scale 1KB
+        // Scale to the highest point, with some headroom
+        let top = self.series.points
+            .iter()
+            .map(|p| {
+                let (a, b) = self.heights(p);
+                a.max(b)
+            })
+            .fold(0.0f32, f32::max)
+            .max(1024.0) // Minimum scale 1KB
+            * 1.1;
+        let (divisor, unit) = rate_unit(top);
 
-        // Build chart
-        let mut chart = builder
-            .x_label_area_size(0)
-            .y_label_area_size(0)
-            .build_cartesian_2d(0.0..GRAPH_WINDOW as f32, 0.0..global_max)
-            .unwrap();
+        // Now on the right, the start of the window on the left
+        let Ok(mut chart) = builder
+            .margin(5)
+            .x_label_area_size(18)
+            .y_label_area_size(44)
+            .build_cartesian_2d(-span..0.0, 0.0..top)
+        else {
+            return;
+        };
 
-        // Draw download line (blue)
-        let data_in_points: Vec<(f32, f32)> = self.data_in
-            .iter()
-            .enumerate()
-            .map(|(i, &val)| (i as f32, val))
-            .collect();
-        
-        chart.draw_series(LineSeries::new(
-            data_in_points,
-            &RGBColor(66, 165, 245),
-        )).ok();
-
-        // Draw upload line (red)
-        let data_out_points: Vec<(f32, f32)> = self.data_out
+        let label_font = ("sans-serif", 11).into_font().color(&colors.text);
+        chart.configure_mesh()
+            .x_labels(6)
+            .y_labels(4)
+            .x_label_formatter(&|v| format_offset(*v))
+            .y_label_formatter(&|v| format!("{:.1}", v / divisor))
+            .y_desc(unit)
+            .label_style(label_font.clone())
+            .axis_desc_style(label_font.clone())
+            .axis_style(colors.text.mix(0.6))
+            .bold_line_style(colors.text.mix(0.12))
+            .light_line_style(TRANSPARENT)
+            .draw()
+            .ok();
+
+        // Each run of readings is drawn on its own, so missed ones leave a gap
+        for run in self.series.runs() {
+            let line_in = run.iter().map(|p| (-p.secs_ago, self.heights(p).0));
+            let line_out = run.iter().map(|p| (-p.secs_ago, self.heights(p).1));
+            if self.stacked {
+                // Out first, so in covers the lower part of it
+                chart.draw_series(AreaSeries::new(line_out, 0.0, colors.rate_out.mix(0.45))
+                    .border_style(colors.rate_out)).ok();
+                chart.draw_series(AreaSeries::new(line_in, 0.0, colors.rate_in.mix(0.55))
+                    .border_style(colors.rate_in)).ok();
+            } else {
+                chart.draw_series(LineSeries::new(line_in, &colors.rate_in)).ok();
+                chart.draw_series(LineSeries::new(line_out, &colors.rate_out)).ok();
+            }
+        }
+
+        // Legend (the series above are drawn per run, so it gets entries of its own)
+        for (label, color) in [("↓ In", colors.rate_in), ("↑ Out", colors.rate_out)] {
+            if let Ok(anno) = chart.draw_series(std::iter::empty::<Circle<(f32, f32), i32>>()) {
+                anno.label(label)
+                    .legend(move |(x, y)| Rectangle::new([(x, y - 4), (x + 10, y + 4)], color.filled()));
+            }
+        }
+        chart.configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(colors.background.mix(0.8))
+            .border_style(colors.text.mix(0.3))
+            .label_font(label_font.clone())
+            .draw()
+            .ok();
+
+        // Crosshair on the point nearest the cursor, with its exact values
+        let Some(cursor) = self.hover else {
+            return;
+        };
+        let (xs, _) = chart.plotting_area().get_pixel_range();
+        let x = cursor.x as i32;
+        if !xs.contains(&x) {
+            return;
+        }
+        let at = -span + (x - xs.start) as f32 / (xs.end - xs.start).max(1) as f32 * span;
+        let Some(point) = self.series.points
             .iter()
-            .enumerate()
-            .map(|(i, &val)| (i as f32, val))
-            .collect();
-        
-        chart.draw_series(LineSeries::new(
-            data_out_points,
-            &RGBColor(239, 83, 80),
-        )).ok();
+            .min_by(|a, b| (-a.secs_ago - at).abs().total_cmp(&(-b.secs_ago - at).abs()))
+        else {
+            return;
+        };
+
+        let px = -point.secs_ago;
+        let (y_in, y_out) = self.heights(point);
+        chart.draw_series(LineSeries::new([(px, 0.0), (px, top)], colors.text.mix(0.5))).ok();
+        chart.draw_series([
+            Circle::new((px, y_in), 3, colors.rate_in.filled()),
+            Circle::new((px, y_out), 3, colors.rate_out.filled()),
+        ]).ok();
+        let readout = format!(
+            "{}  ↓ {}/s  ↑ {}/s",
+            format_offset(px),
+            format_bytes(point.rate_in),
+            format_bytes(point.rate_out)
+        );
+        chart.plotting_area()
+            .draw(&Text::new(readout, (0.0, top), label_font.pos(Pos::new(HPos::Right, VPos::Top))))
+            .ok();
     }
 }
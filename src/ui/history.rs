@@ -0,0 +1,150 @@
+// History page - finished sessions with filters, details and retention
+
+use cosmic::iced::widget::{button, checkbox, column, container, pick_list, row, scrollable, text, text_input};
+use cosmic::iced::{Color, Length};
+use cosmic::widget::Space;
+use cosmic::Element;
+
+use crate::app::OpenVpnGui;
+use crate::history::{HistoryEntry, Retention};
+use crate::models::Message;
+use crate::utils::{format_bytes, format_duration};
+
+/// Shown in the profile filter for "no filter"
+const ALL_PROFILES: &str = "All profiles";
+
+pub fn build_history_page(app: &OpenVpnGui) -> Element<'_, Message> {
+    let entries: Vec<&HistoryEntry> = app.history.filtered(&app.history_filter).collect();
+
+    let mut profiles = vec![ALL_PROFILES.to_string()];
+    profiles.extend(app.history.profiles());
+    let selected_profile = app
+        .history_filter
+        .profile
+        .clone()
+        .unwrap_or_else(|| ALL_PROFILES.to_string());
+
+    let header = row![
+        text("Connection History").size(24),
+        Space::with_width(Length::Fill),
+        button("Back").on_press(Message::CloseHistory),
+    ]
+    .align_y(cosmic::iced::Alignment::Center);
+
+    let filters = row![
+        pick_list(profiles, Some(selected_profile), Message::HistoryProfileFilter),
+        text_input("From (YYYY-MM-DD)", &app.history_filter.from)
+            .on_input(Message::HistoryFromChanged)
+            .width(Length::Fixed(160.0)),
+        text_input("To (YYYY-MM-DD)", &app.history_filter.to)
+            .on_input(Message::HistoryToChanged)
+            .width(Length::Fixed(160.0)),
+    ]
+    .spacing(10)
+    .align_y(cosmic::iced::Alignment::Center);
+
+    let retention = Retention(app.settings.history.retention_days);
+    let settings = row![
+        checkbox("Record history", app.settings.history.enabled).on_toggle(Message::ToggleHistory),
+        text("Keep:").size(13),
+        pick_list(&Retention::CHOICES[..], Some(retention), Message::SetHistoryRetention),
+        Space::with_width(Length::Fill),
+        button("Clear History").on_press(Message::ClearHistory),
+    ]
+    .spacing(10)
+    .align_y(cosmic::iced::Alignment::Center);
+
+    let total_in: u64 = entries.iter().map(|e| e.bytes_in).sum();
+    let total_out: u64 = entries.iter().map(|e| e.bytes_out).sum();
+    let summary = text(format!(
+        "{} sessions, ↓ {} ↑ {}",
+        entries.len(),
+        format_bytes(total_in as f32),
+        format_bytes(total_out as f32)
+    ))
+    .size(13);
+
+    let mut list = column![].spacing(4);
+    if entries.is_empty() {
+        list = list.push(text("No sessions recorded.").size(13));
+    }
+    for entry in entries.iter().copied() {
+        list = list.push(build_history_row(entry));
+        if app.history_selected == Some(entry.id) {
+            list = list.push(build_history_detail(entry));
+        }
+    }
+
+    column![
+        header,
+        filters,
+        settings,
+        summary,
+        scrollable(list).height(Length::Fill),
+    ]
+    .spacing(12)
+    .padding(20)
+    .into()
+}
+
+/// One line per session; clicking it toggles the details
+fn build_history_row(entry: &HistoryEntry) -> Element<'_, Message> {
+    button(
+        row![
+            text(entry.started_local()).size(13).width(Length::Fixed(150.0)),
+            text(entry.profile_name()).size(13).width(Length::Fill),
+            text(format_duration(entry.duration_secs())).size(13).width(Length::Fixed(80.0)),
+            text(format!("↓ {}", format_bytes(entry.bytes_in as f32))).size(13).width(Length::Fixed(100.0)),
+            text(format!("↑ {}", format_bytes(entry.bytes_out as f32))).size(13).width(Length::Fixed(100.0)),
+        ]
+        .spacing(10),
+    )
+    .on_press(Message::SelectHistoryEntry(entry.id))
+    .width(Length::Fill)
+    .into()
+}
+
+fn build_history_detail(entry: &HistoryEntry) -> Element<'_, Message> {
+    let field = |label: &str, value: String| {
+        row![
+            text(format!("{}:", label)).size(12).width(Length::Fixed(130.0)),
+            text(value).size(12),
+        ]
+        .spacing(10)
+    };
+    let latency = entry
+        .avg_latency_ms
+        .map(|ms| format!("{:.1} ms", ms))
+        .unwrap_or_else(|| "-".to_string());
+
+    container(
+        column![
+            field("Profile", entry.profile.clone()),
+            field("Started", entry.started_local()),
+            field("Ended", entry.ended_local()),
+            field("Duration", format_duration(entry.duration_secs())),
+            field("Received", format!("{} ({} bytes)", format_bytes(entry.bytes_in as f32), entry.bytes_in)),
+            field("Sent", format!("{} ({} bytes)", format_bytes(entry.bytes_out as f32), entry.bytes_out)),
+            field("Peak rate in", format!("{}/s", format_bytes(entry.peak_rate_in))),
+            field("Peak rate out", format!("{}/s", format_bytes(entry.peak_rate_out))),
+            field("Average latency", latency),
+            field("Disconnect reason", entry.disconnect_reason.clone()),
+            row![
+                Space::with_width(Length::Fill),
+                button(text("Delete").size(12)).on_press(Message::DeleteHistoryEntry(entry.id)),
+            ],
+        ]
+        .spacing(4),
+    )
+    .style(|_theme| container::Style {
+        background: Some(cosmic::iced::Background::Color(Color::from_rgb8(45, 45, 45))),
+        border: cosmic::iced::Border {
+            color: Color::from_rgb8(100, 100, 100),
+            width: 1.0,
+            radius: 4.0.into(),
+        },
+        ..Default::default()
+    })
+    .padding(10)
+    .into()
+}
@@ -2,6 +2,7 @@
 
 pub mod graph;
 pub mod components;
+pub mod history;
 
 // Re-export
 pub use graph::*;
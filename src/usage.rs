@@ -0,0 +1,349 @@
+// Data usage per profile - daily byte counts in $XDG_STATE_HOME/openvpn-gui/usage.json, plus quotas
+
+use chrono::{Datelike, Duration as DateDuration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::log_files::state_dir;
+
+/// How often usage is written back while a session runs (it is always written when one ends)
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+/// Days older than this are dropped when the file is loaded
+const KEEP_DAYS: i64 = 400;
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+impl Usage {
+    pub fn total(&self) -> u64 {
+        self.bytes_in + self.bytes_out
+    }
+
+    fn add(&mut self, other: Usage) {
+        self.bytes_in += other.bytes_in;
+        self.bytes_out += other.bytes_out;
+    }
+}
+
+/// Rollup period; weeks start on Monday
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Period {
+    Day,
+    Week,
+    #[default]
+    Month,
+}
+
+impl Period {
+    pub const ALL: [Period; 3] = [Period::Day, Period::Week, Period::Month];
+
+    /// First day of the period that contains `today`
+    pub fn start(self, today: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => today,
+            Period::Week => today - DateDuration::days(today.weekday().num_days_from_monday() as i64),
+            Period::Month => today.with_day(1).unwrap_or(today),
+        }
+    }
+
+    /// Identifies the period containing `today`, e.g. "month 2026-10-01"
+    fn key(self, today: NaiveDate) -> String {
+        format!("{} {}", self.short_name(), self.start(today).format(DATE_FORMAT))
+    }
+
+    fn short_name(self) -> &'static str {
+        match self {
+            Period::Day => "day",
+            Period::Week => "week",
+            Period::Month => "month",
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Period::Day => write!(f, "Today"),
+            Period::Week => write!(f, "This week"),
+            Period::Month => write!(f, "This month"),
+        }
+    }
+}
+
+/// Per-profile data cap; in and out both count towards it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quota {
+    pub period: Period,
+    /// 0 turns the quota off
+    pub limit_mb: u64,
+    /// Percentages that raise a notification (reaching 100% always does)
+    pub warn_at: Vec<u8>,
+    /// Disconnect once the limit is reached, and refuse to connect until the period rolls over
+    pub disconnect_at_limit: bool,
+}
+
+impl Default for Quota {
+    fn default() -> Self {
+        Self {
+            period: Period::Month,
+            limit_mb: 0,
+            warn_at: vec![80, 90],
+            disconnect_at_limit: false,
+        }
+    }
+}
+
+impl Quota {
+    /// Largest limit accepted from the UI (1 PiB)
+    pub const MAX_LIMIT_MB: u64 = 1 << 30;
+
+    pub fn enabled(&self) -> bool {
+        self.limit_mb > 0
+    }
+
+    /// Saturates for limits edited into the settings file that do not fit in bytes
+    pub fn limit_bytes(&self) -> u64 {
+        self.limit_mb.saturating_mul(1024 * 1024)
+    }
+
+    /// Share of the limit used (0 when the quota is off)
+    pub fn percent(&self, used: Usage) -> u64 {
+        if !self.enabled() {
+            return 0;
+        }
+        used.total().saturating_mul(100) / self.limit_bytes().max(1)
+    }
+
+    pub fn exhausted(&self, used: Usage) -> bool {
+        self.enabled() && used.total() >= self.limit_bytes()
+    }
+}
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Usage per profile and day, mirrored to the JSON file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageStore {
+    /// Config path -> day (YYYY-MM-DD) -> bytes
+    profiles: BTreeMap<String, BTreeMap<String, Usage>>,
+    /// Counters last seen per openvpn3 session path, so a session that outlives the app is not counted twice
+    sessions: BTreeMap<String, Usage>,
+    /// Highest quota threshold already announced, per profile and period
+    alerted: BTreeMap<String, u8>,
+    #[serde(skip)]
+    dirty: bool,
+    #[serde(skip)]
+    last_save: Option<Instant>,
+}
+
+impl UsageStore {
+    /// Read the file, dropping days past the retention
+    pub fn load() -> Self {
+        let mut store: UsageStore = std::fs::read_to_string(Self::file_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        let cutoff = (today() - DateDuration::days(KEEP_DAYS)).format(DATE_FORMAT).to_string();
+        for days in store.profiles.values_mut() {
+            days.retain(|day, _| *day >= cutoff);
+        }
+        store.profiles.retain(|_, days| !days.is_empty());
+        store
+    }
+
+    /// Count the growth of a session's counters since they were last seen. Counters that
+    /// went down mean openvpn3 reset them, so everything they show now is new traffic.
+    pub fn record(&mut self, profile: &str, session: &str, total_in: u64, total_out: u64) {
+        let last = self.sessions.get(session).copied().unwrap_or_default();
+        let delta = |now: u64, before: u64| if now >= before { now - before } else { now };
+        let added = Usage {
+            bytes_in: delta(total_in, last.bytes_in),
+            bytes_out: delta(total_out, last.bytes_out),
+        };
+        self.sessions.insert(session.to_string(), Usage { bytes_in: total_in, bytes_out: total_out });
+        if added.total() > 0 {
+            self.profiles
+                .entry(profile.to_string())
+                .or_default()
+                .entry(today().format(DATE_FORMAT).to_string())
+                .or_default()
+                .add(added);
+        }
+        self.dirty = true;
+    }
+
+    /// Forget a session's counters once it is gone
+    pub fn end_session(&mut self, session: &str) {
+        if self.sessions.remove(session).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Usage of `profile` in the period containing `today`
+    pub fn period_usage(&self, profile: &str, period: Period, today: NaiveDate) -> Usage {
+        let from = period.start(today).format(DATE_FORMAT).to_string();
+        let to = today.format(DATE_FORMAT).to_string();
+        let mut usage = Usage::default();
+        if let Some(days) = self.profiles.get(profile) {
+            for (_, day) in days.range(from..=to) {
+                usage.add(*day);
+            }
+        }
+        usage
+    }
+
+    /// The highest threshold newly crossed in the current period, if any. Each one is
+    /// announced once per period, across restarts.
+    pub fn check_quota(&mut self, profile: &str, quota: &Quota, today: NaiveDate) -> Option<u8> {
+        if !quota.enabled() {
+            return None;
+        }
+        let percent = quota.percent(self.period_usage(profile, quota.period, today));
+        let key = format!("{} {}", profile, quota.period.key(today));
+        let announced = self.alerted.get(&key).copied().unwrap_or(0);
+        let crossed = quota
+            .warn_at
+            .iter()
+            .copied()
+            .chain([100])
+            .filter(|t| *t as u64 <= percent && *t > announced)
+            .max()?;
+
+        // Older periods will not be looked at again
+        let prefix = format!("{} ", profile);
+        let suffix = quota.period.key(today);
+        self.alerted.retain(|k, _| !k.starts_with(&prefix) || k.ends_with(&suffix));
+        self.alerted.insert(key, crossed);
+        self.dirty = true;
+        Some(crossed)
+    }
+
+    /// Write the file if something changed and the last write was a while ago
+    pub fn save_if_due(&mut self) -> std::io::Result<()> {
+        if self.dirty && self.last_save.is_none_or(|t| t.elapsed() >= SAVE_INTERVAL) {
+            // A failed write waits for the next interval too
+            self.last_save = Some(Instant::now());
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&mut self) -> std::io::Result<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::create_dir_all(state_dir())?;
+        std::fs::write(Self::file_path(), contents)?;
+        self.dirty = false;
+        self.last_save = Some(Instant::now());
+        Ok(())
+    }
+
+    fn file_path() -> PathBuf {
+        state_dir().join("usage.json")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, DATE_FORMAT).unwrap()
+    }
+
+    fn usage(total_mb: u64) -> Usage {
+        Usage { bytes_in: total_mb * MB / 2, bytes_out: total_mb * MB - total_mb * MB / 2 }
+    }
+
+    /// A store with `profile` having used the given megabytes on each day
+    fn store(profile: &str, days: &[(&str, u64)]) -> UsageStore {
+        let mut store = UsageStore::default();
+        let entries = store.profiles.entry(profile.to_string()).or_default();
+        for (day, mb) in days {
+            entries.insert(day.to_string(), usage(*mb));
+        }
+        store
+    }
+
+    fn quota(period: Period, limit_mb: u64) -> Quota {
+        Quota { period, limit_mb, ..Default::default() }
+    }
+
+    #[test]
+    fn period_starts() {
+        // 2026-10-18 is a Sunday
+        let sunday = date("2026-10-18");
+        assert_eq!(Period::Day.start(sunday), sunday);
+        assert_eq!(Period::Week.start(sunday), date("2026-10-12"));
+        assert_eq!(Period::Week.start(date("2026-10-12")), date("2026-10-12"));
+        assert_eq!(Period::Month.start(sunday), date("2026-10-01"));
+        // Weeks run across month and year ends
+        assert_eq!(Period::Week.start(date("2027-01-01")), date("2026-12-28"));
+    }
+
+    #[test]
+    fn period_usage_sums_days_in_the_period() {
+        let store = store(
+            "work",
+            &[("2026-09-30", 100), ("2026-10-01", 1), ("2026-10-12", 2), ("2026-10-17", 4), ("2026-10-18", 8), ("2026-10-19", 16)],
+        );
+        let today = date("2026-10-18");
+        assert_eq!(store.period_usage("work", Period::Day, today), usage(8));
+        assert_eq!(store.period_usage("work", Period::Week, today).total(), 14 * MB);
+        assert_eq!(store.period_usage("work", Period::Month, today).total(), 15 * MB);
+        assert_eq!(store.period_usage("home", Period::Month, today), Usage::default());
+    }
+
+    #[test]
+    fn thresholds_are_announced_once_per_period() {
+        let mut store = store("work", &[("2026-10-05", 85)]);
+        let quota = quota(Period::Month, 100);
+        let today = date("2026-10-18");
+        assert_eq!(store.check_quota("work", &quota, today), Some(80));
+        assert_eq!(store.check_quota("work", &quota, today), None);
+
+        // Crossing several at once announces the highest
+        store.profiles.get_mut("work").unwrap().insert("2026-10-18".to_string(), usage(20));
+        assert_eq!(store.check_quota("work", &quota, today), Some(100));
+        assert_eq!(store.check_quota("work", &quota, today), None);
+
+        // A new month starts over
+        let next_month = date("2026-11-02");
+        store.profiles.get_mut("work").unwrap().insert("2026-11-01".to_string(), usage(95));
+        assert_eq!(store.check_quota("work", &quota, next_month), Some(90));
+        assert_eq!(store.alerted.len(), 1);
+    }
+
+    #[test]
+    fn disabled_quota_never_alerts() {
+        let mut store = store("work", &[("2026-10-18", 1000)]);
+        let today = date("2026-10-18");
+        assert_eq!(store.check_quota("work", &quota(Period::Day, 0), today), None);
+        assert_eq!(quota(Period::Day, 0).percent(usage(1000)), 0);
+        assert!(!quota(Period::Day, 0).exhausted(usage(1000)));
+    }
+
+    #[test]
+    fn huge_limits_do_not_overflow() {
+        let huge = quota(Period::Month, u64::MAX);
+        assert_eq!(huge.limit_bytes(), u64::MAX);
+        assert_eq!(huge.percent(usage(1000)), 0);
+        assert!(!huge.exhausted(usage(1000)));
+
+        let capped = quota(Period::Month, Quota::MAX_LIMIT_MB);
+        assert_eq!(capped.limit_bytes(), Quota::MAX_LIMIT_MB * MB);
+        assert_eq!(quota(Period::Month, 100).percent(usage(50)), 50);
+    }
+}
@@ -1,12 +1,19 @@
 // Utility functions
 
-/// Format bytes into human-readable format (B, KB, MB)
+/// Format bytes into human-readable format (B, KB, MB, GB)
 pub fn format_bytes(num: f32) -> String {
     if num < 1024.0 { 
         format!("{:.0} B", num) 
     } else if num < 1048576.0 { 
         format!("{:.1} KB", num / 1024.0) 
-    } else { 
+    } else if num < 1073741824.0 { 
         format!("{:.2} MB", num / 1048576.0) 
+    } else { 
+        format!("{:.2} GB", num / 1073741824.0) 
     }
 }
+
+/// Format seconds as HH:MM:SS
+pub fn format_duration(secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
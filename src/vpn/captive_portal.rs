@@ -0,0 +1,145 @@
+// Captive portal detection - a plain HTTP request whose answer a portal will hijack
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use url::Url;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptivePortalSettings {
+    /// Off by default: the check is a request to a third-party host
+    pub enabled: bool,
+    /// Must be plain HTTP: portals cannot intercept HTTPS without a certificate error
+    pub url: String,
+    pub expected_status: u16,
+    /// Body the check URL normally returns (empty = only check the status)
+    pub expected_body: String,
+    /// Re-check when a session has been connecting this long
+    pub connecting_timeout_secs: u64,
+    /// How often to re-check while a portal is holding the connection
+    pub retry_secs: u64,
+    pub timeout_secs: u64,
+}
+
+impl Default for CaptivePortalSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "http://nmcheck.gnome.org/check_network_status.txt".to_string(),
+            expected_status: 200,
+            expected_body: "NetworkManager is online".to_string(),
+            connecting_timeout_secs: 20,
+            retry_secs: 5,
+            timeout_secs: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortalCheck {
+    /// The expected answer came back: the network is open
+    Clear,
+    /// Something answered in its place; the URL to open to sign in
+    Portal(String),
+    /// The check itself failed (offline, DNS, timeout); not evidence of a portal
+    Failed(String),
+}
+
+/// Fetch the check URL without following redirects and compare the answer
+pub async fn check_portal(settings: CaptivePortalSettings) -> PortalCheck {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(settings.timeout_secs.max(1)))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => return PortalCheck::Failed(e.to_string()),
+    };
+
+    let response = match client.get(&settings.url).send().await {
+        Ok(response) => response,
+        Err(e) => return PortalCheck::Failed(e.to_string()),
+    };
+
+    let status = response.status();
+    if status.is_redirection() {
+        // Portals usually redirect to their login page
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|l| l.to_str().ok())
+            .and_then(|l| resolve_location(&settings.url, l));
+        return PortalCheck::Portal(location.unwrap_or(settings.url));
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    classify(&settings, status.as_u16(), &body)
+}
+
+/// The page a redirect points to, relative to the check URL. Only web pages are
+/// returned, since the result is handed to the browser.
+fn resolve_location(check_url: &str, location: &str) -> Option<String> {
+    let url = Url::parse(check_url).ok()?.join(location).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+/// Decide from a non-redirect answer whether it is the real one
+pub fn classify(settings: &CaptivePortalSettings, status: u16, body: &str) -> PortalCheck {
+    let body_ok = settings.expected_body.is_empty() || body.trim() == settings.expected_body.trim();
+    if status == settings.expected_status && body_ok {
+        PortalCheck::Clear
+    } else {
+        // The portal page is served in place of the check URL
+        PortalCheck::Portal(settings.url.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECK_URL: &str = "http://nmcheck.gnome.org/check_network_status.txt";
+
+    #[test]
+    fn absolute_location_is_kept() {
+        assert_eq!(
+            resolve_location(CHECK_URL, "https://portal.example.com/login?next=x").as_deref(),
+            Some("https://portal.example.com/login?next=x")
+        );
+    }
+
+    #[test]
+    fn relative_location_resolves_against_check_url() {
+        assert_eq!(
+            resolve_location(CHECK_URL, "/login").as_deref(),
+            Some("http://nmcheck.gnome.org/login")
+        );
+        assert_eq!(
+            resolve_location(CHECK_URL, "portal.html").as_deref(),
+            Some("http://nmcheck.gnome.org/portal.html")
+        );
+        assert_eq!(
+            resolve_location(CHECK_URL, "//portal.example.com/").as_deref(),
+            Some("http://portal.example.com/")
+        );
+    }
+
+    #[test]
+    fn non_web_location_is_refused() {
+        assert_eq!(resolve_location(CHECK_URL, "file:///etc/passwd"), None);
+        assert_eq!(resolve_location(CHECK_URL, "javascript:alert(1)"), None);
+    }
+
+    #[test]
+    fn classify_answers() {
+        let settings = CaptivePortalSettings::default();
+        assert_eq!(classify(&settings, 200, "NetworkManager is online\n"), PortalCheck::Clear);
+        assert_eq!(classify(&settings, 200, "<html>Sign in</html>"), PortalCheck::Portal(settings.url.clone()));
+        assert_eq!(classify(&settings, 511, ""), PortalCheck::Portal(settings.url.clone()));
+    }
+
+    #[test]
+    fn opt_in() {
+        assert!(!CaptivePortalSettings::default().enabled);
+    }
+}
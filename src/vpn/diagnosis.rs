@@ -0,0 +1,155 @@
+// Failure diagnosis - known openvpn3 log/status patterns explained in plain language
+
+/// A known failure: any of `patterns` (lowercase substrings) identifies it
+pub struct DiagnosisRule {
+    pub id: &'static str,
+    pub patterns: &'static [&'static str],
+    pub cause: &'static str,
+    pub fix: &'static str,
+}
+
+/// Checked in order, so more specific rules come before the ones they imply
+/// (an expired CRL also fails certificate verification).
+pub const RULES: &[DiagnosisRule] = &[
+    DiagnosisRule {
+        id: "crl_expired",
+        patterns: &["crl has expired", "crl_has_expired", "crl expired"],
+        cause: "The certificate revocation list (CRL) in your profile has expired, so the server certificate cannot be checked.",
+        fix: "Ask your VPN administrator for an updated CRL or a fresh profile, then import it again.",
+    },
+    DiagnosisRule {
+        id: "cert_expired",
+        patterns: &["certificate has expired", "cert_has_expired", "certificate expired"],
+        cause: "A certificate used for the connection has expired.",
+        fix: "Check your system clock. If it is correct, ask your VPN administrator for a renewed certificate or profile.",
+    },
+    DiagnosisRule {
+        id: "cert_verify_failed",
+        patterns: &["cert_verify_fail", "certificate verify failed", "verify error", "certificate verification failed"],
+        cause: "The server's certificate could not be verified against the CA in your profile.",
+        fix: "Make sure you are using the current profile for this server. The server or CA may have changed.",
+    },
+    DiagnosisRule {
+        id: "auth_failed",
+        patterns: &["auth_failed", "authentication failed", "auth failed", "invalid username or password"],
+        cause: "The server rejected your credentials.",
+        fix: "Check your username, password and 2FA code. Your account may also be locked or expired.",
+    },
+    DiagnosisRule {
+        id: "cipher_negotiation",
+        patterns: &["no shared cipher", "cipher negotiation failed", "unsupported cipher", "cipher not supported", "ncp cipher"],
+        cause: "Client and server could not agree on a data channel cipher.",
+        fix: "Ask your VPN administrator to enable a modern cipher (AES-256-GCM or CHACHA20-POLY1305), or update the profile's data-ciphers.",
+    },
+    DiagnosisRule {
+        id: "tls_handshake_timeout",
+        patterns: &["tls key negotiation failed", "handshake_timeout", "handshake timeout", "tls handshake failed"],
+        cause: "The TLS handshake with the VPN server did not complete. Packets are not getting through, or the server is not answering.",
+        fix: "Check that the server address and port are right and that a firewall is not blocking them. Try the TCP remote if the profile has one.",
+    },
+    DiagnosisRule {
+        id: "remote_dns_failed",
+        patterns: &["resolve_error", "resolve error", "temporary failure in name resolution", "host not found", "name or service not known"],
+        cause: "The VPN server's hostname could not be resolved.",
+        fix: "Check your internet connection and DNS. If you are on a captive portal, sign in first.",
+    },
+    DiagnosisRule {
+        id: "network_unreachable",
+        patterns: &["network is unreachable", "no route to host", "host is unreachable"],
+        cause: "There is no route from this computer to the VPN server.",
+        fix: "Check your network connection. If the server is only reachable from certain networks, connect from one of them.",
+    },
+];
+
+/// A matched rule together with the line that triggered it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+    pub rule_id: &'static str,
+    pub cause: &'static str,
+    pub fix: &'static str,
+    pub evidence: String,
+}
+
+/// Match a single log or status line
+pub fn diagnose(line: &str) -> Option<Diagnosis> {
+    let lower = line.to_lowercase();
+    RULES
+        .iter()
+        .find(|rule| rule.patterns.iter().any(|p| lower.contains(p)))
+        .map(|rule| Diagnosis {
+            rule_id: rule.id,
+            cause: rule.cause,
+            fix: rule.fix,
+            evidence: line.trim().to_string(),
+        })
+}
+
+/// Most specific diagnosis over a whole log or status output
+pub fn diagnose_log(text: &str) -> Option<Diagnosis> {
+    let found = text.lines().filter_map(diagnose).collect::<Vec<_>>();
+    RULES
+        .iter()
+        .find_map(|rule| found.iter().find(|d| d.rule_id == rule.id).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        let path = format!("{}/tests/fixtures/diagnosis/{}", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+    }
+
+    fn rule_for(name: &str) -> Option<&'static str> {
+        diagnose_log(&fixture(name)).map(|d| d.rule_id)
+    }
+
+    #[test]
+    fn tls_handshake_timeout() {
+        assert_eq!(rule_for("tls_handshake_timeout.log"), Some("tls_handshake_timeout"));
+    }
+
+    #[test]
+    fn certificate_verify_failed() {
+        assert_eq!(rule_for("cert_verify_failed.log"), Some("cert_verify_failed"));
+    }
+
+    #[test]
+    fn cipher_negotiation_failure() {
+        assert_eq!(rule_for("cipher_negotiation.log"), Some("cipher_negotiation"));
+    }
+
+    #[test]
+    fn auth_failed() {
+        assert_eq!(rule_for("auth_failed.log"), Some("auth_failed"));
+    }
+
+    #[test]
+    fn remote_dns_failure() {
+        assert_eq!(rule_for("remote_dns_failed.log"), Some("remote_dns_failed"));
+    }
+
+    #[test]
+    fn expired_crl_wins_over_verify_failure() {
+        assert_eq!(rule_for("crl_expired.log"), Some("crl_expired"));
+    }
+
+    #[test]
+    fn clean_connect_has_no_diagnosis() {
+        assert_eq!(rule_for("connected.log"), None);
+    }
+
+    #[test]
+    fn evidence_is_the_matching_line() {
+        let diagnosis = diagnose("  Tue Oct 14 09:12:03 2025 [CLIENT:ERROR] AUTH_FAILED  ").unwrap();
+        assert_eq!(diagnosis.evidence, "Tue Oct 14 09:12:03 2025 [CLIENT:ERROR] AUTH_FAILED");
+    }
+
+    #[test]
+    fn rule_ids_are_unique() {
+        for (i, rule) in RULES.iter().enumerate() {
+            assert!(RULES[i + 1..].iter().all(|r| r.id != rule.id), "duplicate rule {}", rule.id);
+        }
+    }
+}
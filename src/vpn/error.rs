@@ -0,0 +1,194 @@
+// VPN errors - what went wrong, where it came from and how to fix it
+
+use std::fmt;
+
+use super::diagnosis::diagnose_log;
+use super::process::ProcessError;
+
+/// A failed VPN operation. `source` is the raw message it was classified from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VpnError {
+    AuthFailed { source: String },
+    ConfigInvalid { source: String },
+    ServiceUnavailable { source: String },
+    Timeout { source: String },
+    SessionNotFound { source: String },
+    NetworkUnreachable { source: String },
+    CertificateExpired { source: String },
+    /// Nothing more specific could be recognised
+    Other { source: String },
+}
+
+impl VpnError {
+    /// Recognise a failure in openvpn3 output or log text. Connection failures are
+    /// recognised by the diagnosis rules; the patterns here cover openvpn3 itself.
+    pub fn classify(text: &str) -> Option<VpnError> {
+        let source = text.trim().to_string();
+        if let Some(error) = diagnose_log(text).and_then(|d| Self::from_rule(d.rule_id, source.clone())) {
+            return Some(error);
+        }
+
+        let lower = text.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+        if has(&["failed to parse", "option error", "configuration file", "could not import", "no such file or directory"]) {
+            Some(VpnError::ConfigInvalid { source })
+        } else if has(&["serviceunknown", "name has no owner", "could not connect to the d-bus", "is not installed"]) {
+            Some(VpnError::ServiceUnavailable { source })
+        } else if has(&["session not found", "no sessions available", "unknownobject"]) {
+            Some(VpnError::SessionNotFound { source })
+        } else if has(&["timed out", "timeout", "did not respond"]) {
+            Some(VpnError::Timeout { source })
+        } else {
+            None
+        }
+    }
+
+    /// The error a diagnosis rule amounts to (None for rules that only explain the log)
+    fn from_rule(rule_id: &str, source: String) -> Option<VpnError> {
+        match rule_id {
+            "crl_expired" | "cert_expired" => Some(VpnError::CertificateExpired { source }),
+            "auth_failed" => Some(VpnError::AuthFailed { source }),
+            "tls_handshake_timeout" | "remote_dns_failed" | "network_unreachable" => {
+                Some(VpnError::NetworkUnreachable { source })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            VpnError::AuthFailed { .. } => "Authentication failed",
+            VpnError::ConfigInvalid { .. } => "Invalid configuration",
+            VpnError::ServiceUnavailable { .. } => "OpenVPN3 service unavailable",
+            VpnError::Timeout { .. } => "Timed out",
+            VpnError::SessionNotFound { .. } => "Session not found",
+            VpnError::NetworkUnreachable { .. } => "Network unreachable",
+            VpnError::CertificateExpired { .. } => "Certificate expired",
+            VpnError::Other { .. } => "VPN error",
+        }
+    }
+
+    /// Suggested fix shown to the user
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            VpnError::AuthFailed { .. } => "Check your username, password or 2FA code and connect again.",
+            VpnError::ConfigInvalid { .. } => "Check that the .ovpn file exists and is a valid OpenVPN configuration.",
+            VpnError::ServiceUnavailable { .. } => {
+                "Make sure openvpn3 is installed and its D-Bus services are running (openvpn3 sessions-list)."
+            }
+            VpnError::Timeout { .. } => "The openvpn3 service did not answer in time. Try again, or restart it.",
+            VpnError::SessionNotFound { .. } => "The session is gone. Connect again to start a new one.",
+            VpnError::NetworkUnreachable { .. } => "Check your network connection and that the VPN server is reachable.",
+            VpnError::CertificateExpired { .. } => "Ask your VPN administrator for a renewed certificate or profile.",
+            VpnError::Other { .. } => "See the log below for details.",
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        match self {
+            VpnError::AuthFailed { source }
+            | VpnError::ConfigInvalid { source }
+            | VpnError::ServiceUnavailable { source }
+            | VpnError::Timeout { source }
+            | VpnError::SessionNotFound { source }
+            | VpnError::NetworkUnreachable { source }
+            | VpnError::CertificateExpired { source }
+            | VpnError::Other { source } => source,
+        }
+    }
+}
+
+impl fmt::Display for VpnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.source().is_empty() {
+            write!(f, "{}", self.title())
+        } else {
+            write!(f, "{}: {}", self.title(), self.source())
+        }
+    }
+}
+
+impl From<ProcessError> for VpnError {
+    fn from(error: ProcessError) -> Self {
+        let source = error.to_string();
+        match &error {
+            ProcessError::NotFound { .. } | ProcessError::SpawnFailed { .. } => {
+                VpnError::ServiceUnavailable { source }
+            }
+            ProcessError::TimedOut { .. } => VpnError::Timeout { source },
+            // openvpn3 explains itself on stderr; fall back to the exit summary
+            ProcessError::NonZeroExit { stderr, .. } => {
+                VpnError::classify(stderr).unwrap_or(VpnError::Other { source })
+            }
+            ProcessError::Unparseable { output, .. } => {
+                VpnError::classify(output).unwrap_or(VpnError::Other { source })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vpn::diagnosis::RULES;
+
+    fn kind(text: &str) -> Option<&'static str> {
+        VpnError::classify(text).map(|e| e.title())
+    }
+
+    #[test]
+    fn connection_failures_come_from_the_diagnosis_rules() {
+        assert_eq!(kind("[CLIENT:ERROR] AUTH_FAILED"), Some("Authentication failed"));
+        assert_eq!(kind("Invalid username or password"), Some("Authentication failed"));
+        assert_eq!(kind("VERIFY ERROR: certificate has expired"), Some("Certificate expired"));
+        assert_eq!(kind("CRL has expired"), Some("Certificate expired"));
+        assert_eq!(kind("RESOLVE_ERROR: vpn.example.com: Host not found"), Some("Network unreachable"));
+        assert_eq!(kind("TLS key negotiation failed (handshake_timeout)"), Some("Network unreachable"));
+        assert_eq!(kind("connect: Network is unreachable"), Some("Network unreachable"));
+    }
+
+    #[test]
+    fn every_rule_pattern_is_recognised() {
+        // Diagnosis-only rules (cipher, verification) fall through to None
+        for rule in RULES {
+            for pattern in rule.patterns {
+                let expected = VpnError::from_rule(rule.id, pattern.to_string()).map(|e| e.title());
+                assert_eq!(kind(pattern), expected, "pattern {:?} of {}", pattern, rule.id);
+            }
+        }
+    }
+
+    #[test]
+    fn expired_certificate_wins_over_auth_failure() {
+        let log = "AUTH_FAILED\nVERIFY ERROR: certificate has expired\n";
+        assert_eq!(kind(log), Some("Certificate expired"));
+    }
+
+    #[test]
+    fn openvpn3_failures() {
+        assert_eq!(kind("Failed to parse configuration"), Some("Invalid configuration"));
+        assert_eq!(kind("org.freedesktop.DBus.Error.ServiceUnknown"), Some("OpenVPN3 service unavailable"));
+        assert_eq!(kind("Session not found"), Some("Session not found"));
+        assert_eq!(kind("Backend did not respond"), Some("Timed out"));
+        assert_eq!(kind("Connected"), None);
+        assert_eq!(kind(""), None);
+    }
+
+    #[test]
+    fn process_errors() {
+        let missing = ProcessError::NotFound { program: "openvpn3".to_string() };
+        assert_eq!(VpnError::from(missing).title(), "OpenVPN3 service unavailable");
+        let failed = ProcessError::NonZeroExit {
+            program: "openvpn3".to_string(),
+            code: Some(1),
+            stderr: "AUTH_FAILED".to_string(),
+        };
+        assert!(matches!(VpnError::from(failed), VpnError::AuthFailed { source } if source == "AUTH_FAILED"));
+        let unknown = ProcessError::NonZeroExit {
+            program: "openvpn3".to_string(),
+            code: Some(1),
+            stderr: "something odd".to_string(),
+        };
+        assert!(matches!(VpnError::from(unknown), VpnError::Other { .. }));
+    }
+}
@@ -1,25 +1,222 @@
-use std::process::Command;
-
-/// Ping a known endpoint and return latency in ms (None if failed)
-pub async fn ping_latency() -> Option<u32> {
-    // Use system ping for simplicity (Linux only)
-    let output = Command::new("ping")
-        .args(["-c", "1", "-w", "1", "8.8.8.8"]) // 1 packet, 1s timeout
-        .output()
-        .ok()?;
-    if !output.status.success() {
-        return None;
-    }
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    for line in stdout.lines() {
-        if let Some(idx) = line.find("time=") {
-            let ms = &line[idx + 5..];
-            if let Some(end) = ms.find(' ') {
-                if let Ok(val) = ms[..end].parse::<f32>() {
-                    return Some(val.round() as u32);
-                }
+// Connection health - combines probes, traffic counters and DNS into one verdict
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::probes::ProbeStats;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthLevel {
+    Good,
+    Degraded,
+    Broken,
+}
+
+impl HealthLevel {
+    /// Colour used for the status dot and tray icon
+    pub fn color_rgb(&self) -> [u8; 3] {
+        match self {
+            HealthLevel::Good => [60, 220, 60],
+            HealthLevel::Degraded => [255, 193, 7],
+            HealthLevel::Broken => [239, 83, 80],
+        }
+    }
+}
+
+/// Health verdict with the reasons that led to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthReport {
+    pub level: HealthLevel,
+    pub reasons: Vec<String>,
+}
+
+impl HealthReport {
+    pub fn summary(&self) -> String {
+        if self.reasons.is_empty() {
+            format!("{:?}", self.level)
+        } else {
+            format!("{:?}: {}", self.level, self.reasons.join("; "))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthSettings {
+    pub degraded_latency_ms: f32,
+    pub broken_latency_ms: f32,
+    pub degraded_loss_pct: f32,
+    pub broken_loss_pct: f32,
+    /// Seconds without incoming bytes before the tunnel counts as stalled
+    pub stall_secs: u64,
+    /// Name resolved to check DNS through the tunnel (empty = skip)
+    pub dns_check_host: String,
+    /// Restart the session after this long in Broken (0 = never)
+    pub restart_after_broken_secs: u64,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            degraded_latency_ms: 250.0,
+            broken_latency_ms: 1500.0,
+            degraded_loss_pct: 10.0,
+            broken_loss_pct: 60.0,
+            stall_secs: 30,
+            dns_check_host: "example.com".to_string(),
+            restart_after_broken_secs: 0,
+        }
+    }
+}
+
+/// Measurements the verdict is based on
+#[derive(Debug, Clone, Default)]
+pub struct HealthInputs {
+    /// Stats of the most relevant probe target, with its name
+    pub probe: Option<(String, ProbeStats)>,
+    /// Time since the incoming byte counter last moved
+    pub stalled_for: Option<Duration>,
+    /// Latency probes are sending through the tunnel. Without them an idle tunnel
+    /// receives nothing, so a still counter says nothing about its health.
+    pub probe_traffic: bool,
+    /// Result of the last DNS check (None = not checked)
+    pub dns_ok: Option<bool>,
+}
+
+/// Combine the inputs into Good, Degraded or Broken
+pub fn assess(inputs: &HealthInputs, settings: &HealthSettings) -> HealthReport {
+    let mut level = HealthLevel::Good;
+    let mut reasons = Vec::new();
+    let mut raise = |to: HealthLevel, reason: String| {
+        level = level.max(to);
+        reasons.push(reason);
+    };
+
+    if let Some((name, stats)) = &inputs.probe {
+        if stats.samples > 0 && stats.loss_pct >= settings.broken_loss_pct {
+            raise(HealthLevel::Broken, format!("{:.0}% packet loss to {}", stats.loss_pct, name));
+        } else if stats.samples > 0 && stats.loss_pct >= settings.degraded_loss_pct {
+            raise(HealthLevel::Degraded, format!("{:.0}% packet loss to {}", stats.loss_pct, name));
+        }
+
+        if let Some(avg) = stats.avg_ms {
+            if avg >= settings.broken_latency_ms {
+                raise(HealthLevel::Broken, format!("{:.0} ms average latency to {}", avg, name));
+            } else if avg >= settings.degraded_latency_ms {
+                raise(HealthLevel::Degraded, format!("{:.0} ms average latency to {}", avg, name));
             }
         }
     }
-    None
+
+    if let Some(stalled) = inputs.stalled_for.filter(|_| inputs.probe_traffic) {
+        let limit = Duration::from_secs(settings.stall_secs.max(1));
+        if stalled >= limit * 2 {
+            raise(HealthLevel::Broken, format!("no incoming traffic for {}s", stalled.as_secs()));
+        } else if stalled >= limit {
+            raise(HealthLevel::Degraded, format!("no incoming traffic for {}s", stalled.as_secs()));
+        }
+    }
+
+    if inputs.dns_ok == Some(false) {
+        raise(HealthLevel::Degraded, format!("cannot resolve {}", settings.dns_check_host));
+    }
+
+    HealthReport { level, reasons }
+}
+
+/// Check that the configured name resolves (through the tunnel's DNS while connected)
+pub async fn check_dns(host: String) -> bool {
+    let lookup = tokio::net::lookup_host((host.as_str(), 0));
+    match tokio::time::timeout(Duration::from_secs(3), lookup).await {
+        Ok(Ok(mut addrs)) => addrs.next().is_some(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(avg_ms: f32, loss_pct: f32) -> Option<(String, ProbeStats)> {
+        Some((
+            "gateway".to_string(),
+            ProbeStats {
+                avg_ms: Some(avg_ms),
+                loss_pct,
+                samples: 10,
+                ..Default::default()
+            },
+        ))
+    }
+
+    fn level(inputs: HealthInputs) -> HealthLevel {
+        assess(&inputs, &HealthSettings::default()).level
+    }
+
+    #[test]
+    fn no_measurements_is_good() {
+        let report = assess(&HealthInputs::default(), &HealthSettings::default());
+        assert_eq!(report.level, HealthLevel::Good);
+        assert!(report.reasons.is_empty());
+    }
+
+    #[test]
+    fn latency_thresholds() {
+        assert_eq!(level(HealthInputs { probe: probe(40.0, 0.0), ..Default::default() }), HealthLevel::Good);
+        assert_eq!(level(HealthInputs { probe: probe(300.0, 0.0), ..Default::default() }), HealthLevel::Degraded);
+        assert_eq!(level(HealthInputs { probe: probe(2000.0, 0.0), ..Default::default() }), HealthLevel::Broken);
+    }
+
+    #[test]
+    fn loss_thresholds() {
+        assert_eq!(level(HealthInputs { probe: probe(40.0, 20.0), ..Default::default() }), HealthLevel::Degraded);
+        assert_eq!(level(HealthInputs { probe: probe(40.0, 80.0), ..Default::default() }), HealthLevel::Broken);
+    }
+
+    #[test]
+    fn loss_without_samples_is_ignored() {
+        let probe = Some(("gateway".to_string(), ProbeStats { loss_pct: 100.0, ..Default::default() }));
+        assert_eq!(level(HealthInputs { probe, ..Default::default() }), HealthLevel::Good);
+    }
+
+    #[test]
+    fn stall_with_probe_traffic() {
+        let stalled = |secs| HealthInputs {
+            stalled_for: Some(Duration::from_secs(secs)),
+            probe_traffic: true,
+            ..Default::default()
+        };
+        assert_eq!(level(stalled(10)), HealthLevel::Good);
+        assert_eq!(level(stalled(30)), HealthLevel::Degraded);
+        assert_eq!(level(stalled(60)), HealthLevel::Broken);
+    }
+
+    #[test]
+    fn idle_tunnel_without_probes_is_not_stalled() {
+        let inputs = HealthInputs {
+            stalled_for: Some(Duration::from_secs(3600)),
+            probe_traffic: false,
+            ..Default::default()
+        };
+        assert_eq!(level(inputs), HealthLevel::Good);
+    }
+
+    #[test]
+    fn failed_dns_degrades() {
+        let report = assess(&HealthInputs { dns_ok: Some(false), ..Default::default() }, &HealthSettings::default());
+        assert_eq!(report.level, HealthLevel::Degraded);
+        assert_eq!(report.reasons, vec!["cannot resolve example.com".to_string()]);
+    }
+
+    #[test]
+    fn worst_input_wins() {
+        let inputs = HealthInputs {
+            probe: probe(300.0, 80.0),
+            dns_ok: Some(false),
+            ..Default::default()
+        };
+        let report = assess(&inputs, &HealthSettings::default());
+        assert_eq!(report.level, HealthLevel::Broken);
+        assert_eq!(report.reasons.len(), 3);
+    }
 }
@@ -0,0 +1,28 @@
+// Privileged helper - runs openvpn3-gui-helper through pkexec
+
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// Privileged helper invoked through pkexec
+pub const HELPER_PATH: &str = "/usr/libexec/openvpn3-gui-helper";
+
+/// Run a helper subcommand and return its stdout. Everything the helper acts on is
+/// passed as arguments, which it validates before building any rules from them.
+pub async fn run_helper(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("pkexec")
+        .arg(HELPER_PATH)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute pkexec: {}", e))?
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("Helper failed: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
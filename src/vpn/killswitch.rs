@@ -0,0 +1,309 @@
+// Kill switch - nftables rules (built by the helper) that only let traffic out through the tunnel
+
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use tokio::process::Command;
+
+use super::helper::run_helper;
+use super::parser::{parse_nameservers, parse_remotes, parse_resolvectl_dns, Remote};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KillSwitchSettings {
+    pub enabled: bool,
+    /// Keep the local network reachable while the kill switch is active
+    pub allow_lan: bool,
+    pub lan_ranges: Vec<String>,
+}
+
+impl Default for KillSwitchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allow_lan: true,
+            lan_ranges: vec![
+                "10.0.0.0/8".to_string(),
+                "172.16.0.0/12".to_string(),
+                "192.168.0.0/16".to_string(),
+                "169.254.0.0/16".to_string(),
+                "fe80::/10".to_string(),
+                "fc00::/7".to_string(),
+            ],
+        }
+    }
+}
+
+/// A resolved VPN server address the tunnel itself needs to reach
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerEndpoint {
+    pub addr: IpAddr,
+    pub port: u16,
+    pub tcp: bool,
+}
+
+/// Everything the ruleset is generated from
+#[derive(Debug, Clone)]
+pub struct KillSwitchRules {
+    pub tunnel_device: String,
+    pub servers: Vec<ServerEndpoint>,
+    /// Name servers outside the tunnel, so host name remotes resolve while reconnecting
+    pub resolvers: Vec<IpAddr>,
+    /// LAN ranges to allow (empty = LAN blocked)
+    pub lan_ranges: Vec<String>,
+}
+
+impl KillSwitchRules {
+    pub fn new(tunnel_device: String, servers: Vec<ServerEndpoint>, settings: &KillSwitchSettings) -> Self {
+        Self {
+            tunnel_device,
+            servers,
+            resolvers: Vec::new(),
+            lan_ranges: if settings.allow_lan { settings.lan_ranges.clone() } else { Vec::new() },
+        }
+    }
+
+    /// Arguments for the helper's `killswitch-apply`. The helper validates each one
+    /// and builds the ruleset itself, so no nft text crosses the privilege boundary.
+    pub fn helper_args(&self) -> Vec<String> {
+        let mut args = vec![self.tunnel_device.clone()];
+        for server in &self.servers {
+            args.push("server".to_string());
+            args.push(server.addr.to_string());
+            args.push(if server.tcp { "tcp" } else { "udp" }.to_string());
+            args.push(server.port.to_string());
+        }
+        for resolver in &self.resolvers {
+            args.push("dns".to_string());
+            args.push(resolver.to_string());
+        }
+        for range in &self.lan_ranges {
+            args.push("lan".to_string());
+            args.push(range.clone());
+        }
+        args
+    }
+}
+
+/// Endpoint of a remote given as an address; host names need resolving
+fn literal_endpoint(remote: &Remote) -> Option<ServerEndpoint> {
+    remote.host.parse().ok().map(|addr| ServerEndpoint {
+        addr,
+        port: remote.port,
+        tcp: remote.tcp,
+    })
+}
+
+/// Name servers the uplink uses: those in /etc/resolv.conf and, behind systemd-resolved
+/// (which listens on loopback), the servers of every link except the tunnel
+async fn uplink_resolvers(tunnel_device: &str) -> Vec<IpAddr> {
+    let mut resolvers = tokio::fs::read_to_string("/etc/resolv.conf")
+        .await
+        .map(|conf| parse_nameservers(&conf))
+        .unwrap_or_default();
+    if let Ok(output) = Command::new("resolvectl").arg("dns").output().await {
+        resolvers.extend(parse_resolvectl_dns(&String::from_utf8_lossy(&output.stdout), tunnel_device));
+    }
+    let mut unique = Vec::new();
+    for resolver in resolvers {
+        if !resolver.is_loopback() && !unique.contains(&resolver) {
+            unique.push(resolver);
+        }
+    }
+    unique
+}
+
+/// Build the rules for a session: resolves the `remote` entries of the config. Host
+/// names are resolved again on every connect, and their resolvers stay reachable so a
+/// reconnect can look up a server whose address has changed.
+pub async fn build_rules(config_path: String, tunnel_device: String, settings: KillSwitchSettings) -> Result<KillSwitchRules, String> {
+    let config = tokio::fs::read_to_string(&config_path)
+        .await
+        .map_err(|e| format!("Failed to read config: {}", e))?;
+
+    let remotes = parse_remotes(&config);
+    let mut servers = Vec::new();
+    for remote in &remotes {
+        let endpoints = match literal_endpoint(remote) {
+            Some(endpoint) => vec![endpoint],
+            None => tokio::net::lookup_host((remote.host.as_str(), remote.port))
+                .await
+                .map_err(|e| format!("Failed to resolve VPN server {}: {}", remote.host, e))?
+                .map(|addr| ServerEndpoint { addr: addr.ip(), port: remote.port, tcp: remote.tcp })
+                .collect(),
+        };
+        for endpoint in endpoints {
+            if !servers.contains(&endpoint) {
+                servers.push(endpoint);
+            }
+        }
+    }
+    if servers.is_empty() {
+        return Err("No 'remote' entries found in config".to_string());
+    }
+
+    let resolvers = if remotes.iter().any(|r| literal_endpoint(r).is_none()) {
+        uplink_resolvers(&tunnel_device).await
+    } else {
+        Vec::new()
+    };
+    Ok(KillSwitchRules { resolvers, ..KillSwitchRules::new(tunnel_device, servers, &settings) })
+}
+
+/// Install (or atomically replace) the kill switch table
+pub async fn apply_kill_switch(rules: KillSwitchRules) -> Result<(), String> {
+    let mut args = vec!["killswitch-apply".to_string()];
+    args.extend(rules.helper_args());
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    run_helper(&args).await.map(|_| ())
+}
+
+/// Remove the kill switch table
+pub async fn remove_kill_switch() -> Result<(), String> {
+    run_helper(&["killswitch-remove"]).await.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn endpoint(addr: &str, port: u16, tcp: bool) -> ServerEndpoint {
+        ServerEndpoint { addr: addr.parse().unwrap(), port, tcp }
+    }
+
+    fn rules(settings: &KillSwitchSettings) -> KillSwitchRules {
+        KillSwitchRules::new(
+            "tun0".to_string(),
+            vec![endpoint("203.0.113.5", 1194, false), endpoint("2001:db8::1", 443, true)],
+            settings,
+        )
+    }
+
+    /// The ruleset the helper would load for these arguments
+    fn render(args: &[String]) -> Result<String, String> {
+        let helper = concat!(env!("CARGO_MANIFEST_DIR"), "/helper/openvpn3-gui-helper");
+        let output = Command::new("sh")
+            .arg(helper)
+            .arg("killswitch-render")
+            .args(args)
+            .output()
+            .expect("failed to run the helper");
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).to_string())
+        }
+    }
+
+    fn lines(ruleset: &str) -> Vec<&str> {
+        ruleset.lines().map(|l| l.trim()).collect()
+    }
+
+    #[test]
+    fn accepts_tunnel_loopback_servers_and_lan() {
+        let ruleset = render(&rules(&KillSwitchSettings::default()).helper_args()).unwrap();
+        let lines = lines(&ruleset);
+        for expected in [
+            "oifname \"lo\" accept",
+            "iifname \"lo\" accept",
+            "oifname \"tun0\" accept",
+            "iifname \"tun0\" accept",
+            "ip daddr 203.0.113.5 udp dport 1194 accept",
+            "ip6 daddr 2001:db8::1 tcp dport 443 accept",
+            "ip daddr { 10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16, 169.254.0.0/16 } accept",
+            "ip saddr { 10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16, 169.254.0.0/16 } accept",
+            "ip6 daddr { fe80::/10, fc00::/7 } accept",
+            "ip6 saddr { fe80::/10, fc00::/7 } accept",
+        ] {
+            assert!(lines.contains(&expected), "missing `{}` in\n{}", expected, ruleset);
+        }
+        // Everything else is dropped
+        assert_eq!(lines.iter().filter(|l| l.ends_with("policy drop;")).count(), 2);
+    }
+
+    #[test]
+    fn only_touches_its_own_table() {
+        let ruleset = render(&rules(&KillSwitchSettings::default()).helper_args()).unwrap();
+        let tables: Vec<&str> = lines(&ruleset).into_iter().filter(|l| l.contains("table")).collect();
+        assert_eq!(
+            tables,
+            [
+                "table inet openvpn3_gui_killswitch",
+                "flush table inet openvpn3_gui_killswitch",
+                "table inet openvpn3_gui_killswitch {",
+            ]
+        );
+    }
+
+    #[test]
+    fn partitions_v4_and_v6() {
+        let settings = KillSwitchSettings {
+            lan_ranges: vec!["192.168.1.0/24".to_string(), "fd00::/8".to_string()],
+            ..Default::default()
+        };
+        let ruleset = render(&rules(&settings).helper_args()).unwrap();
+        let lines = lines(&ruleset);
+        assert!(lines.contains(&"ip daddr { 192.168.1.0/24 } accept"));
+        assert!(lines.contains(&"ip6 daddr { fd00::/8 } accept"));
+        assert!(!lines.iter().any(|l| l.starts_with("ip daddr 2001:db8::1")));
+        assert!(!lines.iter().any(|l| l.starts_with("ip6 daddr 203.0.113.5")));
+    }
+
+    #[test]
+    fn lan_blocked_without_allow_lan() {
+        let settings = KillSwitchSettings { allow_lan: false, ..Default::default() };
+        let rules = rules(&settings);
+        assert!(!rules.helper_args().contains(&"lan".to_string()));
+
+        let ruleset = render(&rules.helper_args()).unwrap();
+        assert!(!ruleset.contains("saddr"));
+        assert!(!ruleset.contains("daddr {"));
+        // Without IPv6 LAN ranges there is no neighbour discovery either
+        assert!(!ruleset.contains("icmpv6"));
+    }
+
+    #[test]
+    fn resolvers_are_reachable_on_port_53_only() {
+        let mut rules = rules(&KillSwitchSettings { allow_lan: false, ..Default::default() });
+        rules.resolvers = vec!["192.0.2.53".parse().unwrap(), "2001:db8::53".parse().unwrap()];
+        let ruleset = render(&rules.helper_args()).unwrap();
+        let lines = lines(&ruleset);
+        for expected in [
+            "ip daddr 192.0.2.53 udp dport 53 accept",
+            "ip daddr 192.0.2.53 tcp dport 53 accept",
+            "ip6 daddr 2001:db8::53 udp dport 53 accept",
+            "ip6 daddr 2001:db8::53 tcp dport 53 accept",
+        ] {
+            assert!(lines.contains(&expected), "missing `{}` in\n{}", expected, ruleset);
+        }
+        assert_eq!(lines.iter().filter(|l| l.contains("192.0.2.53")).count(), 2);
+    }
+
+    #[test]
+    fn ip_remotes_need_no_resolving() {
+        let config = "remote 198.51.100.7 443 tcp\nremote 2001:db8::2\nremote vpn.example.com 1194 udp\n";
+        let remotes = parse_remotes(config);
+        assert_eq!(literal_endpoint(&remotes[0]), Some(endpoint("198.51.100.7", 443, true)));
+        assert_eq!(literal_endpoint(&remotes[1]), Some(endpoint("2001:db8::2", 1194, false)));
+        assert_eq!(literal_endpoint(&remotes[2]), None);
+    }
+
+    #[test]
+    fn helper_rejects_injected_arguments() {
+        let injected = [
+            vec!["tun0\"; flush ruleset".to_string(), "server".into(), "203.0.113.5".into(), "udp".into(), "1194".into()],
+            vec!["tun0".into(), "server".into(), "203.0.113.5\n}; flush ruleset".into(), "udp".into(), "1194".into()],
+            vec!["tun0".into(), "server".into(), "203.0.113.5".into(), "udp; flush ruleset".into(), "1194".into()],
+            vec!["tun0".into(), "server".into(), "203.0.113.5".into(), "udp".into(), "70000".into()],
+            vec!["tun0".into(), "server".into(), "203.0.113.5".into(), "udp".into(), "1194".into(), "lan".into(), "10.0.0.0/8 }".into()],
+            vec!["tun0".into(), "server".into(), "203.0.113.5".into(), "udp".into(), "1194".into(), "dns".into(), "192.0.2.0/24".into()],
+            vec!["tun0".into(), "server".into(), "203.0.113.5".into(), "udp".into(), "1194".into(), "dns".into(), "1.1.1.1 accept".into()],
+            vec!["tun0".into(), "flush".into(), "ruleset".into()],
+            // No server: the tunnel could never come back up
+            vec!["tun0".into()],
+        ];
+        for args in injected {
+            assert!(render(&args).is_err(), "accepted {:?}", args);
+        }
+    }
+}
@@ -1,10 +1,17 @@
 // VPN Manager - handles all OpenVPN3 operations
 
 use std::path::PathBuf;
-use std::process::Stdio;
-use tokio::process::Command;
+use std::time::{Duration, Instant};
 
-use super::parser::{extract_session_path, parse_stats, extract_ip};
+use super::parser::{extract_session_path, parse_stats, extract_ip, extract_gateway, session_block};
+use super::error::VpnError;
+use super::process::{reap, run, run_with_input, spawn, ProcessError};
+use super::rates::CounterSample;
+
+/// Deadline for openvpn3 commands that only talk to the D-Bus service
+const OPENVPN3_TIMEOUT: Duration = Duration::from_secs(15);
+/// Deadline for local `ip` queries
+const IP_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// File picker for .ovpn config files
 pub async fn pick_file() -> Option<PathBuf> {
@@ -16,164 +23,132 @@ pub async fn pick_file() -> Option<PathBuf> {
 }
 
 /// Start a VPN session
-pub async fn start_vpn(config_path: String) -> Result<(String, String), String> {
-    // OpenVPN3 uses D-Bus and doesn't need elevated privileges
-    let _child = Command::new("openvpn3")
-        .args(&["session-start", "--config", &config_path])
-        .spawn()
-        .map_err(|e| format!("Failed to execute: {}", e))?;
+pub async fn start_vpn(config_path: String) -> Result<(String, String), VpnError> {
+    // OpenVPN3 uses D-Bus and doesn't need elevated privileges.
+    // session-start keeps running until the session connects (or waits for auth);
+    // if this task is aborted it is killed along with the future.
+    let mut child = spawn("openvpn3", &["session-start", "--config", &config_path], false)?;
 
     // Wait a moment for the session to initialize
-    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-    
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    // Failed straight away (bad config, service down): report why
+    if let Ok(Some(status)) = child.try_wait() {
+        if !status.success() {
+            let output = child.wait_with_output().await.ok();
+            let stderr = output
+                .map(|o| String::from_utf8_lossy(&o.stderr).to_string())
+                .unwrap_or_default();
+            return Err(ProcessError::NonZeroExit {
+                program: "openvpn3".to_string(),
+                code: status.code(),
+                stderr,
+            }
+            .into());
+        }
+    }
+
     // Try to find the session by listing all sessions
-    let list_output = Command::new("openvpn3")
-        .args(&["sessions-list"])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to list sessions: {}", e))?;
-    
-    let stdout = String::from_utf8_lossy(&list_output.stdout).to_string();
-    
-    // Extract session path from the list
-    let session_path = extract_session_path(&stdout)
-        .unwrap_or_else(|| format!("/net/openvpn/v3/sessions/{}", uuid::Uuid::new_v4()));
-    
-    Ok((stdout, session_path))
+    let list = run("openvpn3", &["sessions-list"], OPENVPN3_TIMEOUT).await?;
+    let session_path = list
+        .parse("openvpn3", extract_session_path)
+        .map_err(|_| VpnError::SessionNotFound {
+            source: "The new session does not appear in openvpn3 sessions-list".to_string(),
+        })?;
+
+    // Leave session-start running, but make sure it is reaped when it exits
+    reap(child);
+    let status = session_block(&list.stdout, &session_path).unwrap_or_default();
+    Ok((status, session_path))
 }
 
 /// Stop VPN by session path
-pub async fn stop_vpn_by_path(session_path: String) -> Result<String, String> {
-    let output = Command::new("openvpn3")
-        .args(&["session-manage", "--session-path", &session_path, "--disconnect"])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to execute: {}", e))?;
+pub async fn stop_vpn_by_path(session_path: String) -> Result<String, VpnError> {
+    run(
+        "openvpn3",
+        &["session-manage", "--session-path", &session_path, "--disconnect"],
+        OPENVPN3_TIMEOUT,
+    )
+    .await?;
+    Ok("VPN Disconnected.".to_string())
+}
 
-    if output.status.success() {
-        Ok("VPN Disconnected.".to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
+/// Pause, resume or restart a session (`action` is a session-manage flag: pause, resume, restart)
+pub async fn manage_session(session_path: String, action: &'static str) -> Result<String, VpnError> {
+    run(
+        "openvpn3",
+        &["session-manage", "--session-path", &session_path, &format!("--{}", action)],
+        OPENVPN3_TIMEOUT,
+    )
+    .await?;
+    Ok(format!("Session {} requested.", action))
 }
 
 /// Stop VPN by config path (fallback)
-pub async fn stop_vpn_by_config(config_path: String) -> Result<String, String> {
-    let output = Command::new("openvpn3")
-        .args(&["session-manage", "--config", &config_path, "--disconnect"])
-        .output()
-        .await
-        .map_err(|e| format!("Failed to execute: {}", e))?;
-
-    if output.status.success() {
-        Ok("VPN Disconnected.".to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
+pub async fn stop_vpn_by_config(config_path: String) -> Result<String, VpnError> {
+    run(
+        "openvpn3",
+        &["session-manage", "--config", &config_path, "--disconnect"],
+        OPENVPN3_TIMEOUT,
+    )
+    .await?;
+    Ok("VPN Disconnected.".to_string())
 }
 
-/// Check session status (for monitoring during connection)
-pub async fn check_session_status(_session_path: String) -> Option<String> {
-    // Get sessions list to check status
-    let output = Command::new("openvpn3")
-        .args(&["sessions-list"])
-        .output()
-        .await
-        .ok()?;
-    
-    if output.status.success() {
-        Some(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        None
-    }
+/// Check session status (for monitoring during connection). Only the session's own
+/// entry is returned, so other sessions' states never count for this one.
+pub async fn check_session_status(session_path: String) -> Option<String> {
+    let list = run("openvpn3", &["sessions-list"], OPENVPN3_TIMEOUT).await.ok()?;
+    session_block(&list.stdout, &session_path)
 }
 
 /// Fetch session statistics (bytes in/out)
-pub async fn fetch_session_stats(session_path: String) -> Option<(u64, u64)> {
-    let output = Command::new("openvpn3")
-        .args(&["session-stats", "--session-path", &session_path])
-        .output()
+pub async fn fetch_session_stats(session_path: String) -> Option<CounterSample> {
+    // Timed when the counters were asked for: replies can arrive out of order, and
+    // only the request time tells which reading is the older one
+    let at = Instant::now();
+    let output = run("openvpn3", &["session-stats", "--session-path", &session_path], OPENVPN3_TIMEOUT)
         .await
         .ok()?;
-
-    if !output.status.success() {
-        return None;
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    parse_stats(&stdout)
+    let (bytes_in, bytes_out) = output.parse("openvpn3", parse_stats).ok()?;
+    Some(CounterSample { at, bytes_in, bytes_out })
 }
 
-/// Find tunnel IP address (tun0)
-pub async fn find_tunnel_ip() -> Option<String> {
-    let output = Command::new("ip")
-        .args(&["addr", "show", "tun0"])
-        .output()
+/// Find tunnel IP address of the given device (e.g. tun0)
+pub async fn find_tunnel_ip(device: String) -> Option<String> {
+    run("ip", &["addr", "show", &device], IP_TIMEOUT)
         .await
-        .ok()?;
-
-    if !output.status.success() {
-        return None;
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    extract_ip(&stdout)
+        .ok()?
+        .parse("ip", extract_ip)
+        .ok()
 }
 
-/// Fetch public IP from external services
-pub async fn fetch_public_ip() -> Option<String> {
-    // Try to fetch public IP from a service
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(5))
-        .build()
-        .ok()?;
-    
-    // Try multiple services in case one is down
-    let services = [
-        "https://api.ipify.org",
-        "https://ifconfig.me/ip",
-        "https://icanhazip.com",
-    ];
-    
-    for service in &services {
-        if let Ok(response) = client.get(*service).send().await {
-            if let Ok(ip) = response.text().await {
-                let ip = ip.trim().to_string();
-                if !ip.is_empty() {
-                    return Some(ip);
-                }
-            }
-        }
-    }
-    
-    None
+/// Find the VPN gateway reachable through the given tunnel device
+pub async fn find_vpn_gateway(device: String) -> Option<String> {
+    run("ip", &["route", "show", "dev", &device], IP_TIMEOUT)
+        .await
+        .ok()?
+        .parse("ip", extract_gateway)
+        .ok()
 }
 
 /// Submit 2FA/challenge response
-pub async fn submit_challenge(session_path: String, _code: String) -> Result<String, String> {
-    let output = Command::new("openvpn3")
-        .args(&["session-auth", "--session-path", &session_path])
-        .stdin(Stdio::piped())
-        .output()
-        .await
-        .map_err(|e| format!("Failed to submit: {}", e))?;
-
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
-    }
+pub async fn submit_challenge(session_path: String, code: String) -> Result<String, VpnError> {
+    let output = run_with_input(
+        "openvpn3",
+        &["session-auth", "--session-path", &session_path],
+        Some(format!("{}\n", code)),
+        OPENVPN3_TIMEOUT,
+    )
+    .await?;
+    Ok(output.stdout)
 }
 
 /// List all OpenVPN3 sessions (raw output)
 pub async fn list_sessions() -> String {
-    let output = Command::new("openvpn3")
-        .args(&["sessions-list"])
-        .output()
-        .await;
-    match output {
-        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).to_string(),
-        Ok(out) => String::from_utf8_lossy(&out.stderr).to_string(),
+    match run("openvpn3", &["sessions-list"], OPENVPN3_TIMEOUT).await {
+        Ok(out) => out.stdout,
+        Err(e @ ProcessError::NonZeroExit { .. }) => e.stderr().to_string(),
         Err(e) => format!("Failed to list sessions: {}", e),
     }
 }
@@ -1,8 +1,23 @@
 // VPN module
 
 pub mod manager;
+pub mod captive_portal;
 pub mod parser;
+pub mod error;
+pub mod diagnosis;
+pub mod process;
+pub mod probes;
 pub mod health;
+pub mod helper;
+pub mod killswitch;
+pub mod netns;
+pub mod netwatch;
+pub mod split_tunnel;
+pub mod trust;
+pub mod public_ip;
+pub mod session_log;
+pub mod snapshot;
+pub mod rates;
 
 // Re-export commonly used functions
 pub use manager::*;
@@ -0,0 +1,51 @@
+// VPN network namespace - isolate programs so their only route is the tunnel
+
+use super::helper::run_helper;
+
+/// Name of the namespace created for a session (`ip netns` name). The helper only
+/// ever acts on this one, so it is fixed there too.
+pub const NAMESPACE: &str = "openvpn3gui";
+
+/// Session environment forwarded to programs started in the namespace
+const FORWARDED_ENV: &[&str] = &[
+    "HOME",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "XDG_RUNTIME_DIR",
+    "DBUS_SESSION_BUS_ADDRESS",
+    "XAUTHORITY",
+    "LANG",
+];
+
+/// Create the namespace and move the tunnel device into it.
+/// Returns the tunnel addresses that were re-applied inside the namespace.
+pub async fn setup_namespace(tunnel_device: String) -> Result<String, String> {
+    let output = run_helper(&["netns-setup", &tunnel_device]).await?;
+    Ok(output.trim().to_string())
+}
+
+/// Kill everything left in the namespace and delete it
+pub async fn teardown_namespace() -> Result<(), String> {
+    run_helper(&["netns-teardown"]).await.map(|_| ())
+}
+
+/// Run a shell command as the current user inside the namespace and wait for it
+pub async fn run_in_namespace(command: String) -> Result<String, String> {
+    let mut args = vec![
+        "netns-exec".to_string(),
+        "--".to_string(),
+        "env".to_string(),
+    ];
+    for key in FORWARDED_ENV {
+        if let Ok(value) = std::env::var(key) {
+            args.push(format!("{}={}", key, value));
+        }
+    }
+    args.extend(["sh".to_string(), "-c".to_string(), command.clone()]);
+
+    let args = args.iter().map(|a| a.as_str()).collect::<Vec<_>>();
+    match run_helper(&args).await {
+        Ok(_) => Ok(format!("'{}' (VPN namespace) exited", command)),
+        Err(e) => Err(format!("'{}' (VPN namespace) failed: {}", command, e)),
+    }
+}
@@ -0,0 +1,354 @@
+// Network and power events - netlink changes (ip monitor) and logind sleep signals
+
+use cosmic::iced::futures::{SinkExt, Stream};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkEvent {
+    /// Default route or addresses of a physical uplink changed
+    UplinkChanged(String),
+    /// logind is about to suspend
+    Sleep,
+    /// The system resumed
+    Wake,
+}
+
+/// What to do with the session before the system sleeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SleepAction {
+    Nothing,
+    Pause,
+    Disconnect,
+}
+
+/// What to do with a paused or disconnected session after wake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WakeAction {
+    Nothing,
+    Resume,
+    Restart,
+}
+
+/// Per-profile reactions to network and power events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkTriggers {
+    pub on_sleep: SleepAction,
+    pub on_wake: WakeAction,
+    pub restart_on_uplink_change: bool,
+}
+
+impl SleepAction {
+    pub const ALL: [SleepAction; 3] = [SleepAction::Nothing, SleepAction::Pause, SleepAction::Disconnect];
+}
+
+impl std::fmt::Display for SleepAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SleepAction::Nothing => "Do nothing",
+            SleepAction::Pause => "Pause",
+            SleepAction::Disconnect => "Disconnect",
+        })
+    }
+}
+
+impl WakeAction {
+    pub const ALL: [WakeAction; 3] = [WakeAction::Nothing, WakeAction::Resume, WakeAction::Restart];
+}
+
+impl std::fmt::Display for WakeAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            WakeAction::Nothing => "Do nothing",
+            WakeAction::Resume => "Resume",
+            WakeAction::Restart => "Restart",
+        })
+    }
+}
+
+impl Default for NetworkTriggers {
+    fn default() -> Self {
+        Self {
+            on_sleep: SleepAction::Pause,
+            on_wake: WakeAction::Resume,
+            restart_on_uplink_change: true,
+        }
+    }
+}
+
+/// Interfaces that come and go with VPNs, containers and VMs rather than the uplink
+const IGNORED_DEVICE_PREFIXES: &[&str] = &["lo", "tun", "tap", "wg", "veth", "docker", "br-", "virbr"];
+
+fn is_ignored_device(dev: &str) -> bool {
+    IGNORED_DEVICE_PREFIXES.iter().any(|p| dev.starts_with(p))
+}
+
+/// Classify one line of `ip monitor label link address route` output
+pub fn parse_monitor_line(line: &str) -> Option<NetworkEvent> {
+    // Continuation lines (valid_lft ...) are indented
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    // "[ROUTE]default via ..." (older iproute2 puts a space after the label)
+    let (label, rest) = line.split_once(']')?;
+    let rest = rest.trim();
+    let deleted = rest.starts_with("Deleted ");
+    let rest = rest.trim_start_matches("Deleted ");
+
+    match label {
+        "[ROUTE" => {
+            // Only default routes matter; VPN and host routes are ours
+            if !rest.starts_with("default") {
+                return None;
+            }
+            let dev = rest
+                .split_whitespace()
+                .skip_while(|w| *w != "dev")
+                .nth(1)?;
+            if is_ignored_device(dev) {
+                return None;
+            }
+            let what = if deleted { "removed" } else { "changed" };
+            Some(NetworkEvent::UplinkChanged(format!("default route via {} {}", dev, what)))
+        }
+        "[ADDR" => {
+            // "2: wlp2s0    inet 192.168.1.23/24 ..."
+            let mut words = rest.split_whitespace();
+            let dev = words.nth(1)?;
+            let family = words.next()?;
+            let addr = words.next()?;
+            if is_ignored_device(dev) || (family == "inet6" && addr.starts_with("fe80")) {
+                return None;
+            }
+            let what = if deleted { "removed from" } else { "added to" };
+            Some(NetworkEvent::UplinkChanged(format!("address {} {} {}", addr, what, dev)))
+        }
+        "[LINK" => {
+            // "3: wlp2s0: <NO-CARRIER,...> ... state DOWN ..."
+            let dev = rest.split_whitespace().nth(1)?.trim_end_matches(':');
+            let dev = dev.split('@').next()?;
+            if is_ignored_device(dev) || !(deleted || rest.contains("state DOWN")) {
+                return None;
+            }
+            Some(NetworkEvent::UplinkChanged(format!("link {} went down", dev)))
+        }
+        _ => None,
+    }
+}
+
+/// Classify one line of `gdbus monitor` output for logind
+pub fn parse_logind_line(line: &str) -> Option<NetworkEvent> {
+    if !line.contains("PrepareForSleep") {
+        return None;
+    }
+    if line.contains("(true") {
+        Some(NetworkEvent::Sleep)
+    } else if line.contains("(false") {
+        Some(NetworkEvent::Wake)
+    } else {
+        None
+    }
+}
+
+/// Stream of uplink changes from netlink
+pub fn netlink_events() -> impl Stream<Item = NetworkEvent> {
+    cosmic::iced::stream::channel(32, |mut output| async move {
+        loop {
+            let child = Command::new("ip")
+                .args(["monitor", "label", "link", "address", "route"])
+                .stdout(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn();
+            if let Ok(mut child) = child {
+                if let Some(stdout) = child.stdout.take() {
+                    let mut lines = BufReader::new(stdout).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if let Some(event) = parse_monitor_line(&line) {
+                            let _ = output.send(event).await;
+                        }
+                    }
+                }
+            }
+            // Monitor exited or could not start; try again later
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
+    })
+}
+
+/// Hold a delay inhibitor so there is time to act on PrepareForSleep
+fn take_sleep_inhibitor() -> Option<Child> {
+    Command::new("systemd-inhibit")
+        .args([
+            "--what=sleep",
+            "--mode=delay",
+            "--who=OpenVPN3 GUI",
+            "--why=Pause or disconnect the VPN session",
+            "sleep",
+            "infinity",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .ok()
+}
+
+/// Stream of suspend/resume events from logind
+pub fn sleep_events() -> impl Stream<Item = NetworkEvent> {
+    cosmic::iced::stream::channel(8, |mut output| async move {
+        loop {
+            let mut inhibitor = take_sleep_inhibitor();
+            let child = Command::new("gdbus")
+                .args([
+                    "monitor",
+                    "--system",
+                    "--dest",
+                    "org.freedesktop.login1",
+                    "--object-path",
+                    "/org/freedesktop/login1",
+                ])
+                .stdout(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn();
+            if let Ok(mut child) = child {
+                if let Some(stdout) = child.stdout.take() {
+                    let mut lines = BufReader::new(stdout).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        match parse_logind_line(&line) {
+                            Some(NetworkEvent::Sleep) => {
+                                let _ = output.send(NetworkEvent::Sleep).await;
+                                // Give the app a moment to pause/disconnect, then let the system sleep
+                                tokio::time::sleep(Duration::from_secs(2)).await;
+                                inhibitor = None;
+                            }
+                            Some(NetworkEvent::Wake) => {
+                                let _ = output.send(NetworkEvent::Wake).await;
+                                inhibitor = take_sleep_inhibitor();
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            drop(inhibitor);
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uplink(what: &str) -> Option<NetworkEvent> {
+        Some(NetworkEvent::UplinkChanged(what.to_string()))
+    }
+
+    #[test]
+    fn default_route_changes() {
+        assert_eq!(
+            parse_monitor_line("[ROUTE]default via 192.168.1.1 dev wlp2s0 proto dhcp src 192.168.1.23 metric 600 "),
+            uplink("default route via wlp2s0 changed")
+        );
+        assert_eq!(
+            parse_monitor_line("[ROUTE]Deleted default via 192.168.1.1 dev wlp2s0 proto dhcp src 192.168.1.23 metric 600 "),
+            uplink("default route via wlp2s0 removed")
+        );
+        // Older iproute2 puts a space after the label
+        assert_eq!(
+            parse_monitor_line("[ROUTE] default via 10.0.0.1 dev enp0s31f6 proto static metric 100"),
+            uplink("default route via enp0s31f6 changed")
+        );
+    }
+
+    #[test]
+    fn tunnel_and_host_routes_are_ignored() {
+        for line in [
+            "[ROUTE]default dev tun0 scope link ",
+            "[ROUTE]0.0.0.0/1 via 10.8.0.1 dev tun0 ",
+            "[ROUTE]10.8.0.0/24 dev tun0 proto kernel scope link src 10.8.0.2 ",
+            "[ROUTE]local 10.8.0.2 dev tun0 table local proto kernel scope host src 10.8.0.2 ",
+            "[ROUTE]default via 172.17.0.1 dev docker0 ",
+        ] {
+            assert_eq!(parse_monitor_line(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn address_changes() {
+        assert_eq!(
+            parse_monitor_line("[ADDR]2: wlp2s0    inet 192.168.1.23/24 brd 192.168.1.255 scope global dynamic noprefixroute wlp2s0"),
+            uplink("address 192.168.1.23/24 added to wlp2s0")
+        );
+        assert_eq!(
+            parse_monitor_line("[ADDR]Deleted 2: wlp2s0    inet 192.168.1.23/24 brd 192.168.1.255 scope global dynamic noprefixroute wlp2s0"),
+            uplink("address 192.168.1.23/24 removed from wlp2s0")
+        );
+        assert_eq!(
+            parse_monitor_line("[ADDR]2: wlp2s0    inet6 2001:db8::23/64 scope global dynamic mngtmpaddr noprefixroute "),
+            uplink("address 2001:db8::23/64 added to wlp2s0")
+        );
+        // Link-local addresses, tunnel addresses and continuation lines
+        for line in [
+            "[ADDR]2: wlp2s0    inet6 fe80::8e16:45ff:fe12:3456/64 scope link noprefixroute ",
+            "[ADDR]7: tun0    inet 10.8.0.2/24 scope global tun0",
+            "       valid_lft 86390sec preferred_lft 86390sec",
+        ] {
+            assert_eq!(parse_monitor_line(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn links_going_down() {
+        assert_eq!(
+            parse_monitor_line("[LINK]3: wlp2s0: <NO-CARRIER,BROADCAST,MULTICAST,UP> mtu 1500 qdisc noqueue state DOWN group default "),
+            uplink("link wlp2s0 went down")
+        );
+        assert_eq!(
+            parse_monitor_line("[LINK]Deleted 6: enx00e04c680001: <BROADCAST,MULTICAST> mtu 1500 qdisc noop state DOWN group default "),
+            uplink("link enx00e04c680001 went down")
+        );
+        for line in [
+            "[LINK]3: wlp2s0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue state UP group default ",
+            "[LINK]Deleted 9: veth3f2a1b4@if8: <BROADCAST,MULTICAST> mtu 1500 qdisc noop state DOWN ",
+            "[LINK]7: tun0: <POINTOPOINT,MULTICAST,NOARP> mtu 1500 qdisc fq_codel state DOWN ",
+            "    link/ether 8c:16:45:12:34:56 brd ff:ff:ff:ff:ff:ff",
+        ] {
+            assert_eq!(parse_monitor_line(line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn unlabelled_lines_are_ignored() {
+        assert_eq!(parse_monitor_line("default via 192.168.1.1 dev wlp2s0"), None);
+        assert_eq!(parse_monitor_line("[NEIGH]192.168.1.1 dev wlp2s0 lladdr 00:11:22:33:44:55 REACHABLE"), None);
+        assert_eq!(parse_monitor_line(""), None);
+    }
+
+    #[test]
+    fn logind_sleep_and_wake() {
+        assert_eq!(
+            parse_logind_line("/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (true,)"),
+            Some(NetworkEvent::Sleep)
+        );
+        assert_eq!(
+            parse_logind_line("/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForSleep (false,)"),
+            Some(NetworkEvent::Wake)
+        );
+        for line in [
+            "Monitoring signals on object /org/freedesktop/login1 owned by org.freedesktop.login1",
+            "The name org.freedesktop.login1 is owned by :1.4",
+            "/org/freedesktop/login1: org.freedesktop.DBus.Properties.PropertiesChanged ('org.freedesktop.login1.Manager', {'PreparingForSleep': <true>}, @as [])",
+            "/org/freedesktop/login1: org.freedesktop.login1.Manager.PrepareForShutdown (true,)",
+            "/org/freedesktop/login1: org.freedesktop.login1.Manager.SessionNew ('3', objectpath '/org/freedesktop/login1/session/_33')",
+        ] {
+            assert_eq!(parse_logind_line(line), None, "{}", line);
+        }
+    }
+}
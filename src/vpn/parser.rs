@@ -1,6 +1,7 @@
 // VPN parsing helpers
 
 use regex::Regex;
+use std::net::{IpAddr, Ipv4Addr};
 
 /// Extract session path from openvpn3 sessions-list output
 pub fn extract_session_path(output: &str) -> Option<String> {
@@ -27,6 +28,24 @@ pub fn extract_session_path(output: &str) -> Option<String> {
     None
 }
 
+/// The `sessions-list` entry of one session (entries are separated by lines of dashes)
+pub fn session_block(output: &str, session_path: &str) -> Option<String> {
+    let is_separator = |line: &str| !line.trim().is_empty() && line.trim().chars().all(|c| c == '-');
+    let is_ours = |line: &&str| line.trim().strip_prefix("Path:").is_some_and(|p| p.trim() == session_path);
+    let mut block = Vec::new();
+    // A final separator ends the last entry
+    for line in output.lines().chain(["-"]) {
+        if !is_separator(line) {
+            block.push(line);
+        } else if block.iter().any(is_ours) {
+            return Some(block.join("\n"));
+        } else {
+            block.clear();
+        }
+    }
+    None
+}
+
 /// Parse BYTES_IN and BYTES_OUT from openvpn3 session-stats output
 pub fn parse_stats(output: &str) -> Option<(u64, u64)> {
     // Try multiple patterns for BYTES_IN and BYTES_OUT
@@ -84,8 +103,210 @@ pub fn parse_stats(output: &str) -> Option<(u64, u64)> {
     }
 }
 
+/// Every `NAME....value` counter in `openvpn3 session-stats` output, in order
+pub fn parse_counters(output: &str) -> Vec<(String, u64)> {
+    let Ok(re) = Regex::new(r"(?m)^\s*([A-Z][A-Z0-9_]*)[.\s:]+(\d+)\s*$") else {
+        return Vec::new();
+    };
+    re.captures_iter(output)
+        .filter_map(|cap| Some((cap[1].to_string(), cap[2].parse().ok()?)))
+        .collect()
+}
+
 /// Extract IP address from `ip addr show` output
 pub fn extract_ip(output: &str) -> Option<String> {
     let re = Regex::new(r"inet\s+(\d+\.\d+\.\d+\.\d+)").ok()?;
     re.captures(output)?.get(1).map(|m| m.as_str().to_string())
 }
+
+/// Extract the VPN gateway from `ip route show dev <tun>` output
+pub fn extract_gateway(output: &str) -> Option<String> {
+    // Routes pushed by the server usually name the gateway explicitly
+    let via = Regex::new(r"via\s+(\d+\.\d+\.\d+\.\d+)").ok()?;
+    if let Some(caps) = via.captures(output) {
+        return caps.get(1).map(|m| m.as_str().to_string());
+    }
+
+    // Subnet topology without pushed routes: the server holds the first address
+    let subnet = Regex::new(r"(?m)^(\d+\.\d+\.\d+\.\d+)/(\d+)\s.*proto kernel").ok()?;
+    let caps = subnet.captures(output)?;
+    let network: Ipv4Addr = caps.get(1)?.as_str().parse().ok()?;
+    let prefix: u32 = caps.get(2)?.as_str().parse().ok()?;
+    if prefix == 0 || prefix >= 31 {
+        return None;
+    }
+    let mask = u32::MAX << (32 - prefix);
+    let first_host = (u32::from(network) & mask) + 1;
+    Some(Ipv4Addr::from(first_host).to_string())
+}
+
+/// Extract the tunnel device (e.g. tun0) from openvpn3 sessions-list output
+pub fn extract_tunnel_device(output: &str) -> Option<String> {
+    let re = Regex::new(r"Device:\s*([A-Za-z0-9_.-]+)").ok()?;
+    re.captures(output)?.get(1).map(|m| m.as_str().to_string())
+}
+
+/// A `remote` entry from an .ovpn config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    pub host: String,
+    pub port: u16,
+    pub tcp: bool,
+}
+
+/// Parse the `remote`, `port` and `proto` directives of an .ovpn config
+pub fn parse_remotes(config: &str) -> Vec<Remote> {
+    let mut default_port = 1194;
+    let mut default_tcp = false;
+    // (host, port, proto) as written; defaults are applied once the whole file is read
+    let mut entries: Vec<(String, Option<u16>, Option<bool>)> = Vec::new();
+
+    for line in config.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("remote") => {
+                if let Some(host) = parts.next() {
+                    let port = parts.next().and_then(|p| p.parse().ok());
+                    let tcp = parts.next().map(|p| p.starts_with("tcp"));
+                    entries.push((host.to_string(), port, tcp));
+                }
+            }
+            Some("port") => {
+                if let Some(port) = parts.next().and_then(|p| p.parse().ok()) {
+                    default_port = port;
+                }
+            }
+            Some("proto") => {
+                if let Some(proto) = parts.next() {
+                    default_tcp = proto.starts_with("tcp");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+        .into_iter()
+        .map(|(host, port, tcp)| Remote {
+            host,
+            port: port.unwrap_or(default_port),
+            tcp: tcp.unwrap_or(default_tcp),
+        })
+        .collect()
+}
+
+/// `nameserver` addresses of an /etc/resolv.conf
+pub fn parse_nameservers(resolv_conf: &str) -> Vec<IpAddr> {
+    resolv_conf
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|rest| parse_dns_server(rest.trim()))
+        .collect()
+}
+
+/// DNS servers from `resolvectl dns` ("Global: ..." and "Link 2 (wlp3s0): ..."),
+/// leaving out the links of `skip_device`
+pub fn parse_resolvectl_dns(output: &str, skip_device: &str) -> Vec<IpAddr> {
+    let skip = format!("({}):", skip_device);
+    output
+        .lines()
+        .filter(|line| !line.contains(&skip))
+        .filter_map(|line| line.split_once(": ").map(|(_, servers)| servers))
+        .flat_map(|servers| servers.split_whitespace().filter_map(parse_dns_server))
+        .collect()
+}
+
+/// A server address without its `%interface` scope or `#name` (DNS over TLS)
+fn parse_dns_server(server: &str) -> Option<IpAddr> {
+    let server = server.split(['#', '%']).next()?;
+    server.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(host: &str, port: u16, tcp: bool) -> Remote {
+        Remote { host: host.to_string(), port, tcp }
+    }
+
+    #[test]
+    fn remotes_with_defaults() {
+        let config = "client\nremote vpn.example.com\nremote 203.0.113.5 443 tcp-client\n";
+        assert_eq!(
+            parse_remotes(config),
+            [remote("vpn.example.com", 1194, false), remote("203.0.113.5", 443, true)]
+        );
+    }
+
+    #[test]
+    fn port_and_proto_apply_to_all_remotes() {
+        // The directives count wherever they appear, but not over what a remote line says
+        let config = "remote vpn.example.com\nremote 2001:db8::1 1195 udp\nport 443\nproto tcp\n";
+        assert_eq!(
+            parse_remotes(config),
+            [remote("vpn.example.com", 443, true), remote("2001:db8::1", 1195, false)]
+        );
+    }
+
+    #[test]
+    fn hosts_and_addresses_are_kept_as_written() {
+        let config = "remote vpn.example.com\nremote 198.51.100.7\nremote 2001:db8::2\n";
+        let hosts: Vec<String> = parse_remotes(config).into_iter().map(|r| r.host).collect();
+        assert_eq!(hosts, ["vpn.example.com", "198.51.100.7", "2001:db8::2"]);
+    }
+
+    #[test]
+    fn comments_and_incomplete_lines_are_skipped() {
+        let config = "# remote old.example.com\n; remote older.example.com\nremote\nport nope\nremote vpn.example.com\n";
+        assert_eq!(parse_remotes(config), [remote("vpn.example.com", 1194, false)]);
+    }
+
+    #[test]
+    fn nameservers_from_resolv_conf() {
+        let conf = "# Generated\nnameserver 192.168.1.1\nnameserver fe80::1%wlp3s0\nsearch lan\n#nameserver 9.9.9.9\nnameserver bogus\n";
+        let servers: Vec<IpAddr> = vec!["192.168.1.1".parse().unwrap(), "fe80::1".parse().unwrap()];
+        assert_eq!(parse_nameservers(conf), servers);
+    }
+
+    #[test]
+    fn resolvectl_servers_skip_the_tunnel() {
+        let output = "Global: 1.1.1.1#cloudflare-dns.com\nLink 2 (wlp3s0): 192.168.1.1 2001:db8::53\nLink 3 (docker0):\nLink 7 (tun0): 10.8.0.1\n";
+        let servers: Vec<String> = parse_resolvectl_dns(output, "tun0").iter().map(|a| a.to_string()).collect();
+        assert_eq!(servers, ["1.1.1.1", "192.168.1.1", "2001:db8::53"]);
+    }
+
+    #[test]
+    fn session_block_is_only_that_session() {
+        let output = "\
+-----------------------------------------------------------------------------
+        Path: /net/openvpn/v3/sessions/1a2b3c4ds1111s2222s3333s444455556666
+     Created: 2024-05-02 09:12:44                       PID: 4110
+       Owner: alice                                  Device: tun0
+ Config name: work.ovpn
+      Status: Connection, Authentication failed
+-----------------------------------------------------------------------------
+        Path: /net/openvpn/v3/sessions/9f8e7d6cs7777s8888s9999s000011112222
+     Created: 2024-05-02 09:14:02                       PID: 4188
+       Owner: alice                                  Device: tun1
+ Config name: home.ovpn
+      Status: Connection, Client connected
+-----------------------------------------------------------------------------
+";
+        let block = session_block(output, "/net/openvpn/v3/sessions/9f8e7d6cs7777s8888s9999s000011112222").unwrap();
+        assert!(block.contains("home.ovpn") && block.contains("Client connected"));
+        assert!(!block.contains("Authentication failed"));
+        assert_eq!(extract_tunnel_device(&block).as_deref(), Some("tun1"));
+
+        let block = session_block(output, "/net/openvpn/v3/sessions/1a2b3c4ds1111s2222s3333s444455556666").unwrap();
+        assert!(block.contains("Authentication failed") && !block.contains("Client connected"));
+
+        // A path that is only a prefix of another is a different session
+        assert_eq!(session_block(output, "/net/openvpn/v3/sessions/9f8e7d6c"), None);
+        assert_eq!(session_block("No sessions available\n", "/net/openvpn/v3/sessions/9f8e7d6c"), None);
+    }
+}
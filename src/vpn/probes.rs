@@ -0,0 +1,387 @@
+// Latency probes - ICMP/TCP targets with rolling RTT, jitter and loss statistics
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// How a target is probed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ProbeKind {
+    /// One `ping` echo request
+    Icmp,
+    /// Time to complete a TCP handshake
+    Tcp { port: u16 },
+}
+
+/// Which side of the tunnel a target lives on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetRole {
+    /// The VPN server's tunnel address (host is detected while connected)
+    Gateway,
+    /// A host only reachable through the tunnel
+    Internal,
+    /// A host on the public internet
+    Public,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProbeTarget {
+    pub name: String,
+    pub role: TargetRole,
+    /// Host to probe; leave empty for the gateway to use the detected address
+    #[serde(default)]
+    pub host: String,
+    pub kind: ProbeKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProbeSettings {
+    pub targets: Vec<ProbeTarget>,
+    /// Number of samples kept per target
+    pub window: usize,
+    pub interval_secs: u64,
+    pub timeout_ms: u64,
+}
+
+impl Default for ProbeSettings {
+    fn default() -> Self {
+        Self {
+            targets: vec![
+                ProbeTarget {
+                    name: "Gateway".to_string(),
+                    role: TargetRole::Gateway,
+                    host: String::new(),
+                    kind: ProbeKind::Icmp,
+                },
+                ProbeTarget {
+                    name: "Public".to_string(),
+                    role: TargetRole::Public,
+                    host: "8.8.8.8".to_string(),
+                    kind: ProbeKind::Icmp,
+                },
+            ],
+            window: 30,
+            interval_secs: 1,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+/// Summary of a target's rolling window
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProbeStats {
+    pub last_ms: Option<f32>,
+    pub min_ms: Option<f32>,
+    pub avg_ms: Option<f32>,
+    pub max_ms: Option<f32>,
+    /// Mean absolute difference between the RTTs of back-to-back probes
+    pub jitter_ms: Option<f32>,
+    pub loss_pct: f32,
+    pub samples: usize,
+}
+
+/// Rolling window of probe results (None = lost)
+#[derive(Debug, Clone)]
+pub struct ProbeWindow {
+    samples: VecDeque<Option<f32>>,
+    capacity: usize,
+}
+
+impl ProbeWindow {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn push(&mut self, rtt_ms: Option<f32>) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(rtt_ms);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn stats(&self) -> ProbeStats {
+        let received: Vec<f32> = self.samples.iter().flatten().copied().collect();
+        let total = self.samples.len();
+        let loss_pct = if total == 0 {
+            0.0
+        } else {
+            (total - received.len()) as f32 * 100.0 / total as f32
+        };
+
+        let min_ms = received.iter().copied().reduce(f32::min);
+        let max_ms = received.iter().copied().reduce(f32::max);
+        let avg_ms = if received.is_empty() {
+            None
+        } else {
+            Some(received.iter().sum::<f32>() / received.len() as f32)
+        };
+        // Only neighbouring probes that both came back: closing up the gap a loss leaves
+        // would compare RTTs taken further apart and hide the variation around it
+        let diffs: Vec<f32> = self
+            .samples
+            .iter()
+            .zip(self.samples.iter().skip(1))
+            .filter_map(|pair| match pair {
+                (Some(a), Some(b)) => Some((b - a).abs()),
+                _ => None,
+            })
+            .collect();
+        let jitter_ms = if diffs.is_empty() {
+            None
+        } else {
+            Some(diffs.iter().sum::<f32>() / diffs.len() as f32)
+        };
+
+        ProbeStats {
+            last_ms: self.samples.back().copied().flatten(),
+            min_ms,
+            avg_ms,
+            max_ms,
+            jitter_ms,
+            loss_pct,
+            samples: total,
+        }
+    }
+}
+
+struct TargetState {
+    target: ProbeTarget,
+    window: ProbeWindow,
+    in_flight: bool,
+}
+
+/// A probe that should be launched now
+#[derive(Debug, Clone)]
+pub struct ProbeJob {
+    pub name: String,
+    pub host: String,
+    pub kind: ProbeKind,
+    pub timeout: Duration,
+}
+
+/// Keeps per-target windows and decides which probes are due
+pub struct ProbeEngine {
+    targets: Vec<TargetState>,
+    last_round: Option<Instant>,
+}
+
+impl ProbeEngine {
+    pub fn new(settings: &ProbeSettings) -> Self {
+        Self {
+            targets: settings
+                .targets
+                .iter()
+                .map(|t| TargetState {
+                    target: t.clone(),
+                    window: ProbeWindow::new(settings.window),
+                    in_flight: false,
+                })
+                .collect(),
+            last_round: None,
+        }
+    }
+
+    /// Probes to launch this tick. Tunnel-side targets only run while connected.
+    pub fn due_jobs(&mut self, settings: &ProbeSettings, connected: bool, gateway: Option<&str>) -> Vec<ProbeJob> {
+        let interval = Duration::from_secs(settings.interval_secs.max(1));
+        if self.last_round.is_some_and(|t| t.elapsed() < interval) {
+            return Vec::new();
+        }
+        self.last_round = Some(Instant::now());
+
+        let timeout = Duration::from_millis(settings.timeout_ms.max(100));
+        let mut jobs = Vec::new();
+        for state in &mut self.targets {
+            if state.in_flight {
+                continue;
+            }
+            let host = match state.target.role {
+                TargetRole::Gateway if !connected => None,
+                TargetRole::Gateway if state.target.host.is_empty() => gateway.map(|g| g.to_string()),
+                TargetRole::Internal if !connected => None,
+                _ if state.target.host.is_empty() => None,
+                _ => Some(state.target.host.clone()),
+            };
+            if let Some(host) = host {
+                state.in_flight = true;
+                jobs.push(ProbeJob {
+                    name: state.target.name.clone(),
+                    host,
+                    kind: state.target.kind,
+                    timeout,
+                });
+            }
+        }
+        jobs
+    }
+
+    /// Store the result of a finished probe
+    pub fn record(&mut self, name: &str, rtt_ms: Option<f32>) {
+        if let Some(state) = self.targets.iter_mut().find(|s| s.target.name == name) {
+            state.in_flight = false;
+            state.window.push(rtt_ms);
+        }
+    }
+
+    /// Forget tunnel-side samples (e.g. after disconnecting)
+    pub fn reset_tunnel_targets(&mut self) {
+        for state in &mut self.targets {
+            if state.target.role != TargetRole::Public {
+                state.window.clear();
+            }
+        }
+    }
+
+    /// Drop all samples (e.g. when probes get disabled)
+    pub fn clear(&mut self) {
+        for state in &mut self.targets {
+            state.window.clear();
+        }
+    }
+
+    /// Statistics for every target that has samples
+    pub fn stats(&self) -> Vec<(&ProbeTarget, ProbeStats)> {
+        self.targets
+            .iter()
+            .filter(|s| !s.window.samples.is_empty())
+            .map(|s| (&s.target, s.window.stats()))
+            .collect()
+    }
+
+    /// Stats of the most relevant target with samples (gateway first, then internal, then public)
+    pub fn primary_stats(&self) -> Option<(&ProbeTarget, ProbeStats)> {
+        [TargetRole::Gateway, TargetRole::Internal, TargetRole::Public]
+            .iter()
+            .find_map(|role| {
+                self.targets
+                    .iter()
+                    .find(|s| s.target.role == *role && !s.window.samples.is_empty())
+                    .map(|s| (&s.target, s.window.stats()))
+            })
+    }
+
+    /// Latest RTT of the most relevant target
+    pub fn primary_latency(&self) -> Option<f32> {
+        self.primary_stats().and_then(|(_, stats)| stats.last_ms)
+    }
+}
+
+/// Run a single probe and return the round-trip time in ms (None if lost)
+pub async fn run_probe(job: ProbeJob) -> Option<f32> {
+    match job.kind {
+        ProbeKind::Icmp => ping(&job.host, job.timeout).await,
+        ProbeKind::Tcp { port } => tcp_connect(&job.host, port, job.timeout).await,
+    }
+}
+
+async fn ping(host: &str, timeout: Duration) -> Option<f32> {
+    let wait_secs = timeout.as_secs().max(1).to_string();
+    let output = Command::new("ping")
+        .args(["-n", "-c", "1", "-W", &wait_secs, host])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for line in stdout.lines() {
+        if let Some(idx) = line.find("time=") {
+            let ms = &line[idx + 5..];
+            if let Some(end) = ms.find(' ') {
+                if let Ok(val) = ms[..end].parse::<f32>() {
+                    return Some(val);
+                }
+            }
+        }
+    }
+    None
+}
+
+async fn tcp_connect(host: &str, port: u16, timeout: Duration) -> Option<f32> {
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, tokio::net::TcpStream::connect((host, port))).await {
+        Ok(Ok(_stream)) => Some(start.elapsed().as_secs_f32() * 1000.0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(samples: &[Option<f32>]) -> ProbeWindow {
+        let mut window = ProbeWindow::new(samples.len());
+        for sample in samples {
+            window.push(*sample);
+        }
+        window
+    }
+
+    #[test]
+    fn empty_window_has_no_stats() {
+        assert_eq!(ProbeWindow::new(10).stats(), ProbeStats::default());
+    }
+
+    #[test]
+    fn all_lost() {
+        let stats = window(&[None, None, None, None]).stats();
+        assert_eq!(stats.loss_pct, 100.0);
+        assert_eq!(stats.samples, 4);
+        assert_eq!((stats.last_ms, stats.min_ms, stats.avg_ms, stats.max_ms), (None, None, None, None));
+        assert_eq!(stats.jitter_ms, None);
+    }
+
+    #[test]
+    fn no_loss() {
+        let stats = window(&[Some(10.0), Some(20.0), Some(15.0), Some(15.0)]).stats();
+        assert_eq!(stats.loss_pct, 0.0);
+        assert_eq!(stats.last_ms, Some(15.0));
+        assert_eq!(stats.min_ms, Some(10.0));
+        assert_eq!(stats.avg_ms, Some(15.0));
+        assert_eq!(stats.max_ms, Some(20.0));
+        // |20-10|, |15-20|, |15-15|
+        assert_eq!(stats.jitter_ms, Some(5.0));
+    }
+
+    #[test]
+    fn partial_loss() {
+        let stats = window(&[Some(10.0), None, Some(40.0), Some(44.0), None]).stats();
+        assert_eq!(stats.loss_pct, 40.0);
+        assert_eq!(stats.samples, 5);
+        // The newest probe was lost
+        assert_eq!(stats.last_ms, None);
+        assert_eq!(stats.min_ms, Some(10.0));
+        assert_eq!(stats.avg_ms, Some(31.333334));
+        assert_eq!(stats.max_ms, Some(44.0));
+        // Only 40 -> 44 were back to back; 10 -> 40 straddles a loss
+        assert_eq!(stats.jitter_ms, Some(4.0));
+
+        // Every other probe lost: no neighbouring pair to compare
+        assert_eq!(window(&[Some(10.0), None, Some(30.0), None]).stats().jitter_ms, None);
+    }
+
+    #[test]
+    fn window_keeps_the_newest_samples() {
+        let mut window = ProbeWindow::new(3);
+        for sample in [None, None, Some(5.0), Some(7.0), Some(9.0)] {
+            window.push(sample);
+        }
+        let stats = window.stats();
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.loss_pct, 0.0);
+        assert_eq!(stats.min_ms, Some(5.0));
+    }
+}
@@ -0,0 +1,220 @@
+// Process execution - subprocesses with timeouts, captured output and typed errors
+
+use std::fmt;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::{Child, Command};
+
+/// Why running a subprocess did not give us what we needed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessError {
+    /// The program is not installed (or not on PATH)
+    NotFound { program: String },
+    /// The program exists but could not be started
+    SpawnFailed { program: String, error: String },
+    /// No answer within the deadline; the process was killed
+    TimedOut { program: String, after: Duration },
+    /// The program ran and reported failure
+    NonZeroExit { program: String, code: Option<i32>, stderr: String },
+    /// The program succeeded but its output was not what we expected
+    Unparseable { program: String, output: String },
+}
+
+impl ProcessError {
+    /// stderr of a failed run (empty for other errors)
+    pub fn stderr(&self) -> &str {
+        match self {
+            ProcessError::NonZeroExit { stderr, .. } => stderr,
+            _ => "",
+        }
+    }
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::NotFound { program } => write!(f, "{} is not installed", program),
+            ProcessError::SpawnFailed { program, error } => write!(f, "Failed to execute {}: {}", program, error),
+            ProcessError::TimedOut { program, after } => {
+                write!(f, "{} did not respond within {}s", program, after.as_secs())
+            }
+            ProcessError::NonZeroExit { program, code, stderr } => {
+                let code = code.map(|c| c.to_string()).unwrap_or_else(|| "signal".to_string());
+                if stderr.trim().is_empty() {
+                    write!(f, "{} failed (exit {})", program, code)
+                } else {
+                    write!(f, "{} failed (exit {}): {}", program, code, stderr.trim())
+                }
+            }
+            ProcessError::Unparseable { program, .. } => write!(f, "Unexpected output from {}", program),
+        }
+    }
+}
+
+/// Captured result of a successful run
+#[derive(Debug, Clone)]
+pub struct ProcessOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub code: Option<i32>,
+}
+
+impl ProcessOutput {
+    /// Parse stdout, turning `None` into `Unparseable`
+    pub fn parse<T>(&self, program: &str, parse: impl FnOnce(&str) -> Option<T>) -> Result<T, ProcessError> {
+        parse(&self.stdout).ok_or_else(|| ProcessError::Unparseable {
+            program: program.to_string(),
+            output: self.stdout.clone(),
+        })
+    }
+}
+
+/// Start a program with stdout/stderr captured. The child is killed if the
+/// handle is dropped, so an aborted task never leaves it running.
+pub fn spawn(program: &str, args: &[&str], stdin: bool) -> Result<Child, ProcessError> {
+    Command::new(program)
+        .args(args)
+        .stdin(if stdin { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| spawn_error(program, e))
+}
+
+fn spawn_error(program: &str, error: std::io::Error) -> ProcessError {
+    if error.kind() == std::io::ErrorKind::NotFound {
+        ProcessError::NotFound { program: program.to_string() }
+    } else {
+        ProcessError::SpawnFailed { program: program.to_string(), error: error.to_string() }
+    }
+}
+
+/// Run a program to completion within `timeout`
+pub async fn run(program: &str, args: &[&str], timeout: Duration) -> Result<ProcessOutput, ProcessError> {
+    run_with_input(program, args, None, timeout).await
+}
+
+/// Run a program, feeding `input` on stdin, to completion within `timeout`
+pub async fn run_with_input(
+    program: &str,
+    args: &[&str],
+    input: Option<String>,
+    timeout: Duration,
+) -> Result<ProcessOutput, ProcessError> {
+    let mut child = spawn(program, args, input.is_some())?;
+    let stdin = child.stdin.take();
+    let finished = async move {
+        if let (Some(input), Some(mut stdin)) = (input, stdin) {
+            // Dropping stdin afterwards closes it so the program sees EOF
+            let _ = stdin.write_all(input.as_bytes()).await;
+        }
+        child.wait_with_output().await
+    };
+
+    // Writing counts towards the deadline too: a program that never reads would block it.
+    // On timeout the future (and with it the child) is dropped: killed and reaped by tokio
+    let output = tokio::time::timeout(timeout, finished)
+        .await
+        .map_err(|_| ProcessError::TimedOut { program: program.to_string(), after: timeout })?
+        .map_err(|e| spawn_error(program, e))?;
+
+    let result = ProcessOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        code: output.status.code(),
+    };
+    if output.status.success() {
+        Ok(result)
+    } else {
+        Err(ProcessError::NonZeroExit {
+            program: program.to_string(),
+            code: result.code,
+            stderr: result.stderr,
+        })
+    }
+}
+
+/// Wait for a long-running child in the background so it never becomes a zombie
+pub fn reap(child: Child) {
+    tokio::spawn(async move {
+        let _ = child.wait_with_output().await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    async fn captures_output() {
+        let output = run("sh", &["-c", "echo out; echo err >&2"], TIMEOUT).await.unwrap();
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+        assert_eq!(output.code, Some(0));
+    }
+
+    #[tokio::test]
+    async fn feeds_input() {
+        let output = run_with_input("sh", &["-c", "tr a-z A-Z"], Some("hello".to_string()), TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, "HELLO");
+    }
+
+    #[tokio::test]
+    async fn missing_program() {
+        let error = run("openvpn3-gui-no-such-program", &[], TIMEOUT).await.unwrap_err();
+        assert_eq!(error, ProcessError::NotFound { program: "openvpn3-gui-no-such-program".to_string() });
+        assert_eq!(error.to_string(), "openvpn3-gui-no-such-program is not installed");
+    }
+
+    #[tokio::test]
+    async fn non_zero_exit_keeps_stderr() {
+        let error = run("sh", &["-c", "echo 'Session not found' >&2; exit 3"], TIMEOUT).await.unwrap_err();
+        assert_eq!(
+            error,
+            ProcessError::NonZeroExit {
+                program: "sh".to_string(),
+                code: Some(3),
+                stderr: "Session not found\n".to_string(),
+            }
+        );
+        assert_eq!(error.stderr(), "Session not found\n");
+        assert_eq!(error.to_string(), "sh failed (exit 3): Session not found");
+    }
+
+    #[tokio::test]
+    async fn slow_program_times_out() {
+        let timeout = Duration::from_millis(200);
+        let started = std::time::Instant::now();
+        let error = run("sleep", &["10"], timeout).await.unwrap_err();
+        assert_eq!(error, ProcessError::TimedOut { program: "sleep".to_string(), after: timeout });
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn program_that_never_reads_input_times_out() {
+        // Far more than a pipe buffer holds, so the write blocks
+        let input = "x".repeat(1 << 20);
+        let timeout = Duration::from_millis(200);
+        let started = std::time::Instant::now();
+        let error = run_with_input("sleep", &["10"], Some(input), timeout).await.unwrap_err();
+        assert_eq!(error, ProcessError::TimedOut { program: "sleep".to_string(), after: timeout });
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn unexpected_output() {
+        let output = run("sh", &["-c", "echo Status: unknown"], TIMEOUT).await.unwrap();
+        assert_eq!(output.parse("sh", |s| s.trim().strip_prefix("Status: ").map(str::to_string)).unwrap(), "unknown");
+        let error = output.parse("sh", |s| s.parse::<u64>().ok()).unwrap_err();
+        assert_eq!(
+            error,
+            ProcessError::Unparseable { program: "sh".to_string(), output: "Status: unknown\n".to_string() }
+        );
+    }
+}
@@ -0,0 +1,284 @@
+// Public IP lookup - configurable endpoints, caching and consensus
+
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// DNS record type used for DNS-based lookups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecord {
+    A,
+    Txt,
+}
+
+/// A single "what is my IP" source
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IpEndpoint {
+    /// HTTP(S) service returning the address as plain text
+    Http { url: String },
+    /// DNS query for `name` against `resolver` (e.g. myip.opendns.com A @resolver1.opendns.com)
+    Dns { resolver: String, name: String, record: DnsRecord },
+}
+
+impl IpEndpoint {
+    /// Short label used in logs and disagreement reports
+    pub fn label(&self) -> String {
+        match self {
+            IpEndpoint::Http { url } => url::Url::parse(url)
+                .ok()
+                .and_then(|u| u.host_str().map(|h| h.to_string()))
+                .unwrap_or_else(|| url.clone()),
+            IpEndpoint::Dns { resolver, name, .. } => format!("{}@{}", name, resolver),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PublicIpSettings {
+    pub endpoints: Vec<IpEndpoint>,
+    /// Minimum time between two lookups, however they were triggered
+    pub min_interval_secs: u64,
+    /// How long a result is reused before it is refreshed (0 = until the next connect/disconnect)
+    pub cache_ttl_secs: u64,
+    /// Per-endpoint timeout
+    pub timeout_secs: u64,
+}
+
+impl Default for PublicIpSettings {
+    fn default() -> Self {
+        Self {
+            endpoints: vec![
+                IpEndpoint::Http { url: "https://api.ipify.org".to_string() },
+                IpEndpoint::Http { url: "https://ifconfig.me/ip".to_string() },
+                IpEndpoint::Http { url: "https://icanhazip.com".to_string() },
+            ],
+            min_interval_secs: 10,
+            cache_ttl_secs: 0,
+            timeout_secs: 5,
+        }
+    }
+}
+
+/// Result of querying every configured endpoint
+#[derive(Debug, Clone, Default)]
+pub struct PublicIpLookup {
+    /// Address more than half of the answering endpoints agree on
+    pub ip: Option<String>,
+    /// Human readable description when endpoints returned different addresses
+    pub disagreement: Option<String>,
+    /// Why some endpoints could not be queried at all (e.g. dig is not installed)
+    pub problem: Option<String>,
+}
+
+/// Query all endpoints at once and take the majority answer
+pub async fn lookup_public_ip(settings: PublicIpSettings) -> PublicIpLookup {
+    let timeout = Duration::from_secs(settings.timeout_secs.max(1));
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .ok();
+    let client = client.as_ref();
+
+    let queries = settings.endpoints.iter().map(|endpoint| async move {
+        match endpoint {
+            IpEndpoint::Http { url } => match client {
+                Some(client) => Ok(query_http(client, url).await),
+                None => Ok(None),
+            },
+            IpEndpoint::Dns { resolver, name, record } => query_dns(resolver, name, *record, timeout).await,
+        }
+    });
+    let mut answers = Vec::new();
+    let mut problem = None;
+    for (endpoint, answer) in settings.endpoints.iter().zip(join_all(queries).await) {
+        let answer = answer.unwrap_or_else(|e| {
+            problem = Some(e);
+            None
+        });
+        answers.push((endpoint.label(), answer));
+    }
+
+    let (ip, disagreement) = consensus(&answers);
+    PublicIpLookup { ip, disagreement, problem }
+}
+
+async fn query_http(client: &reqwest::Client, url: &str) -> Option<String> {
+    let response = client.get(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    let body = response.text().await.ok()?;
+    normalize_ip(&body)
+}
+
+/// Ask a resolver with dig. Only a missing dig is an error; any other failure is no answer.
+async fn query_dns(resolver: &str, name: &str, record: DnsRecord, timeout: Duration) -> Result<Option<String>, String> {
+    let record_type = match record {
+        DnsRecord::A => "A",
+        DnsRecord::Txt => "TXT",
+    };
+    let output = Command::new("dig")
+        .args([
+            "+short",
+            "+tries=1",
+            &format!("+time={}", timeout.as_secs()),
+            &format!("@{}", resolver),
+            name,
+            record_type,
+        ])
+        .output()
+        .await;
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(_) => return Ok(None),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err("DNS public IP endpoints need dig, which is not installed".to_string());
+        }
+        Err(_) => return Ok(None),
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().find_map(normalize_ip))
+}
+
+/// Trim whitespace and TXT quoting, and only accept valid IP addresses
+fn normalize_ip(raw: &str) -> Option<String> {
+    let candidate = raw.trim().trim_matches('"');
+    candidate.parse::<IpAddr>().ok().map(|ip| ip.to_string())
+}
+
+/// Pick the answer returned by more than half of the endpoints that answered (none
+/// without such a majority) and describe any disagreement between them
+pub fn consensus(answers: &[(String, Option<String>)]) -> (Option<String>, Option<String>) {
+    // (ip, endpoints that returned it), in order of first appearance
+    let mut votes: Vec<(String, Vec<String>)> = Vec::new();
+    for (label, answer) in answers {
+        if let Some(ip) = answer {
+            match votes.iter_mut().find(|(v, _)| v == ip) {
+                Some((_, labels)) => labels.push(label.clone()),
+                None => votes.push((ip.clone(), vec![label.clone()])),
+            }
+        }
+    }
+
+    let answered: usize = votes.iter().map(|(_, labels)| labels.len()).sum();
+    let winner = votes.iter().find(|(_, labels)| labels.len() * 2 > answered);
+
+    let disagreement = if votes.len() > 1 {
+        let parts = votes
+            .iter()
+            .map(|(ip, labels)| format!("{} ({})", ip, labels.join(", ")))
+            .collect::<Vec<_>>()
+            .join(" vs ");
+        Some(format!("Public IP endpoints disagree: {}", parts))
+    } else {
+        None
+    };
+
+    (winner.map(|(ip, _)| ip.clone()), disagreement)
+}
+
+/// Rate limiting and caching for public IP lookups
+#[derive(Debug, Default)]
+pub struct PublicIpTracker {
+    last_lookup: Option<Instant>,
+    refresh_requested: bool,
+    in_flight: bool,
+}
+
+impl PublicIpTracker {
+    pub fn new() -> Self {
+        Self {
+            refresh_requested: true,
+            ..Default::default()
+        }
+    }
+
+    /// Ask for a fresh lookup (e.g. after connecting or disconnecting)
+    pub fn request_refresh(&mut self) {
+        self.refresh_requested = true;
+    }
+
+    /// Whether a lookup should start now; marks it as in flight if so
+    pub fn start_if_due(&mut self, settings: &PublicIpSettings) -> bool {
+        if self.in_flight || settings.endpoints.is_empty() {
+            return false;
+        }
+
+        let since_last = self.last_lookup.map(|t| t.elapsed());
+        let rate_ok = since_last.is_none_or(|d| d >= Duration::from_secs(settings.min_interval_secs));
+        let expired = settings.cache_ttl_secs > 0
+            && since_last.is_some_and(|d| d >= Duration::from_secs(settings.cache_ttl_secs));
+
+        if rate_ok && (self.refresh_requested || expired) {
+            self.refresh_requested = false;
+            self.in_flight = true;
+            self.last_lookup = Some(Instant::now());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Record that the running lookup has finished
+    pub fn finish(&mut self) {
+        self.in_flight = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answers(ips: &[Option<&str>]) -> Vec<(String, Option<String>)> {
+        ips.iter()
+            .enumerate()
+            .map(|(i, ip)| (format!("endpoint{}", i + 1), ip.map(str::to_string)))
+            .collect()
+    }
+
+    #[test]
+    fn normalize_accepts_only_addresses() {
+        assert_eq!(normalize_ip(" 203.0.113.7\n").as_deref(), Some("203.0.113.7"));
+        assert_eq!(normalize_ip("\"203.0.113.7\"").as_deref(), Some("203.0.113.7"));
+        assert_eq!(normalize_ip("2001:DB8::1").as_deref(), Some("2001:db8::1"));
+        assert_eq!(normalize_ip("<html>Too Many Requests</html>"), None);
+        assert_eq!(normalize_ip("203.0.113.256"), None);
+        assert_eq!(normalize_ip(""), None);
+    }
+
+    #[test]
+    fn consensus_needs_a_majority() {
+        let (ip, disagreement) = consensus(&answers(&[Some("1.1.1.1"), Some("1.1.1.1"), Some("2.2.2.2")]));
+        assert_eq!(ip.as_deref(), Some("1.1.1.1"));
+        assert_eq!(
+            disagreement.as_deref(),
+            Some("Public IP endpoints disagree: 1.1.1.1 (endpoint1, endpoint2) vs 2.2.2.2 (endpoint3)")
+        );
+
+        // Three different answers, or an even split: nobody wins
+        let (ip, disagreement) = consensus(&answers(&[Some("1.1.1.1"), Some("2.2.2.2"), Some("3.3.3.3")]));
+        assert_eq!(ip, None);
+        assert!(disagreement.is_some());
+        let (ip, _) = consensus(&answers(&[Some("1.1.1.1"), Some("2.2.2.2")]));
+        assert_eq!(ip, None);
+    }
+
+    #[test]
+    fn consensus_counts_only_endpoints_that_answered() {
+        let (ip, disagreement) = consensus(&answers(&[None, Some("1.1.1.1"), None]));
+        assert_eq!(ip.as_deref(), Some("1.1.1.1"));
+        assert_eq!(disagreement, None);
+
+        let (ip, _) = consensus(&answers(&[Some("1.1.1.1"), None, Some("1.1.1.1"), Some("2.2.2.2")]));
+        assert_eq!(ip.as_deref(), Some("1.1.1.1"));
+
+        assert_eq!(consensus(&answers(&[None, None])), (None, None));
+        assert_eq!(consensus(&[]), (None, None));
+    }
+}
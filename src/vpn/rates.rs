@@ -0,0 +1,444 @@
+// Transfer rates from session-stats counters, timed by when each reading was taken
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Readings further apart than this leave a gap in the graph instead of a joining line
+pub const GAP_SECS: f32 = 3.0;
+
+/// Byte counters of a session and the monotonic time they were requested
+#[derive(Debug, Clone, Copy)]
+pub struct CounterSample {
+    pub at: Instant,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+/// Rates (bytes/sec) over the interval ending at `at`
+#[derive(Debug, Clone, Copy)]
+pub struct RatePoint {
+    pub at: Instant,
+    pub rate_in: f32,
+    pub rate_out: f32,
+}
+
+/// What a new reading amounts to
+#[derive(Debug, Clone, Copy)]
+pub enum SampleOutcome {
+    Rate(RatePoint),
+    /// First reading of the session; a rate needs two
+    First,
+    /// The counters went backwards in a newer reading, so openvpn3 started them over
+    Reset,
+    /// Requested before the last reading (a slow response arriving late); ignored,
+    /// and never taken for a reset even though its counters are lower
+    Stale,
+}
+
+/// Turns successive readings into rates, optionally smoothed
+#[derive(Debug, Clone, Default)]
+pub struct RateMeter {
+    last: Option<CounterSample>,
+    smoothed: Option<(f32, f32)>,
+}
+
+impl RateMeter {
+    /// Rates since the previous reading, averaged over the real time between them.
+    /// With `smoothing_secs` > 0 they go through an exponential moving average with
+    /// that time constant, weighted by the elapsed time so missed ticks count fully.
+    pub fn update(&mut self, sample: CounterSample, smoothing_secs: f32) -> SampleOutcome {
+        let Some(last) = self.last else {
+            self.last = Some(sample);
+            return SampleOutcome::First;
+        };
+        if sample.at <= last.at {
+            return SampleOutcome::Stale;
+        }
+        self.last = Some(sample);
+        if sample.bytes_in < last.bytes_in || sample.bytes_out < last.bytes_out {
+            self.smoothed = None;
+            return SampleOutcome::Reset;
+        }
+
+        let elapsed = (sample.at - last.at).as_secs_f32();
+        let mut rate_in = (sample.bytes_in - last.bytes_in) as f32 / elapsed;
+        let mut rate_out = (sample.bytes_out - last.bytes_out) as f32 / elapsed;
+        if smoothing_secs > 0.0 {
+            if let Some((prev_in, prev_out)) = self.smoothed {
+                let alpha = 1.0 - (-elapsed / smoothing_secs).exp();
+                rate_in = prev_in + alpha * (rate_in - prev_in);
+                rate_out = prev_out + alpha * (rate_out - prev_out);
+            }
+            self.smoothed = Some((rate_in, rate_out));
+        } else {
+            self.smoothed = None;
+        }
+        SampleOutcome::Rate(RatePoint { at: sample.at, rate_in, rate_out })
+    }
+
+    /// Forget the session's readings (the next one starts a new session)
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// A rate point placed relative to a reference time, for drawing and reports
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RelativePoint {
+    /// Seconds before the reference time
+    pub secs_ago: f32,
+    pub rate_in: f32,
+    pub rate_out: f32,
+}
+
+/// Points of one window, oldest first, at the resolution the window is kept at
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RateSeries {
+    pub points: Vec<RelativePoint>,
+    /// Points further apart than this have readings missing between them
+    pub max_gap_secs: f32,
+}
+
+impl RateSeries {
+    /// Runs of points with no reading missing between them
+    pub fn runs(&self) -> impl Iterator<Item = &[RelativePoint]> {
+        let max_gap = self.max_gap_secs;
+        self.points.chunk_by(move |a, b| a.secs_ago - b.secs_ago <= max_gap)
+    }
+}
+
+/// Raw points are kept this long
+const RAW_SPAN: Duration = Duration::from_secs(5 * 60);
+/// Longer windows up to this are drawn from buckets of `HOUR_BUCKET`
+const HOUR_SPAN: Duration = Duration::from_secs(60 * 60);
+const HOUR_BUCKET: Duration = Duration::from_secs(10);
+/// The whole session starts at `HOUR_BUCKET` and halves its resolution whenever it has more buckets than this
+const SESSION_BUCKETS: usize = 720;
+
+/// Average of the points that fell into one interval
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    start: Instant,
+    last: Instant,
+    sum_in: f64,
+    sum_out: f64,
+    count: u32,
+}
+
+impl Bucket {
+    fn new(point: RatePoint) -> Self {
+        Self {
+            start: point.at,
+            last: point.at,
+            sum_in: point.rate_in as f64,
+            sum_out: point.rate_out as f64,
+            count: 1,
+        }
+    }
+
+    fn merge(&mut self, other: &Bucket) {
+        self.last = other.last;
+        self.sum_in += other.sum_in;
+        self.sum_out += other.sum_out;
+        self.count += other.count;
+    }
+
+    fn point(&self) -> RatePoint {
+        RatePoint {
+            at: self.start + (self.last - self.start) / 2,
+            rate_in: (self.sum_in / self.count as f64) as f32,
+            rate_out: (self.sum_out / self.count as f64) as f32,
+        }
+    }
+}
+
+/// Buckets of a fixed width, oldest first
+#[derive(Debug, Clone)]
+struct Tier {
+    buckets: VecDeque<Bucket>,
+    width: Duration,
+}
+
+impl Tier {
+    fn new(width: Duration) -> Self {
+        Self {
+            buckets: VecDeque::new(),
+            width,
+        }
+    }
+
+    fn add(&mut self, point: RatePoint) {
+        match self.buckets.back_mut() {
+            Some(bucket) if point.at < bucket.start + self.width => bucket.merge(&Bucket::new(point)),
+            _ => self.buckets.push_back(Bucket::new(point)),
+        }
+    }
+
+    /// Double the width, merging neighbouring buckets
+    fn coarsen(&mut self) {
+        self.width *= 2;
+        let mut merged: VecDeque<Bucket> = VecDeque::with_capacity(self.buckets.len() / 2 + 1);
+        for bucket in self.buckets.drain(..) {
+            match merged.back_mut() {
+                Some(last) if bucket.start < last.start + self.width => last.merge(&bucket),
+                _ => merged.push_back(bucket),
+            }
+        }
+        self.buckets = merged;
+    }
+}
+
+/// Rate points kept at three resolutions: raw for the last minutes, 10 second
+/// averages for the last hour, and the whole session downsampled to a bounded size
+#[derive(Debug, Clone)]
+pub struct RateHistory {
+    raw: VecDeque<RatePoint>,
+    hour: Tier,
+    session: Tier,
+    session_start: Option<Instant>,
+}
+
+impl Default for RateHistory {
+    fn default() -> Self {
+        Self {
+            raw: VecDeque::new(),
+            hour: Tier::new(HOUR_BUCKET),
+            session: Tier::new(HOUR_BUCKET),
+            session_start: None,
+        }
+    }
+}
+
+impl RateHistory {
+    /// A session connected; the session window starts over (the shorter ones keep their points)
+    pub fn start_session(&mut self, now: Instant) {
+        self.session = Tier::new(HOUR_BUCKET);
+        self.session_start = Some(now);
+    }
+
+    pub fn push(&mut self, point: RatePoint) {
+        self.raw.push_back(point);
+        while self.raw.front().is_some_and(|p| point.at.duration_since(p.at) > RAW_SPAN) {
+            self.raw.pop_front();
+        }
+
+        self.hour.add(point);
+        while self.hour.buckets.front().is_some_and(|b| point.at.duration_since(b.start) > HOUR_SPAN) {
+            self.hour.buckets.pop_front();
+        }
+
+        self.session.add(point);
+        if self.session.buckets.len() > SESSION_BUCKETS {
+            self.session.coarsen();
+        }
+    }
+
+    /// Seconds from the start of the session to `now` (None before the first session)
+    pub fn session_secs(&self, now: Instant) -> Option<f32> {
+        self.session_start.map(|start| now.saturating_duration_since(start).as_secs_f32())
+    }
+
+    /// The window of `span` ending at `now` (None = the whole session), in seconds before `now`
+    pub fn series(&self, now: Instant, span: Option<Duration>) -> RateSeries {
+        let (points, resolution): (Vec<RatePoint>, Duration) = match span {
+            Some(span) if span <= RAW_SPAN => (self.raw.iter().copied().collect(), Duration::ZERO),
+            Some(_) => (self.hour.buckets.iter().map(Bucket::point).collect(), self.hour.width),
+            None => (self.session.buckets.iter().map(Bucket::point).collect(), self.session.width),
+        };
+        let points = points
+            .into_iter()
+            .filter(|p| span.is_none_or(|span| now.saturating_duration_since(p.at) <= span))
+            .map(|p| RelativePoint {
+                secs_ago: now.saturating_duration_since(p.at).as_secs_f32(),
+                rate_in: p.rate_in,
+                rate_out: p.rate_out,
+            })
+            .collect();
+        RateSeries {
+            points,
+            max_gap_secs: resolution.as_secs_f32() + GAP_SECS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(start: Instant, secs: f32, bytes_in: u64, bytes_out: u64) -> CounterSample {
+        CounterSample { at: start + Duration::from_secs_f32(secs), bytes_in, bytes_out }
+    }
+
+    fn rate(outcome: SampleOutcome) -> (f32, f32) {
+        match outcome {
+            SampleOutcome::Rate(point) => (point.rate_in, point.rate_out),
+            other => panic!("expected a rate, got {:?}", other),
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn first_reading_has_no_rate() {
+        let start = Instant::now();
+        let mut meter = RateMeter::default();
+        assert!(matches!(meter.update(sample(start, 0.0, 1000, 500), 0.0), SampleOutcome::First));
+        assert_eq!(rate(meter.update(sample(start, 1.0, 3000, 1500), 0.0)), (2000.0, 1000.0));
+    }
+
+    #[test]
+    fn late_reply_is_stale_not_reset() {
+        let start = Instant::now();
+        let mut meter = RateMeter::default();
+        meter.update(sample(start, 0.0, 0, 0), 0.0);
+        meter.update(sample(start, 2.0, 4000, 2000), 0.0);
+        // Asked for at 1s, answered after the 2s reading: its counters are lower
+        assert!(matches!(meter.update(sample(start, 1.0, 2000, 1000), 0.0), SampleOutcome::Stale));
+        // ...and the meter still measures from the 2s reading
+        assert_eq!(rate(meter.update(sample(start, 3.0, 5000, 2500), 0.0)), (1000.0, 500.0));
+    }
+
+    #[test]
+    fn newer_lower_counters_reset() {
+        let start = Instant::now();
+        let mut meter = RateMeter::default();
+        meter.update(sample(start, 0.0, 0, 0), 5.0);
+        meter.update(sample(start, 1.0, 10_000, 10_000), 5.0);
+        assert!(matches!(meter.update(sample(start, 2.0, 100, 100), 5.0), SampleOutcome::Reset));
+        // The next rate starts from the reset counters, without the old average
+        assert_eq!(rate(meter.update(sample(start, 3.0, 600, 300), 5.0)), (500.0, 200.0));
+    }
+
+    #[test]
+    fn irregular_intervals_use_real_time() {
+        let start = Instant::now();
+        let mut meter = RateMeter::default();
+        meter.update(sample(start, 0.0, 0, 0), 0.0);
+        // A missed tick: three seconds of traffic in one reading
+        let (rate_in, rate_out) = rate(meter.update(sample(start, 3.0, 3000, 600), 0.0));
+        assert!(close(rate_in, 1000.0) && close(rate_out, 200.0));
+        let (rate_in, _) = rate(meter.update(sample(start, 3.5, 3500, 600), 0.0));
+        assert!(close(rate_in, 1000.0));
+    }
+
+    #[test]
+    fn smoothing_weights_by_elapsed_time() {
+        let start = Instant::now();
+        let mut meter = RateMeter::default();
+        meter.update(sample(start, 0.0, 0, 0), 2.0);
+        // The first rate has nothing to average with
+        assert_eq!(rate(meter.update(sample(start, 1.0, 1000, 0), 2.0)).0, 1000.0);
+
+        // One second of 3000 B/s moves the average by 1 - e^(-1/2) of the difference
+        let (rate_in, _) = rate(meter.update(sample(start, 2.0, 4000, 0), 2.0));
+        let alpha = 1.0 - (-0.5f32).exp();
+        assert!(close(rate_in, 1000.0 + alpha * 2000.0), "{}", rate_in);
+
+        // Four seconds at the same rate count for more than one
+        let mut long = meter.clone();
+        let mut short = meter;
+        let (after_long, _) = rate(long.update(sample(start, 6.0, 4000, 0), 2.0));
+        let (after_short, _) = rate(short.update(sample(start, 3.0, 4000, 0), 2.0));
+        assert!(after_long < after_short && after_long < rate_in * 0.2, "{} {}", after_long, after_short);
+    }
+
+    #[test]
+    fn reset_forgets_the_session() {
+        let start = Instant::now();
+        let mut meter = RateMeter::default();
+        meter.update(sample(start, 0.0, 0, 0), 0.0);
+        meter.reset();
+        assert!(matches!(meter.update(sample(start, 1.0, 10, 10), 0.0), SampleOutcome::First));
+    }
+
+    fn point(start: Instant, secs: f32, rate_in: f32) -> RatePoint {
+        RatePoint { at: start + Duration::from_secs_f32(secs), rate_in, rate_out: rate_in / 2.0 }
+    }
+
+    #[test]
+    fn tier_buckets_by_width() {
+        let start = Instant::now();
+        let mut tier = Tier::new(Duration::from_secs(10));
+        for (secs, rate) in [(0.0, 100.0), (4.0, 200.0), (9.5, 300.0), (10.0, 1000.0), (35.0, 50.0)] {
+            tier.add(point(start, secs, rate));
+        }
+        let rates: Vec<f32> = tier.buckets.iter().map(|b| b.point().rate_in).collect();
+        assert_eq!(rates, [200.0, 1000.0, 50.0]);
+        let counts: Vec<u32> = tier.buckets.iter().map(|b| b.count).collect();
+        assert_eq!(counts, [3, 1, 1]);
+    }
+
+    #[test]
+    fn coarsen_merges_neighbours() {
+        let start = Instant::now();
+        let mut tier = Tier::new(Duration::from_secs(10));
+        for (secs, rate) in [(0.0, 100.0), (10.0, 300.0), (20.0, 500.0), (30.0, 700.0), (60.0, 900.0)] {
+            tier.add(point(start, secs, rate));
+        }
+        tier.coarsen();
+        assert_eq!(tier.width, Duration::from_secs(20));
+        let rates: Vec<f32> = tier.buckets.iter().map(|b| b.point().rate_in).collect();
+        // A lone bucket after a gap stays on its own
+        assert_eq!(rates, [200.0, 600.0, 900.0]);
+        // Averages weigh every point, not every bucket
+        tier.add(point(start, 65.0, 300.0));
+        tier.coarsen();
+        let rates: Vec<f32> = tier.buckets.iter().map(|b| b.point().rate_in).collect();
+        assert_eq!(rates, [400.0, 600.0]);
+    }
+
+    #[test]
+    fn session_window_stays_bounded() {
+        let start = Instant::now();
+        let mut history = RateHistory::default();
+        history.start_session(start);
+        let secs = SESSION_BUCKETS as u64 * HOUR_BUCKET.as_secs() * 3;
+        for s in 0..secs {
+            history.push(point(start, s as f32, 1000.0));
+        }
+        assert!(history.session.buckets.len() <= SESSION_BUCKETS);
+        assert_eq!(history.session.width, HOUR_BUCKET * 4);
+        // The hour window keeps its resolution and drops what is older
+        assert_eq!(history.hour.width, HOUR_BUCKET);
+        assert!(history.hour.buckets.len() as u64 <= HOUR_SPAN.as_secs() / HOUR_BUCKET.as_secs() + 1);
+        let now = start + Duration::from_secs(secs);
+        assert!(history.raw.iter().all(|p| now.duration_since(p.at) <= RAW_SPAN + Duration::from_secs(1)));
+
+        // Each window is drawn from its own resolution
+        let session = history.series(now, None);
+        assert_eq!(session.points.len(), history.session.buckets.len());
+        assert!(close(session.max_gap_secs, (HOUR_BUCKET * 4).as_secs_f32() + GAP_SECS));
+        let minute = history.series(now, Some(Duration::from_secs(60)));
+        assert!(minute.points.len() <= 61 && minute.points.iter().all(|p| p.secs_ago <= 60.0));
+        assert!(close(minute.max_gap_secs, GAP_SECS));
+    }
+
+    #[test]
+    fn new_session_starts_its_window_over() {
+        let start = Instant::now();
+        let mut history = RateHistory::default();
+        history.start_session(start);
+        history.push(point(start, 1.0, 100.0));
+        let later = start + Duration::from_secs(100);
+        history.start_session(later);
+        assert!(history.series(later, None).points.is_empty());
+        assert_eq!(history.series(later, Some(RAW_SPAN)).points.len(), 1);
+        assert!(close(history.session_secs(later + Duration::from_secs(5)).unwrap(), 5.0));
+    }
+
+    #[test]
+    fn runs_split_at_missing_readings() {
+        let series = RateSeries {
+            points: [10.0, 9.0, 8.0, 3.0, 2.0]
+                .into_iter()
+                .map(|secs_ago| RelativePoint { secs_ago, rate_in: 0.0, rate_out: 0.0 })
+                .collect(),
+            max_gap_secs: GAP_SECS,
+        };
+        let runs: Vec<usize> = series.runs().map(<[RelativePoint]>::len).collect();
+        assert_eq!(runs, [3, 2]);
+    }
+}
@@ -0,0 +1,174 @@
+// Session log streaming - `openvpn3 log` events for the session being monitored
+
+use cosmic::iced::futures::{SinkExt, Stream};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use super::process::spawn;
+
+/// `openvpn3 log --log-level` values offered in the UI (0 = errors only, 6 = debug)
+pub const LOG_LEVELS: [u8; 7] = [0, 1, 2, 3, 4, 5, 6];
+
+/// One log event from the openvpn3 backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionLogLine {
+    /// Log group (CLIENT, SESSION, BACKENDSTART, ...) if the line names one
+    pub group: Option<String>,
+    /// Log category, i.e. the level (INFO, WARNING, ERROR, DEBUG, ...)
+    pub category: Option<String>,
+    pub message: String,
+}
+
+impl SessionLogLine {
+    /// Category shown next to the source in the log pane, e.g. "CLIENT:INFO"
+    pub fn tag(&self) -> Option<String> {
+        match (&self.group, &self.category) {
+            (Some(group), Some(category)) => Some(format!("{}:{}", group, category)),
+            (Some(tag), None) | (None, Some(tag)) => Some(tag.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Parse a line of `openvpn3 log` output:
+/// `Mon Oct 14 12:00:00 2024 [CLIENT:INFO] Connecting to ...` (timestamp and tag optional)
+pub fn parse_log_line(line: &str) -> Option<SessionLogLine> {
+    let mut rest = line.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    // Drop the timestamp ("Tue Oct  1 ..." pads the day); the log pane adds its own
+    let words: Vec<&str> = rest.split_whitespace().take(6).collect();
+    if words.len() == 6
+        && words[0].len() == 3
+        && words[3].matches(':').count() == 2
+        && words[4].chars().all(|c| c.is_ascii_digit())
+    {
+        for word in &words[..5] {
+            rest = rest.trim_start().strip_prefix(word).unwrap_or(rest);
+        }
+        rest = rest.trim_start();
+    }
+
+    let (mut group, mut category) = (None, None);
+    if let Some(tagged) = rest.strip_prefix('[') {
+        if let Some((tag, message)) = tagged.split_once(']') {
+            match tag.split_once(':') {
+                Some((g, c)) => {
+                    group = Some(g.trim().to_string());
+                    category = Some(c.trim().to_string());
+                }
+                None => category = Some(tag.trim().to_string()),
+            }
+            rest = message.trim_start();
+        }
+    }
+
+    Some(SessionLogLine {
+        group,
+        category,
+        message: rest.to_string(),
+    })
+}
+
+/// Follow the session's log until it ends; restarts `openvpn3 log` if it exits early
+pub fn stream_session_log(session_path: String, level: u8) -> impl Stream<Item = SessionLogLine> {
+    cosmic::iced::stream::channel(64, move |mut output| async move {
+        let level = level.to_string();
+        loop {
+            let child = spawn(
+                "openvpn3",
+                &["log", "--session-path", &session_path, "--log-level", &level],
+                false,
+            );
+            if let Ok(mut child) = child {
+                // Drain stderr alongside stdout: a full pipe would block the child,
+                // and what it reports there (e.g. an unknown session) belongs in the log
+                if let Some(stderr) = child.stderr.take() {
+                    let mut output = output.clone();
+                    tokio::spawn(async move {
+                        let mut lines = BufReader::new(stderr).lines();
+                        while let Ok(Some(line)) = lines.next_line().await {
+                            if let Some(entry) = parse_log_line(&line) {
+                                let _ = output.send(entry).await;
+                            }
+                        }
+                    });
+                }
+                if let Some(stdout) = child.stdout.take() {
+                    let mut lines = BufReader::new(stdout).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if let Some(entry) = parse_log_line(&line) {
+                            let _ = output.send(entry).await;
+                        }
+                    }
+                }
+                let _ = child.wait().await;
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(group: Option<&str>, category: Option<&str>, message: &str) -> Option<SessionLogLine> {
+        Some(SessionLogLine {
+            group: group.map(str::to_string),
+            category: category.map(str::to_string),
+            message: message.to_string(),
+        })
+    }
+
+    #[test]
+    fn timestamp_and_tag_are_split_off() {
+        assert_eq!(
+            parse_log_line("Mon Oct 14 12:00:00 2024 [CLIENT:INFO] Connecting to [vpn.example.com]:1194 (203.0.113.5) via UDPv4"),
+            line(Some("CLIENT"), Some("INFO"), "Connecting to [vpn.example.com]:1194 (203.0.113.5) via UDPv4")
+        );
+        // Single-digit days are padded with a second space
+        assert_eq!(
+            parse_log_line("Tue Oct  1 08:15:42 2024 [SESSION:ERROR] Authentication failed"),
+            line(Some("SESSION"), Some("ERROR"), "Authentication failed")
+        );
+    }
+
+    #[test]
+    fn tag_without_group() {
+        assert_eq!(
+            parse_log_line("Mon Oct 14 12:00:00 2024 [WARNING] Ignoring option 'comp-lzo'"),
+            line(None, Some("WARNING"), "Ignoring option 'comp-lzo'")
+        );
+    }
+
+    #[test]
+    fn untagged_lines_are_kept_whole() {
+        assert_eq!(
+            parse_log_line("Mon Oct 14 12:00:00 2024 Session path: /net/openvpn/v3/sessions/1a2b"),
+            line(None, None, "Session path: /net/openvpn/v3/sessions/1a2b")
+        );
+        // Text that only looks a bit like a timestamp stays part of the message
+        assert_eq!(
+            parse_log_line("Peer Connection Initiated with [AF_INET]203.0.113.5:1194"),
+            line(None, None, "Peer Connection Initiated with [AF_INET]203.0.113.5:1194")
+        );
+        // An unclosed bracket is not a tag
+        assert_eq!(parse_log_line("[CLIENT:INFO Connecting"), line(None, None, "[CLIENT:INFO Connecting"));
+    }
+
+    #[test]
+    fn blank_lines_are_skipped() {
+        assert_eq!(parse_log_line(""), None);
+        assert_eq!(parse_log_line("   \t"), None);
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(line(Some("CLIENT"), Some("INFO"), "").unwrap().tag().as_deref(), Some("CLIENT:INFO"));
+        assert_eq!(line(None, Some("WARNING"), "").unwrap().tag().as_deref(), Some("WARNING"));
+        assert_eq!(line(None, None, "").unwrap().tag(), None);
+    }
+}
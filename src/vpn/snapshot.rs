@@ -0,0 +1,75 @@
+// Network snapshot - session counters, routes and DNS captured for reports
+
+use serde::Serialize;
+use std::time::Duration;
+
+use super::parser::parse_counters;
+use super::process::run;
+
+/// Deadline for each command
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Counter {
+    pub name: String,
+    pub value: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NetworkSnapshot {
+    /// Every counter reported by `openvpn3 session-stats`
+    pub counters: Vec<Counter>,
+    /// IPv4 and IPv6 routing tables
+    pub routes: Vec<String>,
+    /// Resolver state from systemd-resolved, or resolv.conf without it
+    pub dns: Vec<String>,
+}
+
+/// Non-empty output lines of a command, or nothing if it fails
+async fn output_lines(program: &str, args: &[&str]) -> Vec<String> {
+    run(program, args, SNAPSHOT_TIMEOUT)
+        .await
+        .map(|out| {
+            out.stdout
+                .lines()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| l.trim_end().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Capture counters (when a session exists), routes and DNS
+pub async fn capture(session_path: Option<String>) -> NetworkSnapshot {
+    let counters = match &session_path {
+        Some(path) => run("openvpn3", &["session-stats", "--session-path", path], SNAPSHOT_TIMEOUT)
+            .await
+            .map(|out| {
+                parse_counters(&out.stdout)
+                    .into_iter()
+                    .map(|(name, value)| Counter { name, value })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    let mut routes = output_lines("ip", &["route", "show"]).await;
+    routes.extend(output_lines("ip", &["-6", "route", "show"]).await);
+
+    let mut dns = output_lines("resolvectl", &["status"]).await;
+    if dns.is_empty() {
+        dns = tokio::fs::read_to_string("/etc/resolv.conf")
+            .await
+            .map(|resolv| {
+                resolv
+                    .lines()
+                    .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+                    .map(|l| l.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+
+    NetworkSnapshot { counters, routes, dns }
+}
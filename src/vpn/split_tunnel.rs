@@ -0,0 +1,121 @@
+// Split tunnelling - route applications by cgroup through or around the tunnel
+
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use tokio::process::Command;
+
+use super::helper::run_helper;
+
+/// Which side of the tunnel an application is launched on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitSide {
+    Inside,
+    Outside,
+}
+
+impl SplitSide {
+    /// systemd user slice the application is started in (no dashes: they nest slices).
+    /// The helper marks traffic by these cgroups, so it uses the same names.
+    pub fn slice(&self) -> &'static str {
+        match self {
+            SplitSide::Inside => "openvpn3gui_inside.slice",
+            SplitSide::Outside => "openvpn3gui_outside.slice",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SplitSide::Inside => "inside the VPN",
+            SplitSide::Outside => "outside the VPN",
+        }
+    }
+}
+
+/// Per-profile split tunnel configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplitTunnelSettings {
+    pub enabled: bool,
+    /// Commands offered as one-click launchers
+    pub inside_apps: Vec<String>,
+    pub outside_apps: Vec<String>,
+}
+
+/// Create both user slices so their cgroups exist before rules reference them
+async fn ensure_slices() -> Result<(), String> {
+    let mut dir = dirs::config_dir().ok_or("No config directory")?;
+    dir.push("systemd/user");
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    for side in [SplitSide::Inside, SplitSide::Outside] {
+        let unit = format!(
+            "[Unit]\nDescription=Applications launched {} by OpenVPN3 GUI\n",
+            side.label()
+        );
+        tokio::fs::write(dir.join(side.slice()), unit)
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", side.slice(), e))?;
+    }
+
+    run_systemctl(&["--user", "daemon-reload"]).await?;
+    run_systemctl(&[
+        "--user",
+        "start",
+        SplitSide::Inside.slice(),
+        SplitSide::Outside.slice(),
+    ])
+    .await
+}
+
+async fn run_systemctl(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("systemctl")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to execute systemctl: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Install cgroup marking and policy routing for the given tunnel device. The helper
+/// builds the rules for the calling user's slices.
+pub async fn apply_split_tunnel(tunnel_device: String) -> Result<(), String> {
+    ensure_slices().await?;
+    run_helper(&["split-apply", &tunnel_device]).await.map(|_| ())
+}
+
+/// Remove marking and policy routing
+pub async fn remove_split_tunnel() -> Result<(), String> {
+    run_helper(&["split-remove"]).await.map(|_| ())
+}
+
+/// Run a command in the side's slice and wait for it to exit
+pub async fn launch_app(side: SplitSide, command: String) -> Result<String, String> {
+    let status = Command::new("systemd-run")
+        .args([
+            "--user",
+            "--scope",
+            "--quiet",
+            &format!("--slice={}", side.slice()),
+            "--",
+            "sh",
+            "-c",
+            &command,
+        ])
+        .stdin(Stdio::null())
+        .status()
+        .await
+        .map_err(|e| format!("Failed to execute systemd-run: {}", e))?;
+
+    if status.success() {
+        Ok(format!("'{}' ({}) exited", command, side.label()))
+    } else {
+        Err(format!("'{}' ({}) exited with {}", command, side.label(), status))
+    }
+}
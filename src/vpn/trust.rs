@@ -0,0 +1,416 @@
+// Trusted-network rules - connect or disconnect automatically based on where we are
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// What we know about the network we are attached to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkFacts {
+    pub interface: Option<String>,
+    pub gateway_mac: Option<String>,
+    pub ssid: Option<String>,
+    /// MAC of the Wi-Fi access point
+    pub bssid: Option<String>,
+    pub dns_domain: Option<String>,
+}
+
+impl NetworkFacts {
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(ssid) = &self.ssid {
+            parts.push(format!("SSID {}", ssid));
+        }
+        if let Some(bssid) = &self.bssid {
+            parts.push(format!("BSSID {}", bssid));
+        }
+        if let Some(iface) = &self.interface {
+            parts.push(format!("interface {}", iface));
+        }
+        if let Some(mac) = &self.gateway_mac {
+            parts.push(format!("gateway {}", mac));
+        }
+        if let Some(domain) = &self.dns_domain {
+            parts.push(format!("domain {}", domain));
+        }
+        if parts.is_empty() {
+            "no network".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    /// Untrusted network: bring the rule's profile up
+    Connect,
+    /// Trusted network: take any session down
+    Disconnect,
+}
+
+/// A rule matches when every condition it sets matches (unset conditions are ignored)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkRule {
+    pub name: String,
+    pub gateway_mac: Option<String>,
+    pub ssid: Option<String>,
+    pub bssid: Option<String>,
+    pub interface: Option<String>,
+    pub dns_domain: Option<String>,
+    pub action: RuleAction,
+    /// Config path of the profile to connect (Connect rules)
+    pub profile: Option<String>,
+}
+
+impl Default for NetworkRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            gateway_mac: None,
+            ssid: None,
+            bssid: None,
+            interface: None,
+            dns_domain: None,
+            action: RuleAction::Connect,
+            profile: None,
+        }
+    }
+}
+
+impl NetworkRule {
+    pub fn matches(&self, facts: &NetworkFacts) -> bool {
+        condition(&self.gateway_mac, &facts.gateway_mac, |want, got| want.eq_ignore_ascii_case(got))
+            && condition(&self.ssid, &facts.ssid, |want, got| want == got)
+            && condition(&self.bssid, &facts.bssid, |want, got| want.eq_ignore_ascii_case(got))
+            && condition(&self.interface, &facts.interface, |want, got| want == got)
+            && condition(&self.dns_domain, &facts.dns_domain, |want, got| {
+                // "example.com" also matches "corp.example.com"
+                let got = got.to_ascii_lowercase();
+                let want = want.to_ascii_lowercase();
+                got == want || got.ends_with(&format!(".{}", want))
+            })
+    }
+}
+
+fn condition(want: &Option<String>, got: &Option<String>, eq: impl Fn(&str, &str) -> bool) -> bool {
+    match (want, got) {
+        (None, _) => true,
+        (Some(want), Some(got)) => eq(want, got),
+        (Some(_), None) => false,
+    }
+}
+
+/// Outcome of evaluating the rules against the current facts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleDecision {
+    /// Name of the first matching rule (None = no rule matched, nothing to do)
+    pub rule: Option<String>,
+    pub action: Option<RuleAction>,
+    pub profile: Option<String>,
+}
+
+impl RuleDecision {
+    pub fn summary(&self) -> String {
+        match (&self.rule, self.action) {
+            (Some(rule), Some(RuleAction::Connect)) => {
+                let profile = self
+                    .profile
+                    .as_deref()
+                    .and_then(|p| std::path::Path::new(p).file_name())
+                    .map(|f| f.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "selected profile".to_string());
+                format!("{} → connect {}", rule, profile)
+            }
+            (Some(rule), Some(RuleAction::Disconnect)) => format!("{} → trusted, disconnect", rule),
+            _ => "no rule matched".to_string(),
+        }
+    }
+}
+
+/// First matching rule wins
+pub fn evaluate(rules: &[NetworkRule], facts: &NetworkFacts) -> RuleDecision {
+    match rules.iter().find(|r| r.matches(facts)) {
+        Some(rule) => RuleDecision {
+            rule: Some(if rule.name.is_empty() { "unnamed rule".to_string() } else { rule.name.clone() }),
+            action: Some(rule.action),
+            profile: rule.profile.clone(),
+        },
+        None => RuleDecision { rule: None, action: None, profile: None },
+    }
+}
+
+async fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().await.ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        None
+    }
+}
+
+/// `default via 192.168.1.1 dev wlp2s0 ...` -> (gateway, device), ignoring VPN routes
+pub fn parse_default_route(output: &str) -> Option<(String, String)> {
+    output.lines().find_map(|line| {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let via = words.iter().position(|w| *w == "via").and_then(|i| words.get(i + 1))?;
+        let dev = words.iter().position(|w| *w == "dev").and_then(|i| words.get(i + 1))?;
+        if dev.starts_with("tun") || dev.starts_with("tap") {
+            return None;
+        }
+        Some((via.to_string(), dev.to_string()))
+    })
+}
+
+/// `192.168.1.1 lladdr aa:bb:cc:dd:ee:ff REACHABLE` -> MAC
+pub fn parse_neighbour_mac(output: &str) -> Option<String> {
+    let words: Vec<&str> = output.split_whitespace().collect();
+    let idx = words.iter().position(|w| *w == "lladdr")?;
+    words.get(idx + 1).map(|m| m.to_ascii_lowercase())
+}
+
+/// busctl `ay 4 104 111 109 101` -> "home"
+pub fn parse_busctl_bytes(output: &str) -> Option<String> {
+    let mut words = output.split_whitespace();
+    if words.next()? != "ay" {
+        return None;
+    }
+    let len: usize = words.next()?.parse().ok()?;
+    let bytes: Vec<u8> = words.filter_map(|w| w.parse().ok()).take(len).collect();
+    if bytes.is_empty() {
+        None
+    } else {
+        Some(String::from_utf8_lossy(&bytes).to_string())
+    }
+}
+
+/// busctl `s "AA:BB:CC:DD:EE:FF"` -> the string
+fn parse_busctl_string(output: &str) -> Option<String> {
+    let value = output.trim().strip_prefix("s ")?.trim_matches('"');
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// busctl `o "/org/freedesktop/..."` -> object path ("/" means none)
+fn parse_busctl_object(output: &str) -> Option<String> {
+    let path = output.trim().strip_prefix("o ")?.trim_matches('"');
+    if path == "/" {
+        None
+    } else {
+        Some(path.to_string())
+    }
+}
+
+async fn nm_property(path: &str, interface: &str, property: &str) -> Option<String> {
+    run(
+        "busctl",
+        &["--system", "get-property", "org.freedesktop.NetworkManager", path, interface, property],
+    )
+    .await
+}
+
+/// SSID and BSSID of NetworkManager's primary connection, if it is Wi-Fi
+async fn primary_access_point() -> Option<(Option<String>, Option<String>)> {
+    let active = nm_property(
+        "/org/freedesktop/NetworkManager",
+        "org.freedesktop.NetworkManager",
+        "PrimaryConnection",
+    )
+    .await
+    .and_then(|o| parse_busctl_object(&o))?;
+    let access_point = nm_property(
+        &active,
+        "org.freedesktop.NetworkManager.Connection.Active",
+        "SpecificObject",
+    )
+    .await
+    .and_then(|o| parse_busctl_object(&o))?;
+    if !access_point.contains("/AccessPoint/") {
+        return None;
+    }
+    let ssid = nm_property(&access_point, "org.freedesktop.NetworkManager.AccessPoint", "Ssid")
+        .await
+        .and_then(|b| parse_busctl_bytes(&b));
+    let bssid = nm_property(&access_point, "org.freedesktop.NetworkManager.AccessPoint", "HwAddress")
+        .await
+        .and_then(|s| parse_busctl_string(&s));
+    Some((ssid, bssid))
+}
+
+/// Search domain of the uplink (systemd-resolved, falling back to resolv.conf)
+async fn dns_domain(interface: &str) -> Option<String> {
+    // "Link 3 (wlp2s0): corp.example.com"
+    let from_resolved = run("resolvectl", &["domain", interface])
+        .await
+        .and_then(|out| {
+            out.split_once("):")
+                .and_then(|(_, domains)| domains.split_whitespace().next().map(|d| d.trim_start_matches('~').to_string()))
+        })
+        .filter(|d| !d.is_empty() && d != ".");
+    if from_resolved.is_some() {
+        return from_resolved;
+    }
+    let resolv = tokio::fs::read_to_string("/etc/resolv.conf").await.ok()?;
+    resolv.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("search") | Some("domain") => words.next().map(|d| d.to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// Collect the facts the rules match against
+pub async fn gather_facts() -> NetworkFacts {
+    let mut facts = NetworkFacts::default();
+    let Some((gateway, device)) = run("ip", &["route", "show", "default"])
+        .await
+        .and_then(|out| parse_default_route(&out))
+    else {
+        return facts;
+    };
+
+    facts.gateway_mac = run("ip", &["neigh", "show", &gateway, "dev", &device])
+        .await
+        .and_then(|out| parse_neighbour_mac(&out));
+    if let Some((ssid, bssid)) = primary_access_point().await {
+        facts.ssid = ssid;
+        facts.bssid = bssid;
+    }
+    facts.dns_domain = dns_domain(&device).await;
+    facts.interface = Some(device);
+    facts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn office_wifi() -> NetworkFacts {
+        NetworkFacts {
+            interface: Some("wlp2s0".to_string()),
+            gateway_mac: Some("aa:bb:cc:dd:ee:ff".to_string()),
+            ssid: Some("Office".to_string()),
+            bssid: Some("12:34:56:78:9a:bc".to_string()),
+            dns_domain: Some("corp.example.com".to_string()),
+        }
+    }
+
+    fn rule(name: &str, action: RuleAction) -> NetworkRule {
+        NetworkRule { name: name.to_string(), action, ..Default::default() }
+    }
+
+    #[test]
+    fn conditions_match_the_facts() {
+        let facts = office_wifi();
+        let matching = [
+            NetworkRule { gateway_mac: Some("AA:BB:CC:DD:EE:FF".to_string()), ..Default::default() },
+            NetworkRule { ssid: Some("Office".to_string()), ..Default::default() },
+            NetworkRule { bssid: Some("12:34:56:78:9A:BC".to_string()), ..Default::default() },
+            NetworkRule { interface: Some("wlp2s0".to_string()), ..Default::default() },
+            NetworkRule { dns_domain: Some("corp.example.com".to_string()), ..Default::default() },
+            NetworkRule { dns_domain: Some("Example.com".to_string()), ..Default::default() },
+        ];
+        for rule in &matching {
+            assert!(rule.matches(&facts), "{:?}", rule);
+        }
+        let other = [
+            NetworkRule { gateway_mac: Some("aa:bb:cc:dd:ee:00".to_string()), ..Default::default() },
+            // SSIDs are case sensitive
+            NetworkRule { ssid: Some("office".to_string()), ..Default::default() },
+            NetworkRule { bssid: Some("12:34:56:78:9a:bd".to_string()), ..Default::default() },
+            NetworkRule { interface: Some("enp0s31f6".to_string()), ..Default::default() },
+            NetworkRule { dns_domain: Some("ample.com".to_string()), ..Default::default() },
+        ];
+        for rule in &other {
+            assert!(!rule.matches(&facts), "{:?}", rule);
+        }
+    }
+
+    #[test]
+    fn every_set_condition_must_match() {
+        let facts = office_wifi();
+        let mut rule = NetworkRule {
+            ssid: Some("Office".to_string()),
+            gateway_mac: Some("aa:bb:cc:dd:ee:ff".to_string()),
+            ..Default::default()
+        };
+        assert!(rule.matches(&facts));
+        rule.interface = Some("enp0s31f6".to_string());
+        assert!(!rule.matches(&facts));
+
+        // A condition on a fact we do not have never matches (e.g. SSID on a wired link)
+        let wired = NetworkFacts { ssid: None, bssid: None, ..office_wifi() };
+        assert!(!NetworkRule { ssid: Some("Office".to_string()), ..Default::default() }.matches(&wired));
+        // A rule without conditions matches anywhere
+        assert!(NetworkRule::default().matches(&NetworkFacts::default()));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = [
+            NetworkRule { ssid: Some("Cafe".to_string()), ..rule("Cafe", RuleAction::Connect) },
+            NetworkRule { ssid: Some("Office".to_string()), ..rule("Office", RuleAction::Disconnect) },
+            NetworkRule { profile: Some("/home/me/vpn/work.ovpn".to_string()), ..rule("Anywhere else", RuleAction::Connect) },
+        ];
+        let decision = evaluate(&rules, &office_wifi());
+        assert_eq!(decision.rule.as_deref(), Some("Office"));
+        assert_eq!(decision.action, Some(RuleAction::Disconnect));
+        assert_eq!(decision.summary(), "Office → trusted, disconnect");
+
+        let home = NetworkFacts { ssid: Some("Home".to_string()), ..office_wifi() };
+        let decision = evaluate(&rules, &home);
+        assert_eq!(decision.rule.as_deref(), Some("Anywhere else"));
+        assert_eq!(decision.profile.as_deref(), Some("/home/me/vpn/work.ovpn"));
+        assert_eq!(decision.summary(), "Anywhere else → connect work.ovpn");
+
+        let decision = evaluate(&rules[..2], &home);
+        assert_eq!(decision, RuleDecision { rule: None, action: None, profile: None });
+        assert_eq!(decision.summary(), "no rule matched");
+        assert_eq!(evaluate(&[rule("", RuleAction::Connect)], &home).rule.as_deref(), Some("unnamed rule"));
+    }
+
+    #[test]
+    fn default_route_skips_the_tunnel() {
+        let output = "default dev tun0 scope link\ndefault via 192.168.1.1 dev wlp2s0 proto dhcp src 192.168.1.23 metric 600\n";
+        assert_eq!(
+            parse_default_route(output),
+            Some(("192.168.1.1".to_string(), "wlp2s0".to_string()))
+        );
+        assert_eq!(parse_default_route("default via 10.8.0.1 dev tun0\n"), None);
+        assert_eq!(parse_default_route(""), None);
+    }
+
+    #[test]
+    fn neighbour_mac() {
+        assert_eq!(
+            parse_neighbour_mac("192.168.1.1 lladdr AA:BB:CC:DD:EE:FF REACHABLE\n").as_deref(),
+            Some("aa:bb:cc:dd:ee:ff")
+        );
+        assert_eq!(parse_neighbour_mac("192.168.1.1  FAILED\n"), None);
+        assert_eq!(parse_neighbour_mac(""), None);
+    }
+
+    #[test]
+    fn busctl_values() {
+        assert_eq!(parse_busctl_bytes("ay 4 104 111 109 101\n").as_deref(), Some("home"));
+        // UTF-8 SSIDs arrive as their bytes
+        assert_eq!(parse_busctl_bytes("ay 5 67 97 102 195 169").as_deref(), Some("Café"));
+        // Only the announced length is taken
+        assert_eq!(parse_busctl_bytes("ay 2 104 105 33").as_deref(), Some("hi"));
+        assert_eq!(parse_busctl_bytes("ay 0"), None);
+        assert_eq!(parse_busctl_bytes("s \"home\""), None);
+
+        assert_eq!(parse_busctl_string("s \"12:34:56:78:9A:BC\"\n").as_deref(), Some("12:34:56:78:9A:BC"));
+        assert_eq!(parse_busctl_string("s \"\""), None);
+        assert_eq!(
+            parse_busctl_object("o \"/org/freedesktop/NetworkManager/AccessPoint/7\"\n").as_deref(),
+            Some("/org/freedesktop/NetworkManager/AccessPoint/7")
+        );
+        assert_eq!(parse_busctl_object("o \"/\""), None);
+    }
+}
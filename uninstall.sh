@@ -24,6 +24,12 @@ for size in 16 24 32 48 64 128 256; do
     fi
 done
 
+# Remove privileged helper and polkit policy
+if [ -f /usr/libexec/openvpn3-gui-helper ] || [ -f /usr/share/polkit-1/actions/xyz.fonzi.openvpn3gui.policy ]; then
+    echo "Removing privileged helper (requires sudo)..."
+    sudo rm -f /usr/libexec/openvpn3-gui-helper /usr/share/polkit-1/actions/xyz.fonzi.openvpn3gui.policy || true
+fi
+
 # Update desktop database
 update-desktop-database "$HOME/.local/share/applications" 2>/dev/null || true
 